@@ -0,0 +1,78 @@
+//! Rendering benchmarks, run with `cargo bench`. criterion compares each
+//! case with the last run and reports whether it got faster or slower.
+
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use mdman::{RoffOptions, parse_page, render_page};
+
+fn benches(c: &mut Criterion) {
+    let options = RoffOptions::default();
+    for (name, markdown) in [
+        ("prose", prose_page()),
+        ("mixed", mixed_page()),
+        ("reference", reference_page()),
+    ] {
+        c.bench_function(&format!("parse {}", name), |b| {
+            b.iter(|| parse_page(black_box(&markdown), &None).unwrap())
+        });
+        let (nodes, _) = parse_page(&markdown, &None).unwrap();
+        c.bench_function(&format!("render {}", name), |b| {
+            b.iter(|| render_page(black_box(&nodes), &options))
+        });
+    }
+}
+
+criterion_group!(render, benches);
+criterion_main!(render);
+
+/// A long page of nothing but plain paragraphs, the common case.
+fn prose_page() -> String {
+    let mut page = String::from("---\nname: bench\nsection: 1\ndate: 2025-01-01\n---\n\n");
+    for section in 0..20 {
+        page.push_str(&format!("# SECTION {}\n\n", section));
+        for _ in 0..20 {
+            page.push_str(
+                "This paragraph is plain text with the odd -flag, a file.name and\n\
+                 a few \"quotes\" in it, wrapped over several lines the way\n\
+                 pages are usually written.\n\n",
+            );
+        }
+    }
+    page
+}
+
+/// A generated API reference, big enough to be rendered in parallel.
+fn reference_page() -> String {
+    let mut page = String::from("---\nname: bench\nsection: 3\ndate: 2025-01-01\n---\n\n");
+    for function in 0..500 {
+        page.push_str(&format!("# bench_fn_{}\n\n", function));
+        for _ in 0..6 {
+            page.push_str(
+                "Takes a *path* and returns `-1` on error, see [errno](https://example.com).\n\n",
+            );
+        }
+    }
+    page
+}
+
+/// Options, fonts, links, code and tables.
+fn mixed_page() -> String {
+    let mut page = String::from("---\nname: bench\nsection: 1\ndate: 2025-01-01\n---\n\n");
+    page.push_str("# OPTIONS\n\n---\n\n");
+    for i in 0..100 {
+        page.push_str(&format!(
+            "- **-o{}**, **--option-{}**=*VALUE*\n  Set *VALUE*, see [the docs](https://example.com/{}).\n\n",
+            i, i, i
+        ));
+    }
+    page.push_str("---\n\n# EXAMPLES\n\n");
+    for _ in 0..50 {
+        page.push_str("Run `bench --option-1=x`:\n\n```\nbench -o1 x | less\n```\n\n");
+    }
+    page.push_str("| a | b |\n|---|--:|\n");
+    for i in 0..100 {
+        page.push_str(&format!("| **{}** | `{}` |\n", i, i));
+    }
+    page
+}
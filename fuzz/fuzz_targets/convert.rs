@@ -0,0 +1,20 @@
+//! Markdown through the whole conversion: parsing, the man node tree and
+//! roff output. Malformed input must come back as an error, never a panic.
+//!
+//! Run with `cargo +nightly fuzz run convert` from the repository root.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mdman::{ToRoff, parse_page};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(markdown) = std::str::from_utf8(data) else {
+        return;
+    };
+    if let Ok((nodes, _)) = parse_page(markdown, &None) {
+        for node in &nodes {
+            node.to_roff();
+        }
+    }
+});
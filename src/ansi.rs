@@ -0,0 +1,601 @@
+use jiff::Zoned;
+
+use crate::man_node::{ManNode, TableAlign, title_line};
+use crate::render::Renderer;
+use crate::unicode::display_width;
+
+const BOLD: &str = "\x1b[1m";
+const UNDERLINE: &str = "\x1b[4m";
+const STRIKE: &str = "\x1b[9m";
+const RESET: &str = "\x1b[0m";
+/// Stands in for a hard break in the text of pieces until they are filled.
+const LINE_BREAK: char = '\u{2028}';
+
+/// Where body text starts, like the default indentation of `man`.
+const INDENT: usize = 7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+struct Style {
+    bold: bool,
+    underline: bool,
+    strike: bool,
+}
+
+impl Style {
+    const PLAIN: Style = Style {
+        bold: false,
+        underline: false,
+        strike: false,
+    };
+}
+
+/// Text in one style; words may be made of several of them, as in `**-o**,`.
+#[derive(Debug, Clone)]
+struct Piece {
+    text: String,
+    style: Style,
+}
+
+/// Format a page for the terminal the way `man` would, with bold and
+/// underlined text as ANSI escape sequences, filled to `width` columns. This
+/// needs no roff formatter, so previews work where none is installed.
+pub fn ansi_page(nodes: &[ManNode], width: usize) -> String {
+    let mut page = Page {
+        out: String::new(),
+        width: width.max(INDENT + 20),
+        after_heading: false,
+    };
+    let title = title_line(nodes);
+    if let Some(title) = title {
+        let name = format!("{}({})", title.name.to_uppercase(), title.section);
+        let center = title.center_footer.as_deref().unwrap_or("");
+        page.out.push_str(&page.columns(&name, center, &name));
+        page.out.push('\n');
+    }
+    for node in nodes {
+        page.node(node, INDENT);
+    }
+    if let Some(title) = title {
+        let name = format!("{}({})", title.name.to_uppercase(), title.section);
+        let date = title
+            .date
+            .clone()
+            .unwrap_or_else(|| Zoned::now().strftime("%Y-%m-%d").to_string());
+        let left = title.left_footer.as_deref().unwrap_or("");
+        page.out.push('\n');
+        page.out.push_str(&page.columns(left, &date, &name));
+        page.out.push('\n');
+    }
+    page.out
+}
+
+struct Page {
+    out: String,
+    width: usize,
+    /// Blocks directly after a heading (or list item marker) get no blank line.
+    after_heading: bool,
+}
+
+impl Page {
+    fn separate(&mut self) {
+        if !self.out.is_empty() && !self.after_heading {
+            self.out.push('\n');
+        }
+        self.after_heading = false;
+    }
+
+    fn node(&mut self, node: &ManNode, indent: usize) {
+        match node {
+            ManNode::TitleLine(_) => {}
+            ManNode::SectionHeading { title, .. } => {
+                self.separate();
+                self.out.push_str(&format!("{}{}{}\n", BOLD, title, RESET));
+                self.after_heading = true;
+            }
+            ManNode::SubsectionHeading { title, .. } => {
+                self.separate();
+                self.out
+                    .push_str(&format!("   {}{}{}\n", BOLD, title, RESET));
+                self.after_heading = true;
+            }
+            ManNode::MinorHeading { title, .. } => {
+                self.separate();
+                let indent = " ".repeat(INDENT);
+                self.out
+                    .push_str(&format!("{}{}{}{}\n", indent, BOLD, title, RESET));
+                self.after_heading = true;
+            }
+            ManNode::Paragraph { children } => {
+                self.separate();
+                self.fill(&pieces(children), &" ".repeat(indent), indent);
+            }
+            // A diagram needs pic, which is not available here.
+            ManNode::Pic(_) => {}
+            // Nor is a formatter for raw roff.
+            ManNode::RawBlock(_) => {}
+            // Without eqn, the source of an equation is the best there is.
+            ManNode::CodeBlock { code: text, .. }
+            | ManNode::NoFill(text)
+            | ManNode::Equation(text) => {
+                self.separate();
+                for line in text.lines() {
+                    self.out
+                        .push_str(format!("{}{}", " ".repeat(indent), line).trim_end());
+                    self.out.push('\n');
+                }
+            }
+            ManNode::BulletList { children } => {
+                self.separate();
+                for item in children {
+                    let (marker, width) = match item.checked() {
+                        Some(true) => ("[x] ", 6),
+                        Some(false) => ("[ ] ", 6),
+                        None => ("• ", 4),
+                    };
+                    let marker = format!("{}{}", " ".repeat(indent + 2), marker);
+                    self.item(item, &marker, indent + width);
+                }
+            }
+            ManNode::NumberedList { children } => {
+                self.separate();
+                for (i, item) in children.iter().enumerate() {
+                    let marker = format!("{}{:<4}", " ".repeat(indent + 2), format!("{}.", i + 1));
+                    self.item(item, &marker, indent + 6);
+                }
+            }
+            ManNode::DefinitionList { children } => {
+                for item in children {
+                    self.separate();
+                    let all = pieces(item.children());
+                    let (term, description) = split_term(&all);
+                    self.fill(&term, &" ".repeat(indent), indent);
+                    if !description.is_empty() {
+                        self.fill(&description, &" ".repeat(indent + 7), indent + 7);
+                    }
+                }
+            }
+            ManNode::Table {
+                align, children, ..
+            } => {
+                self.separate();
+                self.table(align, children, indent);
+            }
+            ManNode::Blockquote { children } => {
+                for child in children {
+                    self.node(child, indent + 4);
+                }
+            }
+            inline => {
+                self.separate();
+                let children = std::slice::from_ref(inline);
+                self.fill(&pieces(children), &" ".repeat(indent), indent);
+            }
+        }
+    }
+
+    /// A list item: `first` holds the indentation and the marker. Blocks in
+    /// the item line up with its text.
+    fn item(&mut self, item: &ManNode, first: &str, indent: usize) {
+        let mut inlines = Vec::new();
+        let mut first = Some(first.to_string());
+        let flush = |page: &mut Page, inlines: &mut Vec<&ManNode>, first: &mut Option<String>| {
+            if inlines.is_empty() {
+                return;
+            }
+            let prefix = first.take().unwrap_or_else(|| " ".repeat(indent));
+            page.fill(&pieces(inlines.drain(..)), &prefix, indent);
+        };
+        for child in item.children() {
+            if is_block(child) {
+                flush(self, &mut inlines, &mut first);
+                if let Some(marker) = first.take() {
+                    self.out.push_str(marker.trim_end());
+                    self.out.push('\n');
+                }
+                self.after_heading = true;
+                self.node(child, indent);
+            } else {
+                inlines.push(child);
+            }
+        }
+        flush(self, &mut inlines, &mut first);
+    }
+
+    /// Fill `pieces` into lines of at most `width` columns. The first line
+    /// starts with `first`, the others are indented by `indent`.
+    fn fill(&mut self, pieces: &[Piece], first: &str, indent: usize) {
+        let mut line = first.to_string();
+        let mut column = display_width(first);
+        let mut empty = true;
+        for word in words(pieces) {
+            if word.is_empty() {
+                self.out.push_str(line.trim_end());
+                self.out.push('\n');
+                line = " ".repeat(indent);
+                column = indent;
+                empty = true;
+                continue;
+            }
+            let word_width = word.iter().map(|p| display_width(&p.text)).sum::<usize>();
+            if !empty && column + 1 + word_width > self.width {
+                self.out.push_str(&line);
+                self.out.push('\n');
+                line = " ".repeat(indent);
+                column = indent;
+                empty = true;
+            }
+            if !empty {
+                line.push(' ');
+                column += 1;
+            }
+            for piece in &word {
+                line.push_str(&styled(&piece.text, piece.style));
+            }
+            column += word_width;
+            empty = false;
+        }
+        self.out.push_str(line.trim_end());
+        self.out.push('\n');
+    }
+
+    fn table(&mut self, align: &[TableAlign], rows: &[ManNode], indent: usize) {
+        let cells = rows
+            .iter()
+            .map(|row| {
+                row.children()
+                    .iter()
+                    .map(|cell| {
+                        // Cells stay on one line, hard breaks or not.
+                        let words = words(&pieces(cell.children()))
+                            .into_iter()
+                            .filter(|w| !w.is_empty())
+                            .collect::<Vec<_>>();
+                        let text = words
+                            .iter()
+                            .map(|w| w.iter().map(|p| styled(&p.text, p.style)).collect())
+                            .collect::<Vec<String>>()
+                            .join(" ");
+                        let width = words
+                            .iter()
+                            .map(|w| w.iter().map(|p| display_width(&p.text)).sum::<usize>())
+                            .sum::<usize>()
+                            + words.len().saturating_sub(1);
+                        (text, width)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let mut widths = vec![0; align.len()];
+        for row in &cells {
+            for (i, (_, width)) in row.iter().enumerate() {
+                if i < widths.len() {
+                    widths[i] = widths[i].max(*width);
+                }
+            }
+        }
+        for (r, row) in cells.iter().enumerate() {
+            let mut line = " ".repeat(indent);
+            for (i, (text, width)) in row.iter().enumerate() {
+                let column = widths.get(i).copied().unwrap_or(*width);
+                let pad = column - width;
+                let (left, right) = match align.get(i) {
+                    Some(TableAlign::Right) => (pad, 0),
+                    Some(TableAlign::Center) => (pad / 2, pad - pad / 2),
+                    _ => (0, pad),
+                };
+                if i > 0 {
+                    line.push_str("  ");
+                }
+                line.push_str(&" ".repeat(left));
+                line.push_str(text);
+                line.push_str(&" ".repeat(right));
+            }
+            self.out.push_str(line.trim_end());
+            self.out.push('\n');
+            if r == 0 {
+                let rule = widths.iter().sum::<usize>() + 2 * widths.len().saturating_sub(1);
+                self.out
+                    .push_str(&format!("{}{}\n", " ".repeat(indent), "-".repeat(rule)));
+            }
+        }
+    }
+
+    /// `left` and `right` at the margins and `center` in the middle.
+    fn columns(&self, left: &str, center: &str, right: &str) -> String {
+        let (l, c, r) = (
+            display_width(left),
+            display_width(center),
+            display_width(right),
+        );
+        let start = (self.width.saturating_sub(c) / 2).max(l + 1);
+        let end = self.width.saturating_sub(r).max(start + c + 1);
+        format!(
+            "{}{}{}{}{}",
+            left,
+            " ".repeat(start - l),
+            center,
+            " ".repeat(end - start - c),
+            right
+        )
+    }
+}
+
+fn is_block(node: &ManNode) -> bool {
+    matches!(
+        node,
+        ManNode::Paragraph { .. }
+            | ManNode::CodeBlock { .. }
+            | ManNode::NoFill(_)
+            | ManNode::Pic(_)
+            | ManNode::Equation(_)
+            | ManNode::RawBlock(_)
+            | ManNode::BulletList { .. }
+            | ManNode::NumberedList { .. }
+            | ManNode::DefinitionList { .. }
+            | ManNode::Table { .. }
+            | ManNode::Blockquote { .. }
+    )
+}
+
+fn pieces<'a>(children: impl IntoIterator<Item = &'a ManNode>) -> Vec<Piece> {
+    styled_pieces(children, Style::PLAIN)
+}
+
+/// The text of inline nodes, in `style` plus their own.
+fn styled_pieces<'a>(children: impl IntoIterator<Item = &'a ManNode>, style: Style) -> Vec<Piece> {
+    let mut out = Vec::new();
+    for child in children {
+        let text = match child {
+            ManNode::Text(text) | ManNode::InlineCode(text) => text.clone(),
+            ManNode::SoftBreak => " ".into(),
+            ManNode::LineBreak => LINE_BREAK.into(),
+            ManNode::Bold(children) => {
+                out.extend(styled_pieces(
+                    children,
+                    Style {
+                        bold: true,
+                        ..style
+                    },
+                ));
+                continue;
+            }
+            ManNode::Italic(children) => {
+                out.extend(styled_pieces(
+                    children,
+                    Style {
+                        underline: true,
+                        ..style
+                    },
+                ));
+                continue;
+            }
+            ManNode::Strikethrough(children) => {
+                out.extend(styled_pieces(
+                    children,
+                    Style {
+                        strike: true,
+                        ..style
+                    },
+                ));
+                continue;
+            }
+            // Roff means nothing without a formatter.
+            ManNode::RawInline(_) => continue,
+            ManNode::ManRef { name, section } => {
+                out.push(Piece {
+                    text: name.clone(),
+                    style: Style {
+                        bold: true,
+                        ..style
+                    },
+                });
+                format!("({})", section)
+            }
+            ManNode::Uri { url, children, .. } => {
+                let text = styled_pieces(children, style);
+                let plain = text.iter().map(|p| p.text.as_str()).collect::<String>();
+                let url = url.strip_prefix("mailto:").unwrap_or(url);
+                let autolink = plain == url || url.strip_prefix("http://") == Some(&plain);
+                if !autolink {
+                    out.extend(text);
+                    out.push(Piece {
+                        text: " ".into(),
+                        style,
+                    });
+                }
+                out.push(Piece {
+                    text: format!("<{}>", url),
+                    style: Style {
+                        underline: true,
+                        ..style
+                    },
+                });
+                continue;
+            }
+            other => {
+                out.extend(styled_pieces(other.children(), style));
+                continue;
+            }
+        };
+        out.push(Piece { text, style });
+    }
+    out
+}
+
+/// Split styled text at whitespace into words. A hard break comes out as an
+/// empty word.
+fn words(pieces: &[Piece]) -> Vec<Vec<Piece>> {
+    let mut words = Vec::new();
+    let mut word: Vec<Piece> = Vec::new();
+    for piece in pieces {
+        let mut current = String::new();
+        for c in piece.text.chars() {
+            if c == LINE_BREAK {
+                if !current.is_empty() {
+                    word.push(Piece {
+                        text: std::mem::take(&mut current),
+                        style: piece.style,
+                    });
+                }
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+                words.push(Vec::new());
+            } else if c.is_whitespace() {
+                if !current.is_empty() {
+                    word.push(Piece {
+                        text: std::mem::take(&mut current),
+                        style: piece.style,
+                    });
+                }
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            } else {
+                current.push(c);
+            }
+        }
+        if !current.is_empty() {
+            word.push(Piece {
+                text: current,
+                style: piece.style,
+            });
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// The term of a definition list item ends at its first line break.
+fn split_term(pieces: &[Piece]) -> (Vec<Piece>, Vec<Piece>) {
+    for (i, piece) in pieces.iter().enumerate() {
+        if let Some((before, after)) = piece.text.split_once('\n') {
+            let mut term = pieces[..i].to_vec();
+            term.push(Piece {
+                text: before.into(),
+                style: piece.style,
+            });
+            let mut description = vec![Piece {
+                text: after.into(),
+                style: piece.style,
+            }];
+            description.extend_from_slice(&pieces[i + 1..]);
+            return (term, description);
+        }
+    }
+    (pieces.to_vec(), vec![])
+}
+
+fn styled(text: &str, style: Style) -> String {
+    let mut codes = String::new();
+    for (on, code) in [
+        (style.bold, BOLD),
+        (style.underline, UNDERLINE),
+        (style.strike, STRIKE),
+    ] {
+        if on {
+            codes.push_str(code);
+        }
+    }
+    if codes.is_empty() {
+        text.to_string()
+    } else {
+        format!("{}{}{}", codes, text, RESET)
+    }
+}
+
+/// Text formatted by [`ansi_page`] without its styles.
+pub fn strip_styles(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("\x1b[") {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        rest = match rest.find('m') {
+            Some(end) => &rest[end + 1..],
+            None => "",
+        };
+    }
+    out.push_str(rest);
+    out
+}
+
+/// [`ansi_page`] as a [`Renderer`].
+pub struct AnsiRenderer {
+    pub width: usize,
+}
+
+impl AnsiRenderer {
+    /// Fill lines to the width of the terminal as reported by the shell in
+    /// `COLUMNS`, 80 columns otherwise.
+    pub fn for_terminal() -> Self {
+        let width = std::env::var("COLUMNS")
+            .ok()
+            .and_then(|c| c.parse().ok())
+            .unwrap_or(80);
+        Self { width }
+    }
+}
+
+impl Renderer for AnsiRenderer {
+    fn render(&self, nodes: &[ManNode]) -> String {
+        ansi_page(nodes, self.width)
+    }
+
+    fn extension(&self, _nodes: &[ManNode]) -> String {
+        "txt".into()
+    }
+
+    fn for_terminal(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_page;
+
+    #[test]
+    fn test_ansi_page() {
+        let markdown = "---\nname: tool\nsection: 1\ndate: 2025-01-01\n---\n\n# NAME\n\n\
+                        **tool** - do _many_ things with a rather long description\n\n\
+                        # OPTIONS\n\n---\n\n- **-v**\n  Be verbose.\n\n---\n\n\
+                        # EXAMPLES\n\n- Run it:\n\n  ```\n  tool -v\n  ```\n";
+        let (nodes, _) = parse_page(markdown, &None).unwrap();
+        assert_eq!(
+            ansi_page(&nodes, 40),
+            "TOOL(1)                          TOOL(1)\n\n\
+             \x1b[1mNAME\x1b[0m\n       \x1b[1mtool\x1b[0m - do \x1b[4mmany\x1b[0m things with a\n       \
+             rather long description\n\n\
+             \x1b[1mOPTIONS\x1b[0m\n       \x1b[1m-v\x1b[0m\n              Be verbose.\n\n\
+             \x1b[1mEXAMPLES\x1b[0m\n         • Run it:\n           tool -v\n\n\
+             \x20              2025-01-01        TOOL(1)\n"
+        );
+    }
+
+    #[test]
+    fn test_table() {
+        let markdown = "| a | long |\n|--:|---|\n| 100 | b |\n";
+        let (nodes, _) = parse_page(markdown, &None).unwrap();
+        assert_eq!(
+            ansi_page(&nodes, 80),
+            "         a  long\n       ---------\n       100  b\n"
+        );
+    }
+
+    #[test]
+    fn test_line_break() {
+        let (nodes, _) = parse_page("# SYNOPSIS\n\ntool FILE\\\ntool --help\n", &None).unwrap();
+        assert_eq!(
+            ansi_page(&nodes, 80),
+            "\x1b[1mSYNOPSIS\x1b[0m\n       tool FILE\n       tool --help\n"
+        );
+        assert_eq!(
+            strip_styles(&ansi_page(&nodes, 80)),
+            "SYNOPSIS\n       tool FILE\n       tool --help\n"
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! Converting many pages at once on several threads. What a page prints goes
+//! through [`eprint`] and [`print`], which hold it back while the page is
+//! converted on a worker thread, so that the output of every run reads as if
+//! the pages had been converted one after the other.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::io::{Write, stderr, stdout};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+/// What a page printed while it was converted.
+#[derive(Debug, Default)]
+pub struct Captured {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+thread_local! {
+    static CAPTURED: RefCell<Option<Captured>> = const { RefCell::new(None) };
+}
+
+/// Print a line to stderr, or hold it back for the page being converted.
+pub fn eprint(args: fmt::Arguments) {
+    let line = format!("{}\n", args);
+    write_or_capture(line.as_bytes(), true);
+}
+
+/// Print a line to stdout, or hold it back for the page being converted.
+pub fn print(args: fmt::Arguments) {
+    let line = format!("{}\n", args);
+    write_or_capture(line.as_bytes(), false);
+}
+
+/// Write `bytes` to stdout as they are, or hold them back.
+pub fn write_stdout(bytes: &[u8]) {
+    write_or_capture(bytes, false);
+}
+
+fn write_or_capture(bytes: &[u8], to_stderr: bool) {
+    CAPTURED.with_borrow_mut(|captured| match captured {
+        Some(captured) if to_stderr => captured.stderr.extend_from_slice(bytes),
+        Some(captured) => captured.stdout.extend_from_slice(bytes),
+        None if to_stderr => _ = stderr().write_all(bytes),
+        None => _ = stdout().write_all(bytes),
+    });
+}
+
+/// Run `f` with what it prints through [`eprint`] and [`print`] held back,
+/// and return that along with its result.
+pub fn capture<R>(f: impl FnOnce() -> R) -> (R, Captured) {
+    let outer = CAPTURED.replace(Some(Captured::default()));
+    let result = f();
+    let captured = CAPTURED.replace(outer).unwrap_or_default();
+    (result, captured)
+}
+
+/// The number of threads to use by default: one per CPU.
+pub fn default_jobs() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// `f` applied to each of `items` on up to `jobs` threads, in the order of
+/// `items`. What `f` prints for an item is printed once the items before it
+/// are done, all of it together.
+pub fn map<T: Sync, R: Send>(items: &[T], jobs: usize, f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let jobs = jobs.min(items.len());
+    if jobs <= 1 {
+        return items.iter().map(f).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new((0..items.len()).map(|_| None).collect::<Vec<_>>());
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            let sender = sender.clone();
+            let (next, results, f) = (&next, &results, &f);
+            scope.spawn(move || {
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(i) else {
+                        break;
+                    };
+                    let (result, captured) = capture(|| f(item));
+                    results.lock().unwrap()[i] = Some(result);
+                    _ = sender.send((i, captured));
+                }
+            });
+        }
+        drop(sender);
+
+        // Print each item's output as soon as everything before it is out.
+        let mut waiting = (0..items.len()).map(|_| None).collect::<Vec<_>>();
+        let mut printed = 0;
+        for (i, captured) in receiver {
+            waiting[i] = Some(captured);
+            while let Some(captured) = waiting.get_mut(printed).and_then(Option::take) {
+                _ = stdout().write_all(&captured.stdout);
+                _ = stderr().write_all(&captured.stderr);
+                printed += 1;
+            }
+        }
+    });
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.expect("every item is done when the threads are"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map() {
+        let items = (0..50).collect::<Vec<u64>>();
+        let squares = map(&items, 4, |&n| {
+            thread::sleep(std::time::Duration::from_micros((50 - n) * 20));
+            n * n
+        });
+        assert_eq!(squares, items.iter().map(|n| n * n).collect::<Vec<_>>());
+        assert_eq!(map(&items[..1], 4, |&n| n + 1), [1]);
+        assert!(map(&[] as &[u64], 4, |&n| n).is_empty());
+    }
+}
@@ -0,0 +1,138 @@
+use clap::ValueEnum;
+
+use mdman::flags::Flag;
+
+/// Shells `export-completions` can write scripts for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// A completion script for `command` offering the documented flags.
+pub fn completion_script(shell: Shell, command: &str, flags: &[Flag]) -> String {
+    match shell {
+        Shell::Bash => bash(command, flags),
+        Shell::Zsh => zsh(command, flags),
+        Shell::Fish => fish(command, flags),
+    }
+}
+
+fn bash(command: &str, flags: &[Flag]) -> String {
+    let words = flags
+        .iter()
+        .flat_map(|f| f.short.iter().chain(&f.long))
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let function = format!("_{}", command.replace(|c: char| !c.is_alphanumeric(), "_"));
+    format!(
+        "{function}() {{\n    local cur=\"${{COMP_WORDS[COMP_CWORD]}}\"\n    \
+         COMPREPLY=($(compgen -W \"{words}\" -- \"$cur\"))\n}}\n\
+         complete -o default -F {function} {command}\n"
+    )
+}
+
+fn zsh(command: &str, flags: &[Flag]) -> String {
+    let mut script = format!("#compdef {}\n\n_arguments \\\n", command);
+    for flag in flags {
+        let description = flag
+            .summary()
+            .replace('\'', "'\\''")
+            .replace('[', "\\[")
+            .replace(']', "\\]");
+        let arg = flag
+            .arg
+            .as_ref()
+            .map(|a| format!(":{}:", a.to_lowercase().replace(':', "\\:")))
+            .unwrap_or_default();
+        let names = flag
+            .short
+            .iter()
+            .chain(&flag.long)
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        let spec = match names.as_slice() {
+            [one] => format!("'{}[{}]{}'", one, description, arg),
+            _ => format!(
+                "'({})'{{{}}}'[{}]{}'",
+                names.join(" "),
+                names.join(","),
+                description,
+                arg
+            ),
+        };
+        script.push_str(&format!("  {} \\\n", spec));
+    }
+    script.push_str("  '*:file:_files'\n");
+    script
+}
+
+fn fish(command: &str, flags: &[Flag]) -> String {
+    let mut script = String::new();
+    for flag in flags {
+        script.push_str(&format!("complete -c {}", command));
+        if let Some(short) = &flag.short {
+            script.push_str(&format!(" -s {}", short.trim_start_matches('-')));
+        }
+        if let Some(long) = &flag.long {
+            script.push_str(&format!(" -l {}", long.trim_start_matches('-')));
+        }
+        if flag.arg.is_some() {
+            script.push_str(" -r");
+        }
+        let description = flag.summary();
+        if !description.is_empty() {
+            script.push_str(&format!(" -d '{}'", description.replace('\'', "\\'")));
+        }
+        script.push('\n');
+    }
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flags() -> Vec<Flag> {
+        vec![
+            Flag {
+                short: Some("-o".into()),
+                long: Some("--output".into()),
+                arg: Some("FILE".into()),
+                description: "Write to FILE. More text.".into(),
+            },
+            Flag {
+                short: None,
+                long: Some("--verbose".into()),
+                arg: None,
+                description: "Don't be quiet.".into(),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_fish() {
+        assert_eq!(
+            completion_script(Shell::Fish, "tool", &flags()),
+            "complete -c tool -s o -l output -r -d 'Write to FILE'\n\
+             complete -c tool -l verbose -d 'Don\\'t be quiet'\n"
+        );
+    }
+
+    #[test]
+    fn test_zsh() {
+        let script = completion_script(Shell::Zsh, "tool", &flags());
+        assert!(script.starts_with("#compdef tool\n"));
+        assert!(script.contains("  '(-o --output)'{-o,--output}'[Write to FILE]:file:' \\\n"));
+        assert!(script.contains("  '--verbose[Don'\\''t be quiet]' \\\n"));
+    }
+
+    #[test]
+    fn test_bash() {
+        let script = completion_script(Shell::Bash, "my-tool", &flags());
+        assert!(script.contains("compgen -W \"-o --output --verbose\""));
+        assert!(script.ends_with("complete -o default -F _my_tool my-tool\n"));
+    }
+}
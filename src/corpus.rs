@@ -0,0 +1,136 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A Markdown file of a regression corpus and the roff it should produce.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Case {
+    pub markdown: PathBuf,
+    /// The sibling `NAME.expected.N`, if there is one.
+    pub expected: Option<PathBuf>,
+}
+
+/// Find every `.md` file under `dir`, sorted by path.
+pub fn find_cases(dir: &Path) -> io::Result<Vec<Case>> {
+    markdown_files(dir)?
+        .into_iter()
+        .map(|md| {
+            let expected = expected_file(&md)?;
+            Ok(Case {
+                markdown: md,
+                expected,
+            })
+        })
+        .collect()
+}
+
+/// Every `.md` file under `dir`, sorted by path.
+pub fn markdown_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut markdown = Vec::new();
+    collect_markdown(dir, &mut markdown)?;
+    markdown.sort();
+    Ok(markdown)
+}
+
+fn collect_markdown(dir: &Path, found: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_markdown(&path, found)?;
+        } else if path.extension().is_some_and(|ext| ext == "md") {
+            found.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// `dir/foo.md` is compared against `dir/foo.expected.N`, whatever the section.
+fn expected_file(markdown: &Path) -> io::Result<Option<PathBuf>> {
+    let Some(stem) = markdown.file_stem() else {
+        return Ok(None);
+    };
+    let prefix = format!("{}.expected.", stem.to_string_lossy());
+    let dir = markdown.parent().unwrap_or(Path::new("."));
+    let mut candidates = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with(&prefix))
+        })
+        .collect::<Vec<_>>();
+    candidates.sort();
+    Ok(candidates.into_iter().next())
+}
+
+/// Line diff between the expected and the actual output, with `-` for lines
+/// only in `expected` and `+` for lines only in `actual`. Returns an empty
+/// string if both are equal.
+pub fn diff(expected: &str, actual: &str) -> String {
+    let old = expected.lines().collect::<Vec<_>>();
+    let new = actual.lines().collect::<Vec<_>>();
+
+    // Longest common subsequence table, filled from the end.
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            out.push_str(&format!("{:>5} -{}\n", i + 1, old[i]));
+            i += 1;
+        } else {
+            out.push_str(&format!("{:>5} +{}\n", j + 1, new[j]));
+            j += 1;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff() {
+        assert_eq!(diff("a\nb\nc\n", "a\nb\nc\n"), "");
+        assert_eq!(diff("a\nb\nc\n", "a\nx\nc\n"), "    2 -b\n    2 +x\n");
+    }
+
+    #[test]
+    fn test_find_cases() {
+        let dir = std::env::temp_dir().join(format!("mdman-corpus-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("b.md"), "").unwrap();
+        fs::write(dir.join("b.expected.5"), "").unwrap();
+        fs::write(dir.join("sub/a.md"), "").unwrap();
+
+        let cases = find_cases(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            cases,
+            [
+                Case {
+                    markdown: dir.join("b.md"),
+                    expected: Some(dir.join("b.expected.5")),
+                },
+                Case {
+                    markdown: dir.join("sub/a.md"),
+                    expected: None,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,207 @@
+//! `mdman diff`: what changed between two versions of a page, section by
+//! section, so that reviewers see the changes readers will see.
+
+use mdman::man_node::{ManNode, title_line};
+
+/// Unchanged lines shown around a change.
+const CONTEXT: usize = 3;
+
+/// A section of a page as lines of output. The nodes before the first
+/// section heading have the empty title.
+#[derive(Debug)]
+pub struct Section {
+    pub title: String,
+    pub lines: Vec<String>,
+}
+
+/// Split a page into its sections and render each with `render`. The title
+/// line becomes a section of its own, `TITLE`, with its fields.
+pub fn sections(nodes: &[ManNode], render: impl Fn(&[ManNode]) -> String) -> Vec<Section> {
+    let mut sections = Vec::new();
+    if let Some(title) = title_line(nodes) {
+        let fields = [
+            Some(format!("{}({})", title.name, title.section)),
+            title.date.clone(),
+            title.version.clone(),
+            title.left_footer.clone(),
+            title.center_footer.clone(),
+        ];
+        sections.push(Section {
+            title: "TITLE".into(),
+            lines: vec![fields.into_iter().flatten().collect::<Vec<_>>().join(", ")],
+        });
+    }
+    let body = nodes
+        .iter()
+        .position(|n| !matches!(n, ManNode::TitleLine(_)))
+        .map_or(&[][..], |start| &nodes[start..]);
+    let mut start = 0;
+    while start < body.len() {
+        let (title, first) = match &body[start] {
+            ManNode::SectionHeading { title, .. } => (title.clone(), start + 1),
+            _ => (String::new(), start),
+        };
+        let end = body[first..]
+            .iter()
+            .position(|n| matches!(n, ManNode::SectionHeading { .. }))
+            .map_or(body.len(), |offset| first + offset);
+        let text = render(&body[first..end]);
+        sections.push(Section {
+            title,
+            lines: text.lines().map(|l| l.trim_end().to_string()).collect(),
+        });
+        start = end;
+    }
+    sections
+}
+
+/// The changed sections of `new` compared to `old`, each under a
+/// `@@ TITLE @@` line with its changed lines marked `-` and `+` between some
+/// unchanged ones. Sections are matched by title; those only in one page are
+/// shown as added or removed. Empty if nothing changed.
+pub fn diff(old: &[Section], new: &[Section]) -> String {
+    let find = |sections: &[Section], title: &str| {
+        sections
+            .iter()
+            .position(|s| s.title.eq_ignore_ascii_case(title))
+    };
+    let heading = |section: &Section| match section.title.as_str() {
+        "" => "(before the first section)".to_string(),
+        title => title.to_string(),
+    };
+    let mut out = String::new();
+    for section in new {
+        let heading = heading(section);
+        match find(old, &section.title) {
+            Some(i) => {
+                let hunks = line_diff(&old[i].lines, &section.lines);
+                if !hunks.is_empty() {
+                    out.push_str(&format!("@@ {} @@\n{}", heading, hunks));
+                }
+            }
+            None => {
+                out.push_str(&format!("@@ {} (added) @@\n", heading));
+                section
+                    .lines
+                    .iter()
+                    .for_each(|l| push_line(&mut out, '+', l));
+            }
+        }
+    }
+    for section in old.iter().filter(|s| find(new, &s.title).is_none()) {
+        out.push_str(&format!("@@ {} (removed) @@\n", heading(section)));
+        section
+            .lines
+            .iter()
+            .for_each(|l| push_line(&mut out, '-', l));
+    }
+    out
+}
+
+fn push_line(out: &mut String, mark: char, line: &str) {
+    out.push(mark);
+    out.push_str(line);
+    out.push('\n');
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Same,
+    Removed,
+    Added,
+}
+
+/// The changes from `old` to `new` with [`CONTEXT`] unchanged lines around
+/// each, and `...` where unchanged lines are left out.
+fn line_diff(old: &[String], new: &[String]) -> String {
+    // lcs[i][j]: length of the longest common subsequence of old[i..] and
+    // new[j..].
+    let mut lcs = vec![vec![0u32; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut ops = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            ops.push((Op::Same, &old[i]));
+            (i, j) = (i + 1, j + 1);
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push((Op::Removed, &old[i]));
+            i += 1;
+        } else {
+            ops.push((Op::Added, &new[j]));
+            j += 1;
+        }
+    }
+    if ops.iter().all(|(op, _)| *op == Op::Same) {
+        return String::new();
+    }
+
+    let near_change = |k: usize| {
+        let from = k.saturating_sub(CONTEXT);
+        let to = (k + CONTEXT + 1).min(ops.len());
+        ops[from..to].iter().any(|(op, _)| *op != Op::Same)
+    };
+    let mut out = String::new();
+    let mut skipped = false;
+    for (k, (op, line)) in ops.iter().enumerate() {
+        if *op == Op::Same && !near_change(k) {
+            skipped = true;
+            continue;
+        }
+        if skipped && !out.is_empty() {
+            out.push_str("...\n");
+        }
+        skipped = false;
+        let mark = match op {
+            Op::Same => ' ',
+            Op::Removed => '-',
+            Op::Added => '+',
+        };
+        push_line(&mut out, mark, line);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mdman::parse_page;
+
+    #[test]
+    fn test_diff() {
+        let page = |markdown: &str| {
+            let nodes = parse_page(markdown, &None).unwrap().0;
+            sections(&nodes, |nodes| {
+                nodes
+                    .iter()
+                    .map(ManNode::plain_text)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+        };
+        let old = page(
+            "---\nname: tool\nsection: 1\nversion: 1.0\n---\n\n# NAME\n\ntool - x\n\n\
+             # OPTIONS\n\na\n\nb\n\nc\n\nd\n\ne\n\ne2\n\ne3\n\nf\n\ng\n\nh\n\n# BUGS\n\nMany.\n",
+        );
+        let new = page(
+            "---\nname: tool\nsection: 1\nversion: 1.1\n---\n\n# NAME\n\ntool - x\n\n\
+             # OPTIONS\n\na\n\nB\n\nc\n\nd\n\ne\n\ne2\n\ne3\n\nf\n\ng\n\nh\n\ni\n\n# EXAMPLES\n\ntool\n",
+        );
+        assert_eq!(
+            diff(&old, &new),
+            "@@ TITLE @@\n-tool(1), 1.0\n+tool(1), 1.1\n\
+             @@ OPTIONS @@\n a\n-b\n+B\n c\n d\n e\n...\n f\n g\n h\n+i\n\
+             @@ EXAMPLES (added) @@\n+tool\n\
+             @@ BUGS (removed) @@\n-Many.\n"
+        );
+        assert_eq!(diff(&old, &old), "");
+    }
+}
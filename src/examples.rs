@@ -0,0 +1,167 @@
+//! `mdman extract-examples`: the commands shown in the EXAMPLES section, as
+//! a shell script with one test per code block, so that a project can check
+//! that what its manual tells readers to run still works.
+
+use mdman::man_node::{ManNode, section_range};
+
+/// Code blocks of console sessions: commands after a `$ ` prompt, the rest
+/// is their output.
+const CONSOLE: [&str; 3] = ["console", "shell-session", "shellsession"];
+
+/// Code blocks that are shell commands, line by line.
+const SHELL: [&str; 4] = ["sh", "shell", "bash", "zsh"];
+
+/// The runnable code blocks of an EXAMPLES section.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Example {
+    /// The text of the paragraph or list item before the block.
+    pub description: String,
+    pub commands: Vec<String>,
+}
+
+/// The `console` and `sh` blocks of the EXAMPLES section, in order. Blocks
+/// of other languages, and without one, are left out.
+pub fn examples(nodes: &[ManNode]) -> Vec<Example> {
+    let Some(range) = section_range(nodes, "EXAMPLES") else {
+        return vec![];
+    };
+    let mut examples = Vec::new();
+    let mut description = String::new();
+    for node in &nodes[range] {
+        node.walk(&mut |n| match n {
+            ManNode::Paragraph { .. } => description = n.plain_text(),
+            ManNode::ListItem { children, .. } => {
+                description = children
+                    .iter()
+                    .take_while(|c| !matches!(c, ManNode::CodeBlock { .. }))
+                    .map(ManNode::plain_text)
+                    .collect();
+            }
+            ManNode::CodeBlock {
+                lang: Some(lang),
+                code,
+            } => {
+                let console = CONSOLE.contains(&lang.as_str());
+                if !console && !SHELL.contains(&lang.as_str()) {
+                    return;
+                }
+                let commands = commands(code, console);
+                if !commands.is_empty() {
+                    let text = description.split_whitespace().collect::<Vec<_>>();
+                    examples.push(Example {
+                        description: text.join(" ").trim_end_matches(':').to_string(),
+                        commands,
+                    });
+                }
+                description.clear();
+            }
+            _ => {}
+        });
+    }
+    examples
+}
+
+/// The commands of a code block. Shell blocks that show `$ ` prompts are
+/// read as console sessions. Lines ending in `\` go on on the next line.
+fn commands(code: &str, console: bool) -> Vec<String> {
+    let console = console || code.lines().any(|l| l.trim_start().starts_with("$ "));
+    let mut commands = Vec::new();
+    let mut continued = false;
+    for line in code.lines() {
+        let command = match line.trim_start().strip_prefix("$ ") {
+            _ if continued => Some(line),
+            Some(command) => Some(command),
+            None if console => None,
+            None => Some(line),
+        };
+        continued = false;
+        let Some(command) = command else {
+            continue;
+        };
+        continued = command.ends_with('\\');
+        if !command.trim().is_empty() {
+            commands.push(command.to_string());
+        }
+    }
+    commands
+}
+
+/// A POSIX shell script that runs each example in a subshell that stops at
+/// the first failing command, and reports it in the Test Anything Protocol:
+/// `ok N - description` or `not ok N - description` on stdout, the output of
+/// the commands on stderr. It exits with 1 if any example failed.
+pub fn shell_script(page: &str, examples: &[Example]) -> String {
+    let mut script = format!(
+        "#!/bin/sh\n\
+         # The examples of {}, one test per code block.\n\
+         # Generated by mdman extract-examples.\n\
+         \n\
+         failed=0\n\
+         echo 1..{}\n",
+        page,
+        examples.len()
+    );
+    for (i, example) in examples.iter().enumerate() {
+        let name = match example.description.as_str() {
+            "" => format!("{}", i + 1),
+            text => format!("{} - {}", i + 1, text),
+        };
+        // Not in the condition of the `if`, where `set -e` does nothing.
+        script.push_str(&format!("\n# {}\n(\n    set -e\n", name));
+        // Not indented, which would break the end of here-documents.
+        for command in &example.commands {
+            script.push_str(&format!("{}\n", command));
+        }
+        script.push_str(&format!(
+            ") >&2\nif [ $? -eq 0 ]; then\n    echo {}\nelse\n    echo {}\n    failed=$((failed + 1))\nfi\n",
+            quote(&format!("ok {}", name)),
+            quote(&format!("not ok {}", name))
+        ));
+    }
+    script.push_str("\n[ \"$failed\" -eq 0 ]\n");
+    script
+}
+
+/// `text` in single quotes for the shell.
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mdman::parse_page;
+
+    #[test]
+    fn test_examples() {
+        let markdown = "# EXAMPLES\n\n\
+                        List the files:\n\n```console\n$ tool ls \\\n    --all\na.txt\n$ tool ls -1\n```\n\n\
+                        - Don't do this:\n\n  ```sh\n  tool rm\n\n  tool gc\n  ```\n\n\
+                        ```toml\nkey = 1\n```\n\n```\n$ tool\n```\n\n\
+                        # SEE ALSO\n\n```sh\ntool\n```\n";
+        let nodes = parse_page(markdown, &None).unwrap().0;
+        let examples = examples(&nodes);
+        assert_eq!(
+            examples,
+            [
+                Example {
+                    description: "List the files".into(),
+                    commands: vec!["tool ls \\".into(), "    --all".into(), "tool ls -1".into()],
+                },
+                Example {
+                    description: "Don't do this".into(),
+                    commands: vec!["tool rm".into(), "tool gc".into()],
+                },
+            ]
+        );
+        assert_eq!(
+            shell_script("tool(1)", &examples[1..]),
+            "#!/bin/sh\n# The examples of tool(1), one test per code block.\n\
+             # Generated by mdman extract-examples.\n\nfailed=0\necho 1..1\n\n\
+             # 1 - Don't do this\n(\n    set -e\ntool rm\ntool gc\n) >&2\nif [ $? -eq 0 ]; then\n\
+             \x20   echo 'ok 1 - Don'\\''t do this'\nelse\n\
+             \x20   echo 'not ok 1 - Don'\\''t do this'\n    failed=$((failed + 1))\nfi\n\
+             \n[ \"$failed\" -eq 0 ]\n"
+        );
+    }
+}
@@ -0,0 +1,462 @@
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, Child, ChildStdin, Command, ExitStatus, Output, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+
+/// External programs mdman can hand its output to.
+pub const FORMATTERS: &[&str] = &["man", "mandoc", "groff", "less"];
+
+/// How long a formatter that runs without a terminal may take before it is
+/// killed.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Pages larger than this are handed to a pager in a temporary file instead
+/// of through a pipe.
+pub const PIPE_LIMIT: usize = 1 << 20;
+
+/// Look up an executable in `PATH`, like `which` does.
+pub fn find_executable(name: &str) -> Option<PathBuf> {
+    let path = env::var_os("PATH")?;
+    env::split_paths(&path)
+        .map(|dir| dir.join(name))
+        .find(|candidate| is_executable(candidate))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &std::path::Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &std::path::Path) -> bool {
+    path.is_file() || path.with_extension("exe").is_file()
+}
+
+#[derive(Debug)]
+pub enum PostprocessError {
+    EmptyCommand,
+    SpawnError(String, io::Error),
+    Failed(String, ExitStatus),
+    TimedOut(String, Duration),
+}
+
+impl fmt::Display for PostprocessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PostprocessError::EmptyCommand => write!(f, "mdman: Empty postprocess command"),
+            PostprocessError::SpawnError(command, e) => {
+                write!(f, "mdman: Could not run {}. Error: {}", command, e)
+            }
+            PostprocessError::Failed(command, status) => {
+                write!(f, "mdman: {} failed ({})", command, status)
+            }
+            PostprocessError::TimedOut(command, timeout) => {
+                write!(
+                    f,
+                    "mdman: {} did not finish within {}s and was killed",
+                    command,
+                    timeout.as_secs()
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for PostprocessError {}
+
+/// Split a command line into words like a shell would, minus expansions:
+/// words are separated by blanks, quotes group and backslashes escape.
+pub fn split_command(command: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') | (None, '\\') => {
+                word.extend(chars.next());
+                in_word = true;
+            }
+            (Some(_), c) => word.push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    words
+}
+
+/// Pipe `input` through `command` and return what it writes to stdout. Its
+/// stderr goes to ours. The command is killed if it runs longer than
+/// `timeout`. If the command is groff, `language` is loaded as a macro
+/// package (`-mde`) for its hyphenation patterns and strings.
+pub fn postprocess(
+    command: &str,
+    input: &[u8],
+    timeout: Option<Duration>,
+    language: Option<&str>,
+) -> Result<Vec<u8>, PostprocessError> {
+    let words = split_command(command);
+    let (program, args) = words.split_first().ok_or(PostprocessError::EmptyCommand)?;
+    let mut command = Command::new(program);
+    if let Some(language) = language
+        && Path::new(program)
+            .file_name()
+            .is_some_and(|name| name == "groff")
+    {
+        command.arg(format!("-m{}", language));
+    }
+    let output = run_filter(command.args(args).stdout(Stdio::piped()), input, timeout).map_err(
+        |e| match (e.kind(), timeout) {
+            (io::ErrorKind::TimedOut, Some(timeout)) => {
+                PostprocessError::TimedOut(program.clone(), timeout)
+            }
+            _ => PostprocessError::SpawnError(program.clone(), e),
+        },
+    )?;
+    if !output.status.success() {
+        return Err(PostprocessError::Failed(program.clone(), output.status));
+    }
+    Ok(output.stdout)
+}
+
+/// Run a non-interactive `command` with `input` on its stdin and collect
+/// whatever of stdout and stderr it was set up to pipe. A command that quits
+/// without reading all of its input is not an error. After `timeout` the
+/// command is killed and an error of kind [`io::ErrorKind::TimedOut`] is
+/// returned; either way the child is reaped before this returns.
+pub fn run_filter(
+    command: &mut Command,
+    input: &[u8],
+    timeout: Option<Duration>,
+) -> io::Result<Output> {
+    let mut child = command.stdin(Stdio::piped()).spawn()?;
+    // Write and read from other threads so a filter that fills its stdout
+    // pipe before reading all of its input cannot deadlock us. They are not
+    // scoped: after a timeout, a grandchild may still hold the pipes open and
+    // we do not wait for it.
+    let stdin = child.stdin.take().unwrap();
+    let input = input.to_vec();
+    let writer = thread::spawn(move || feed(stdin, &input));
+    let stdout = child.stdout.take().map(collect);
+    let stderr = child.stderr.take().map(collect);
+
+    let status = wait(&mut child, timeout)?;
+    writer.join().unwrap()?;
+    let join = |reader: Option<JoinHandle<io::Result<Vec<u8>>>>| match reader {
+        Some(reader) => reader.join().unwrap(),
+        None => Ok(Vec::new()),
+    };
+    Ok(Output {
+        status,
+        stdout: join(stdout)?,
+        stderr: join(stderr)?,
+    })
+}
+
+/// Run an interactive `command`, such as a pager, with `input` on its stdin
+/// and wait for it. Quitting before reading everything is fine.
+pub fn pipe_to(command: &mut Command, input: &[u8]) -> io::Result<ExitStatus> {
+    let mut child = command.stdin(Stdio::piped()).spawn()?;
+    let written = feed(child.stdin.take().unwrap(), input);
+    // Reap the child even if writing failed.
+    let status = child.wait()?;
+    written.map(|_| status)
+}
+
+/// Run a pager on `input`. `command` gets one more argument: `-` for stdin,
+/// or for inputs over [`PIPE_LIMIT`] a temporary file, which is removed when
+/// the pager exits.
+pub fn page(command: &mut Command, input: &[u8]) -> io::Result<ExitStatus> {
+    if input.len() <= PIPE_LIMIT {
+        return pipe_to(command.arg("-"), input);
+    }
+    let file = TempFile::new(input)?;
+    command.arg(file.path()).status()
+}
+
+/// A pager showing a page while we go on, e.g. to watch for changes to it.
+pub struct LivePager {
+    child: Child,
+    writer: JoinHandle<io::Result<()>>,
+}
+
+impl LivePager {
+    /// Start `command` with `input` on its stdin.
+    pub fn spawn(command: &mut Command, input: &[u8]) -> io::Result<LivePager> {
+        let mut child = command.stdin(Stdio::piped()).spawn()?;
+        let stdin = child.stdin.take().unwrap();
+        let input = input.to_vec();
+        let writer = thread::spawn(move || feed(stdin, &input));
+        Ok(LivePager { child, writer })
+    }
+
+    /// Whether the user has quit the pager.
+    pub fn has_quit(&mut self) -> bool {
+        !matches!(self.child.try_wait(), Ok(None))
+    }
+
+    /// Ask the pager to quit, so that it puts the terminal back the way it
+    /// was, and wait for it.
+    pub fn stop(mut self) {
+        terminate(&mut self.child);
+        _ = self.child.wait();
+        _ = self.writer.join();
+    }
+}
+
+#[cfg(unix)]
+fn terminate(child: &mut Child) {
+    const SIGTERM: i32 = 15;
+    unsafe extern "C" {
+        fn kill(pid: i32, sig: i32) -> i32;
+    }
+    // SAFETY: the child has not been reaped yet, so its pid is still ours.
+    if unsafe { kill(child.id() as i32, SIGTERM) } != 0 {
+        _ = child.kill();
+    }
+}
+
+#[cfg(not(unix))]
+fn terminate(child: &mut Child) {
+    _ = child.kill();
+}
+
+/// A file in the temporary directory that only we can read, removed on drop.
+pub struct TempFile {
+    path: PathBuf,
+}
+
+impl TempFile {
+    pub fn new(content: &[u8]) -> io::Result<TempFile> {
+        let mut options = fs::OpenOptions::new();
+        // Never open a file someone else put there, e.g. a symlink.
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+        let mut attempts = 0;
+        loop {
+            let nanos = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|d| d.subsec_nanos())
+                .unwrap_or_default();
+            let path = env::temp_dir().join(format!("mdman-{}-{}", process::id(), nanos));
+            match options.open(&path) {
+                Ok(mut file) => {
+                    let temp = TempFile { path };
+                    file.write_all(content)?;
+                    return Ok(temp);
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists && attempts < 100 => {
+                    attempts += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Run `f`, which waits for an interactive child, with Ctrl-C caught rather
+/// than fatal. The child shares our process group and gets the SIGINT as
+/// well; if it had killed us first, the child would be left behind with the
+/// terminal in whatever state it was in. Also returns whether there was an
+/// interrupt.
+///
+/// Signal handlers belong to the whole process, so this is for the `mdman`
+/// binary, not for library users converting pages on several threads.
+#[cfg(unix)]
+pub fn catch_interrupt<T>(f: impl FnOnce() -> T) -> (T, bool) {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    const SIGINT: i32 = 2;
+    static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+    unsafe extern "C" {
+        fn signal(signum: i32, handler: usize) -> usize;
+    }
+
+    // Unlike an ignored signal, a handler is reset to the default in the
+    // child on exec, so the child can still be interrupted.
+    extern "C" fn note_interrupt(_: i32) {
+        INTERRUPTED.store(true, Ordering::SeqCst);
+    }
+
+    INTERRUPTED.store(false, Ordering::SeqCst);
+    let handler: extern "C" fn(i32) = note_interrupt;
+    // SAFETY: the handler only stores to an atomic, and the previous one is
+    // put back before returning.
+    let previous = unsafe { signal(SIGINT, handler as usize) };
+    let result = f();
+    unsafe { signal(SIGINT, previous) };
+    (result, INTERRUPTED.load(Ordering::SeqCst))
+}
+
+#[cfg(not(unix))]
+pub fn catch_interrupt<T>(f: impl FnOnce() -> T) -> (T, bool) {
+    (f(), false)
+}
+
+/// Make the cursor visible again after a full-screen program died without
+/// cleaning up, if stdout is a terminal.
+pub fn restore_cursor() {
+    let mut stdout = io::stdout();
+    if io::IsTerminal::is_terminal(&stdout) {
+        _ = stdout.write_all(b"\x1b[?25h");
+        _ = stdout.flush();
+    }
+}
+
+/// Write `input` and close the pipe. A reader that went away early is not an
+/// error: Rust ignores SIGPIPE, so it shows up as `BrokenPipe`.
+fn feed(mut stdin: ChildStdin, input: &[u8]) -> io::Result<()> {
+    match stdin.write_all(input) {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => result,
+    }
+}
+
+fn collect<R: Read + Send + 'static>(mut pipe: R) -> JoinHandle<io::Result<Vec<u8>>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        pipe.read_to_end(&mut buf).map(|_| buf)
+    })
+}
+
+fn wait(child: &mut Child, timeout: Option<Duration>) -> io::Result<ExitStatus> {
+    let Some(timeout) = timeout else {
+        return child.wait();
+    };
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        if Instant::now() >= deadline {
+            _ = child.kill();
+            _ = child.wait();
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("no result after {} seconds", timeout.as_secs()),
+            ));
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_command() {
+        assert_eq!(
+            split_command(r#"groff -man -T utf8 -P "-c -b" 'a b'\ c "" x\"y"#),
+            [
+                "groff", "-man", "-T", "utf8", "-P", "-c -b", "a b c", "", "x\"y"
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_filter() {
+        let output = run_filter(
+            Command::new("head").arg("-c3").stdout(Stdio::piped()),
+            &vec![b'x'; 1 << 20],
+            Some(DEFAULT_TIMEOUT),
+        )
+        .unwrap();
+        assert_eq!(output.stdout, b"xxx");
+
+        let started = Instant::now();
+        let error = run_filter(
+            Command::new("sleep").arg("10"),
+            b"",
+            Some(Duration::from_millis(100)),
+        )
+        .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_postprocess_groff_language() {
+        // echo under the name of groff, to see the arguments it gets.
+        let dir = env::temp_dir().join(format!("mdman-test-groff-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let groff = dir.join("groff");
+        _ = fs::remove_file(&groff);
+        std::os::unix::fs::symlink("/bin/echo", &groff).unwrap();
+        let command = format!("{} -man -Tpdf", groff.display());
+
+        let with = postprocess(&command, b"", Some(DEFAULT_TIMEOUT), Some("de"));
+        let without = postprocess(&command, b"", Some(DEFAULT_TIMEOUT), None);
+        let other = postprocess("echo -man", b"", Some(DEFAULT_TIMEOUT), Some("de"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(with.unwrap(), b"-mde -man -Tpdf\n");
+        assert_eq!(without.unwrap(), b"-man -Tpdf\n");
+        assert_eq!(other.unwrap(), b"-man\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_catch_interrupt() {
+        unsafe extern "C" {
+            fn raise(signum: i32) -> i32;
+        }
+        // SIGINT would end the test binary if it were not caught.
+        let (result, interrupted) = catch_interrupt(|| unsafe { raise(2) });
+        assert_eq!((result, interrupted), (0, true));
+        assert_eq!(catch_interrupt(|| ()), ((), false));
+    }
+
+    #[test]
+    fn test_temp_file() {
+        let file = TempFile::new(b"page").unwrap();
+        let path = file.path().to_path_buf();
+        assert_eq!(fs::read(&path).unwrap(), b"page");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(path.metadata().unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        drop(file);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,132 @@
+use serde::Serialize;
+
+use crate::man_node::{ManNode, section_range};
+
+/// A command-line flag as documented in a page's OPTIONS section.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Flag {
+    /// E.g. `-o`.
+    pub short: Option<String>,
+    /// E.g. `--output`.
+    pub long: Option<String>,
+    /// Name of the flag's argument, e.g. `FILE`.
+    pub arg: Option<String>,
+    pub description: String,
+}
+
+impl Flag {
+    /// The first sentence of the description, without its full stop.
+    pub fn summary(&self) -> &str {
+        let first = match self.description.find(". ") {
+            Some(end) => &self.description[..end],
+            None => &self.description,
+        };
+        first.trim_end_matches('.')
+    }
+}
+
+/// Read the flags back out of the definition lists of the OPTIONS section,
+/// whether they were written in Markdown or generated from an options file.
+pub fn document_flags(nodes: &[ManNode]) -> Vec<Flag> {
+    let Some(range) = section_range(nodes, "OPTIONS") else {
+        return vec![];
+    };
+    let mut flags = Vec::new();
+    for node in &nodes[range] {
+        node.walk(&mut |n| {
+            if let ManNode::DefinitionList { children } = n {
+                flags.extend(children.iter().filter_map(parse_item));
+            }
+        });
+    }
+    flags
+}
+
+/// A definition list item is the term (`**-o**, **--output** _FILE_`), a line
+/// break and the description.
+fn parse_item(item: &ManNode) -> Option<Flag> {
+    let ManNode::ListItem { children, .. } = item else {
+        return None;
+    };
+    let text = children.iter().map(ManNode::plain_text).collect::<String>();
+    let (term, description) = text.split_once('\n').unwrap_or((&text, ""));
+
+    let mut flag = Flag {
+        short: None,
+        long: None,
+        arg: None,
+        description: description.split_whitespace().collect::<Vec<_>>().join(" "),
+    };
+    for alternative in term.split(',') {
+        let alternative = alternative.trim();
+        let (name, arg) = match alternative.find(['=', ' ', '[']) {
+            Some(end) => (&alternative[..end], Some(&alternative[end..])),
+            None => (alternative, None),
+        };
+        if name.starts_with("--") {
+            flag.long = Some(name.to_string());
+        } else if name.starts_with('-') && name.len() > 1 {
+            flag.short = Some(name.to_string());
+        } else {
+            continue;
+        }
+        if let Some(arg) = arg.map(|a| a.trim_matches(['=', ' ', '[', ']', '<', '>']))
+            && !arg.is_empty()
+        {
+            flag.arg = Some(arg.to_string());
+        }
+    }
+    (flag.short.is_some() || flag.long.is_some()).then_some(flag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(children: Vec<ManNode>) -> ManNode {
+        ManNode::list_item(children)
+    }
+
+    #[test]
+    fn test_document_flags() {
+        let nodes = vec![
+            ManNode::SectionHeading {
+                title: "OPTIONS".into(),
+                children: vec![],
+            },
+            ManNode::DefinitionList {
+                children: vec![
+                    item(vec![
+                        ManNode::bold("-o"),
+                        ManNode::Text(", ".into()),
+                        ManNode::bold("--output"),
+                        ManNode::Text(" ".into()),
+                        ManNode::italic("FILE"),
+                        ManNode::Text("\n".into()),
+                        ManNode::Text("Write to FILE. Defaults".into()),
+                        ManNode::SoftBreak,
+                        ManNode::Text("to stdout.".into()),
+                    ]),
+                    item(vec![
+                        ManNode::bold("--color[=WHEN]"),
+                        ManNode::Text("\nColorize.".into()),
+                    ]),
+                ],
+            },
+        ];
+        let flags = document_flags(&nodes);
+        assert_eq!(
+            flags[0],
+            Flag {
+                short: Some("-o".into()),
+                long: Some("--output".into()),
+                arg: Some("FILE".into()),
+                description: "Write to FILE. Defaults to stdout.".into(),
+            }
+        );
+        assert_eq!(flags[0].summary(), "Write to FILE");
+        assert_eq!(flags[1].long.as_deref(), Some("--color"));
+        assert_eq!(flags[1].arg.as_deref(), Some("WHEN"));
+        assert_eq!(flags[1].summary(), "Colorize");
+    }
+}
@@ -0,0 +1,256 @@
+//! The snippet library: `{{snippet: exit-codes}}` is replaced by the Markdown
+//! of `snippets/exit-codes.md` before the page is parsed, and
+//! `{{snippet: exit-codes tool=mytool}}` fills the snippet's `{{tool}}`
+//! placeholders in as well.
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Where snippets are looked up, relative to the page.
+pub const SNIPPET_DIR: &str = "snippets";
+
+/// Snippets may use other snippets, this many levels deep.
+const MAX_DEPTH: usize = 16;
+
+#[derive(Debug)]
+pub enum SnippetError {
+    ReadError(PathBuf, io::Error),
+    /// A `{{snippet:` without its `}}`, on this line of the text it is in.
+    Unterminated(usize),
+    /// A `key=value` argument without the `=`.
+    BadArgument(String, String),
+    /// A placeholder of the snippet that the reference gives no value for.
+    MissingValue(String, String),
+    /// Snippets that use each other in a circle.
+    TooDeep(String),
+}
+
+impl fmt::Display for SnippetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnippetError::ReadError(file, e) => write!(
+                f,
+                "mdman: Could not read snippet {}. Error: {}",
+                file.display(),
+                e
+            ),
+            SnippetError::Unterminated(line) => {
+                write!(
+                    f,
+                    "mdman: {{{{snippet: on line {} has no closing }}}}",
+                    line
+                )
+            }
+            SnippetError::BadArgument(name, argument) => write!(
+                f,
+                "mdman: Argument {} of snippet {} is not of the form key=value",
+                argument, name
+            ),
+            SnippetError::MissingValue(name, key) => write!(
+                f,
+                "mdman: Snippet {} needs a value for {}, e.g. {{{{snippet: {} {}=...}}}}",
+                name, key, name, key
+            ),
+            SnippetError::TooDeep(name) => write!(
+                f,
+                "mdman: Snippet {} uses snippets more than {} levels deep; do they use each other?",
+                name, MAX_DEPTH
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SnippetError {}
+
+/// Replace every `{{snippet: NAME key=value ...}}` in `markdown` with the
+/// content of `NAME.md` in `dir`, without its final line break, and its
+/// `{{key}}` placeholders with the values. Values with blanks go in double
+/// quotes. Snippets used by a snippet get its values too.
+pub fn expand(markdown: &str, dir: &Path) -> Result<String, SnippetError> {
+    expand_at(markdown, dir, 0, None, &[])
+}
+
+fn expand_at(
+    markdown: &str,
+    dir: &Path,
+    depth: usize,
+    parent: Option<&str>,
+    inherited: &[(String, String)],
+) -> Result<String, SnippetError> {
+    const OPEN: &str = "{{snippet:";
+    if !markdown.contains(OPEN) {
+        return Ok(markdown.to_string());
+    }
+    if depth > MAX_DEPTH {
+        return Err(SnippetError::TooDeep(
+            parent.unwrap_or_default().to_string(),
+        ));
+    }
+    let mut out = String::new();
+    let mut rest = markdown;
+    while let Some(start) = rest.find(OPEN) {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + OPEN.len()..];
+        if in_code(markdown, markdown.len() - rest.len() + start) {
+            out.push_str(OPEN);
+            rest = after;
+            continue;
+        }
+        let Some(end) = after.find("}}") else {
+            let offset = markdown.len() - rest.len() + start;
+            let line = markdown[..offset].matches('\n').count() + 1;
+            return Err(SnippetError::Unterminated(line));
+        };
+        let (name, mut values) = parse_reference(&after[..end])?;
+        values.extend(inherited.iter().cloned());
+        let path = dir.join(format!("{}.md", name));
+        let content =
+            std::fs::read_to_string(&path).map_err(|e| SnippetError::ReadError(path.clone(), e))?;
+        let content = fill_in(content.trim_end_matches(['\n', '\r']), &name, &values)?;
+        out.push_str(&expand_at(&content, dir, depth + 1, Some(&name), &values)?);
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Whether `offset` in `markdown` is in a fenced code block or a code span,
+/// where references are examples rather than references.
+fn in_code(markdown: &str, offset: usize) -> bool {
+    let before = &markdown[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let mut fence: Option<char> = None;
+    for line in before[..line_start].lines() {
+        let trimmed = line.trim_start();
+        match fence {
+            Some(c) if trimmed.starts_with(&format!("{c}{c}{c}")) => fence = None,
+            None if trimmed.starts_with("```") || trimmed.starts_with("~~~") => {
+                fence = trimmed.chars().next();
+            }
+            _ => {}
+        }
+    }
+    fence.is_some() || before[line_start..].matches('`').count() % 2 == 1
+}
+
+/// The name and `key=value` arguments of a reference, e.g. of
+/// ` exit-codes tool="my tool"`.
+fn parse_reference(reference: &str) -> Result<(String, Vec<(String, String)>), SnippetError> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    for c in reference.trim().chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            c => word.push(c),
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    let mut words = words.into_iter();
+    let name = words.next().unwrap_or_default();
+    let values = words
+        .map(|argument| match argument.split_once('=') {
+            Some((key, value)) => Ok((key.to_string(), value.to_string())),
+            None => Err(SnippetError::BadArgument(name.clone(), argument)),
+        })
+        .collect::<Result<_, _>>()?;
+    Ok((name, values))
+}
+
+/// Replace the `{{key}}` placeholders of a snippet. Anything else in double
+/// braces, such as `{{include-section: ...}}`, is left alone.
+fn fill_in(content: &str, name: &str, values: &[(String, String)]) -> Result<String, SnippetError> {
+    let is_key = |key: &str| {
+        !key.is_empty()
+            && key
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    };
+    let mut out = String::new();
+    let mut rest = content;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) if is_key(&after[..end]) => {
+                let key = &after[..end];
+                let Some((_, value)) = values.iter().find(|(k, _)| k == key) else {
+                    return Err(SnippetError::MissingValue(name.into(), key.into()));
+                };
+                out.push_str(value);
+                rest = &after[end + 2..];
+            }
+            _ => {
+                out.push_str("{{");
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand() {
+        let dir = std::env::temp_dir().join(format!("mdman-snippets-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("exit-codes.md"),
+            "**{{tool}}** exits with 0 on success.\n{{snippet: footer}}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("footer.md"),
+            "See {{include-section: x.md#Y}} for {{tool}}.\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("loop.md"), "{{snippet: loop}}").unwrap();
+        let expand = |markdown: &str| expand(markdown, &dir).map_err(|e| e.to_string());
+        let filled = expand("# EXIT STATUS\n\n{{snippet: exit-codes tool=\"my tool\"}}\n\nMore.\n");
+        let unchanged = expand("No snippets, {{tool}} and all.\n");
+        let missing = expand("{{snippet: exit-codes}}");
+        let bad = expand("{{snippet: exit-codes tool}}");
+        let open = expand("a\n{{snippet: exit-codes\nb\n");
+        let looping = expand("{{snippet: loop}}");
+        let code = "Use `{{snippet: x}}`:\n\n```\n{{snippet: x}}\n```\n";
+        let examples = expand(code);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            filled.unwrap(),
+            "# EXIT STATUS\n\n**my tool** exits with 0 on success.\n\
+             See {{include-section: x.md#Y}} for my tool.\n\nMore.\n"
+        );
+        assert_eq!(unchanged.unwrap(), "No snippets, {{tool}} and all.\n");
+        assert_eq!(
+            missing.unwrap_err(),
+            "mdman: Snippet exit-codes needs a value for tool, e.g. {{snippet: exit-codes tool=...}}"
+        );
+        assert_eq!(
+            bad.unwrap_err(),
+            "mdman: Argument tool of snippet exit-codes is not of the form key=value"
+        );
+        assert_eq!(
+            open.unwrap_err(),
+            "mdman: {{snippet: on line 2 has no closing }}"
+        );
+        assert_eq!(examples.unwrap(), code);
+        assert!(
+            looping
+                .unwrap_err()
+                .contains("Snippet loop uses snippets more than")
+        );
+    }
+}
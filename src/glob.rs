@@ -0,0 +1,136 @@
+//! Expansion of `*`, `?` and `[...]` in input paths, for shells that pass
+//! patterns through as they are (cmd.exe, PowerShell, or a quoted
+//! `"docs/*.md"`).
+
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Whether `path` has a wildcard in it.
+pub fn is_pattern(path: &Path) -> bool {
+    path.to_string_lossy().contains(['*', '?', '['])
+}
+
+/// The paths matching `pattern`, sorted. Wildcards match within one
+/// component of the path, and only match a leading `.` if the pattern has
+/// one, as in shells.
+pub fn expand(pattern: &Path) -> Vec<PathBuf> {
+    let mut paths = vec![PathBuf::new()];
+    for component in pattern.components() {
+        let Component::Normal(name) = component else {
+            // The root, `.` and `..` are taken as they are.
+            paths.iter_mut().for_each(|p| p.push(component));
+            continue;
+        };
+        let name = name.to_string_lossy();
+        if !is_pattern(Path::new(name.as_ref())) {
+            paths.iter_mut().for_each(|p| p.push(name.as_ref()));
+            continue;
+        }
+        let pattern = name.chars().collect::<Vec<_>>();
+        let mut matched = Vec::new();
+        for dir in &paths {
+            let read = if dir.as_os_str().is_empty() {
+                fs::read_dir(".")
+            } else {
+                fs::read_dir(dir)
+            };
+            let Ok(entries) = read else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let entry = entry.file_name().to_string_lossy().to_string();
+                let text = entry.chars().collect::<Vec<_>>();
+                let hidden = text.first() == Some(&'.') && pattern.first() != Some(&'.');
+                if !hidden && matches(&pattern, &text) {
+                    matched.push(dir.join(entry));
+                }
+            }
+        }
+        paths = matched;
+    }
+    paths.retain(|p| p.exists());
+    paths.sort();
+    paths
+}
+
+/// Match `text` against `pattern`: `*` is any run of characters, `?` any one,
+/// `[abc]`, `[a-z]` and `[!abc]` one of (or none of) a set.
+fn matches(pattern: &[char], text: &[char]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some('*') => (0..=text.len()).any(|skip| matches(&pattern[1..], &text[skip..])),
+        Some('?') => !text.is_empty() && matches(&pattern[1..], &text[1..]),
+        Some('[') => {
+            let Some(close) = pattern.iter().skip(2).position(|&c| c == ']') else {
+                // No closing bracket: a literal `[`.
+                return text.first() == Some(&'[') && matches(&pattern[1..], &text[1..]);
+            };
+            let Some(&c) = text.first() else {
+                return false;
+            };
+            let set = &pattern[1..close + 2];
+            let (negated, set) = match set.first() {
+                Some('!') => (true, &set[1..]),
+                _ => (false, set),
+            };
+            let mut found = false;
+            let mut i = 0;
+            while i < set.len() {
+                if i + 2 < set.len() && set[i + 1] == '-' {
+                    found |= (set[i]..=set[i + 2]).contains(&c);
+                    i += 3;
+                } else {
+                    found |= set[i] == c;
+                    i += 1;
+                }
+            }
+            found != negated && matches(&pattern[close + 3..], &text[1..])
+        }
+        Some(&p) => text.first() == Some(&p) && matches(&pattern[1..], &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        let matches = |pattern: &str, text: &str| {
+            let pattern = pattern.chars().collect::<Vec<_>>();
+            matches(&pattern, &text.chars().collect::<Vec<_>>())
+        };
+        assert!(matches("*.md", "tool.md"));
+        assert!(matches("*.md", ".md"));
+        assert!(!matches("*.md", "tool.md.bak"));
+        assert!(matches("tool-?.md", "tool-a.md"));
+        assert!(!matches("tool-?.md", "tool-.md"));
+        assert!(matches("[a-c]*", "build"));
+        assert!(!matches("[!a-c]*", "build"));
+        assert!(matches("[]x]", "]"));
+        assert!(matches("a[b", "a[b"));
+    }
+
+    #[test]
+    fn test_expand() {
+        let dir = std::env::temp_dir().join(format!("mdman-glob-{}", std::process::id()));
+        fs::create_dir_all(dir.join("doc/sub")).unwrap();
+        for file in [
+            "doc/b.md",
+            "doc/a.md",
+            "doc/.hidden.md",
+            "doc/c.txt",
+            "doc/sub/d.md",
+        ] {
+            fs::write(dir.join(file), "").unwrap();
+        }
+        let found = expand(&dir.join("doc/*.md"));
+        let nested = expand(&dir.join("*/s?b/*.md"));
+        let none = expand(&dir.join("doc/*.rst"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(found, [dir.join("doc/a.md"), dir.join("doc/b.md")]);
+        assert_eq!(nested, [dir.join("doc/sub/d.md")]);
+        assert!(none.is_empty());
+    }
+}
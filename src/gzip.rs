@@ -0,0 +1,50 @@
+//! gzip for man pages, which most systems install compressed.
+//!
+//! The header has no file name and no time, so that the same page always
+//! compresses to the same bytes.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use flate2::{Compression, GzBuilder};
+
+/// `data` as a gzip file.
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzBuilder::new().write(Vec::new(), Compression::best());
+    // Writing to memory does not fail.
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+/// Whether `path` names a gzip file, e.g. `tool.1.gz`.
+pub fn is_gzip_name(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == "gz")
+}
+
+/// `path` with `.gz` added, e.g. `man1/tool.1.gz`.
+pub fn gzip_name(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".gz");
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_compress() {
+        let page = ".TH TOOL 1\n.SH NAME\ntool\n".repeat(100);
+        let compressed = compress(page.as_bytes());
+        // Magic, deflate, no flags and no time.
+        assert_eq!(compressed[..8], [0x1f, 0x8b, 8, 0, 0, 0, 0, 0]);
+        assert!(compressed.len() < page.len() / 10);
+        assert_eq!(compress(page.as_bytes()), compressed);
+        let mut decompressed = String::new();
+        flate2::read::GzDecoder::new(compressed.as_slice())
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, page);
+    }
+}
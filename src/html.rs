@@ -0,0 +1,743 @@
+use std::collections::BTreeMap;
+
+use crate::man_node::{ManNode, TableAlign, title_line};
+use crate::render::Renderer;
+
+/// Hides the skip link until it has the keyboard focus.
+const STYLE: &str = ".skip-link{position:absolute;left:-10000px}\
+                     .skip-link:focus{left:1em;top:1em}";
+
+/// Keeps the index of pages in a single file beside them on wide screens.
+const SIDEBAR_STYLE: &str = "@media(min-width:60em){body{display:grid;\
+                             grid-template-columns:15em minmax(0,1fr);gap:2em}\
+                             .pages{position:sticky;top:0;align-self:start;\
+                             max-height:100vh;overflow:auto}}";
+
+/// What an --html-template can contain, in the order [`document`] fills them.
+pub const PLACEHOLDERS: &[&str] = &["{{title}}", "{{lang}}", "{{head}}", "{{content}}"];
+
+/// Link texts that tell a screen reader's list of links nothing.
+const VAGUE_LINK_TEXT: &[&str] = &[
+    "click here",
+    "here",
+    "link",
+    "more",
+    "read more",
+    "this",
+    "this link",
+];
+
+/// Settings of the HTML output that do not come from the page.
+#[derive(Debug, Default, Clone)]
+pub struct HtmlOptions {
+    /// Where the pages converted together are, by name and section, so that
+    /// references to them become links, e.g. `("ls", "1")` to `ls.1.html`.
+    pub pages: BTreeMap<(String, String), String>,
+    /// A document to put every page into instead of the built-in one, with
+    /// [`PLACEHOLDERS`] where the page goes.
+    pub template: Option<String>,
+    /// Stylesheet linked from every page, e.g. that of a documentation site.
+    pub css: Option<String>,
+}
+
+/// Render a page as a standalone HTML document for screen readers as much as
+/// for browsers: a skip link, a `<nav>` landmark with the sections, one
+/// `<section>` per heading labelled by it, and `<dl>` for definition lists
+/// such as OPTIONS. Pages without `lang` in the frontmatter are marked as
+/// English.
+pub fn html_page(nodes: &[ManNode], options: &HtmlOptions) -> String {
+    let title = title_line(nodes);
+    let lang = title.and_then(|t| t.lang.as_deref()).unwrap_or("en");
+    let mut out = String::new();
+    out.push_str("<a class=\"skip-link\" href=\"#content\">Skip to content</a>\n");
+    write_page(&mut out, nodes, options, None);
+    document(&page_heading(nodes), lang, STYLE, &out, options)
+}
+
+/// Render the pages converted together as one HTML document: a sidebar
+/// `<nav>` with an index of the pages, then every page as an `<article>`
+/// like [`html_page`] would show it, with its headings a level lower below
+/// the `<h1>` of the manual.
+/// References between the pages link to their articles. The document takes
+/// the language of the first page.
+pub fn html_pages(pages: &[&[ManNode]], options: &HtmlOptions) -> String {
+    let anchors = pages
+        .iter()
+        .enumerate()
+        .map(|(i, nodes)| match title_line(nodes) {
+            Some(t) => format!("{}.{}", t.name, t.section).replace(char::is_whitespace, "-"),
+            None => format!("page-{}", i + 1),
+        })
+        .collect::<Vec<_>>();
+    let options = HtmlOptions {
+        pages: pages
+            .iter()
+            .zip(&anchors)
+            .filter_map(|(nodes, anchor)| {
+                let t = title_line(nodes)?;
+                Some((
+                    (t.name.clone(), t.section.to_string()),
+                    format!("#{}", anchor),
+                ))
+            })
+            .collect(),
+        ..options.clone()
+    };
+    let titles = pages
+        .iter()
+        .map(|nodes| title_line(nodes))
+        .collect::<Vec<_>>();
+    let lang = titles
+        .first()
+        .copied()
+        .flatten()
+        .and_then(|t| t.lang.as_deref())
+        .unwrap_or("en");
+    // The manual all of the pages belong to, if they agree on one.
+    let manual = titles
+        .iter()
+        .map(|t| t.and_then(|t| t.center_footer.as_deref()))
+        .reduce(|a, b| if a == b { a } else { None })
+        .flatten()
+        .unwrap_or("Manual pages");
+
+    let mut out = String::new();
+    out.push_str("<a class=\"skip-link\" href=\"#content\">Skip to content</a>\n");
+    out.push_str(&format!(
+        "<header>\n<h1>{}</h1>\n</header>\n",
+        escape(manual)
+    ));
+    out.push_str("<nav class=\"pages\" aria-label=\"Pages\">\n<ul>\n");
+    for (nodes, anchor) in pages.iter().zip(&anchors) {
+        out.push_str(&format!(
+            "<li><a href=\"#{}\">{}</a></li>\n",
+            escape(anchor),
+            escape(&page_heading(nodes))
+        ));
+    }
+    out.push_str("</ul>\n</nav>\n");
+    out.push_str("<main id=\"content\" tabindex=\"-1\">\n");
+    for (nodes, anchor) in pages.iter().zip(&anchors) {
+        write_page(&mut out, nodes, &options, Some(anchor));
+    }
+    out.push_str("</main>\n");
+    document(
+        manual,
+        lang,
+        &format!("{}{}", STYLE, SIDEBAR_STYLE),
+        &out,
+        &options,
+    )
+}
+
+/// What a page is called at its top: `name(section)`.
+fn page_heading(nodes: &[ManNode]) -> String {
+    title_line(nodes).map_or_else(
+        || "Manual page".to_string(),
+        |t| format!("{}({})", t.name, t.section),
+    )
+}
+
+/// Write the header, contents, sections and footer of a page. On a page of
+/// its own, the sections are the `<main>` landmark; among others, the page
+/// is an `<article>` with the id `anchor`, which also prefixes the ids of its
+/// headings.
+fn write_page(out: &mut String, nodes: &[ManNode], options: &HtmlOptions, anchor: Option<&str>) {
+    let title = title_line(nodes);
+    let heading = page_heading(nodes);
+    let ids = heading_ids(nodes)
+        .into_iter()
+        .map(|id| match anchor {
+            Some(anchor) => id.map(|id| format!("{}-{}", escape(anchor), id)),
+            None => id,
+        })
+        .collect::<Vec<_>>();
+    // Among other pages, the page title is an `<h2>` and everything below it
+    // moves down a level.
+    let shift = usize::from(anchor.is_some());
+
+    match anchor {
+        Some(anchor) => {
+            let anchor = escape(anchor);
+            out.push_str(&format!(
+                "<article id=\"{anchor}\" aria-labelledby=\"{anchor}-title\">\n<header>\n\
+                 <h2 id=\"{anchor}-title\">{}</h2>\n",
+                escape(&heading)
+            ));
+        }
+        None => out.push_str(&format!("<header>\n<h1>{}</h1>\n", escape(&heading))),
+    }
+    if let Some(manual) = title.and_then(|t| t.center_footer.as_ref()) {
+        out.push_str(&format!("<p>{}</p>\n", escape(manual)));
+    }
+    out.push_str("</header>\n");
+
+    let contents = nodes
+        .iter()
+        .zip(&ids)
+        .filter_map(|(node, id)| match node {
+            ManNode::SectionHeading { title, .. } => Some((title, id.as_deref()?)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if !contents.is_empty() {
+        // Landmarks of the same kind need names of their own.
+        match anchor {
+            Some(_) => out.push_str(&format!(
+                "<nav aria-label=\"Contents of {}\">\n<ul>\n",
+                escape(&heading)
+            )),
+            None => out.push_str("<nav aria-label=\"Contents\">\n<ul>\n"),
+        }
+        for (title, id) in contents {
+            out.push_str(&format!(
+                "<li><a href=\"#{}\">{}</a></li>\n",
+                id,
+                escape(title)
+            ));
+        }
+        out.push_str("</ul>\n</nav>\n");
+    }
+
+    if anchor.is_none() {
+        out.push_str("<main id=\"content\" tabindex=\"-1\">\n");
+    }
+    // Heading levels of the sections still open.
+    let mut open: Vec<usize> = Vec::new();
+    for (node, id) in nodes.iter().zip(&ids) {
+        match (heading_level(node), id) {
+            (Some((level, title)), Some(id)) => {
+                let level = (level + shift).min(6);
+                while open.last().is_some_and(|&l| l >= level) {
+                    open.pop();
+                    out.push_str("</section>\n");
+                }
+                open.push(level);
+                out.push_str(&format!("<section aria-labelledby=\"{}\">\n", id));
+                out.push_str(&format!(
+                    "<h{level} id=\"{}\">{}</h{level}>\n",
+                    id,
+                    escape(title)
+                ));
+            }
+            _ if matches!(node, ManNode::TitleLine(_)) => {}
+            _ => write_block(out, node, options),
+        }
+    }
+    for _ in open {
+        out.push_str("</section>\n");
+    }
+    if anchor.is_none() {
+        out.push_str("</main>\n");
+    }
+
+    let footer = title
+        .map(|t| [&t.left_footer, &t.date])
+        .into_iter()
+        .flatten()
+        .flatten()
+        .collect::<Vec<_>>();
+    if !footer.is_empty() {
+        out.push_str("<footer>\n");
+        for line in footer {
+            out.push_str(&format!("<p>{}</p>\n", escape(line)));
+        }
+        out.push_str("</footer>\n");
+    }
+    if anchor.is_some() {
+        out.push_str("</article>\n");
+    }
+}
+
+/// The whole HTML document around `content`, from the --html-template if
+/// there is one. Its `{{title}}`, `{{lang}}`, `{{head}}` and `{{content}}`
+/// are replaced in one pass, so that text of the page that looks like a
+/// placeholder stays as it is.
+fn document(title: &str, lang: &str, style: &str, content: &str, options: &HtmlOptions) -> String {
+    let mut head = String::new();
+    head.push_str("<meta charset=\"utf-8\">\n");
+    head.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n");
+    head.push_str(&format!("<style>{}</style>\n", style));
+    if let Some(css) = &options.css {
+        head.push_str(&format!(
+            "<link rel=\"stylesheet\" href=\"{}\">\n",
+            escape(css)
+        ));
+    }
+    let (title, lang) = (escape(title), escape(lang));
+    let Some(template) = &options.template else {
+        return format!(
+            "<!DOCTYPE html>\n<html lang=\"{}\">\n<head>\n{}<title>{}</title>\n</head>\n\
+             <body>\n{}</body>\n</html>\n",
+            lang, head, title, content
+        );
+    };
+    let mut out = String::with_capacity(template.len() + content.len());
+    let mut rest = template.as_str();
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let value = PLACEHOLDERS
+            .iter()
+            .zip([&title, &lang, &head, content])
+            .find(|(placeholder, _)| rest.starts_with(**placeholder));
+        match value {
+            Some((placeholder, value)) => {
+                out.push_str(value);
+                rest = &rest[placeholder.len()..];
+            }
+            None => {
+                out.push_str("{{");
+                rest = &rest[2..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The HTML heading level and title of a heading node. The page title is the
+/// only `<h1>`, so `#` sections are `<h2>`.
+fn heading_level(node: &ManNode) -> Option<(usize, &str)> {
+    match node {
+        ManNode::SectionHeading { title, .. } => Some((2, title)),
+        ManNode::SubsectionHeading { title, .. } => Some((3, title)),
+        ManNode::MinorHeading { level, title, .. } => {
+            Some(((usize::from(*level) + 1).min(6), title))
+        }
+        _ => None,
+    }
+}
+
+/// An `id` for each heading, made from its title and unique in the page.
+fn heading_ids(nodes: &[ManNode]) -> Vec<Option<String>> {
+    let mut taken: Vec<String> = Vec::new();
+    nodes
+        .iter()
+        .map(|node| {
+            let (_, title) = heading_level(node)?;
+            let slug = title
+                .to_lowercase()
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|word| !word.is_empty())
+                .collect::<Vec<_>>()
+                .join("-");
+            let slug = if slug.is_empty() {
+                "section".into()
+            } else {
+                slug
+            };
+            let id = (1..)
+                .map(|n| match n {
+                    1 => slug.clone(),
+                    n => format!("{}-{}", slug, n),
+                })
+                .find(|id| !taken.contains(id) && id != "content")
+                .expect("some suffix is free");
+            taken.push(id.clone());
+            Some(id)
+        })
+        .collect()
+}
+
+fn write_block(out: &mut String, node: &ManNode, options: &HtmlOptions) {
+    match node {
+        ManNode::Paragraph { children } => {
+            out.push_str("<p>");
+            write_inlines(out, children, options);
+            out.push_str("</p>\n");
+        }
+        ManNode::CodeBlock { lang, code } => {
+            let class = lang
+                .as_ref()
+                .map(|lang| format!(" class=\"language-{}\"", escape(lang)))
+                .unwrap_or_default();
+            out.push_str(&format!("<pre tabindex=\"0\"><code{}>", class));
+            out.push_str(&escape(code.trim_end_matches('\n')));
+            out.push_str("</code></pre>\n");
+        }
+        ManNode::Equation(text) | ManNode::NoFill(text) => {
+            // Wide preformatted text scrolls, which needs the keyboard focus.
+            out.push_str("<pre tabindex=\"0\">");
+            out.push_str(&escape(text.trim_end_matches('\n')));
+            out.push_str("</pre>\n");
+        }
+        ManNode::Pic(_) => out.push_str("<!-- pic diagram omitted -->\n"),
+        ManNode::RawBlock(_) => out.push_str("<!-- raw roff omitted -->\n"),
+        ManNode::BulletList { children } => list(out, "ul", children, options),
+        ManNode::NumberedList { children } => list(out, "ol", children, options),
+        ManNode::DefinitionList { children } => {
+            out.push_str("<dl>\n");
+            for item in children {
+                let (term, description) = split_term(item.children());
+                out.push_str("<dt>");
+                write_inlines(out, term, options);
+                out.push_str("</dt>\n");
+                if !description.is_empty() {
+                    out.push_str("<dd>");
+                    write_inlines(out, description, options);
+                    out.push_str("</dd>\n");
+                }
+            }
+            out.push_str("</dl>\n");
+        }
+        ManNode::Table {
+            align, children, ..
+        } => {
+            out.push_str("<table>\n");
+            for (i, row) in children.iter().enumerate() {
+                match i {
+                    0 => out.push_str("<thead>\n"),
+                    1 => out.push_str("<tbody>\n"),
+                    _ => {}
+                }
+                out.push_str("<tr>");
+                for (cell, align) in row.children().iter().zip(align) {
+                    let style = match align {
+                        TableAlign::Right => " style=\"text-align:right\"",
+                        TableAlign::Center => " style=\"text-align:center\"",
+                        TableAlign::Left | TableAlign::None => "",
+                    };
+                    let tag = if i == 0 { "th" } else { "td" };
+                    let scope = if i == 0 { " scope=\"col\"" } else { "" };
+                    out.push_str(&format!("<{}{}{}>", tag, scope, style));
+                    write_inlines(out, cell.children(), options);
+                    out.push_str(&format!("</{}>", tag));
+                }
+                out.push_str("</tr>\n");
+                if i == 0 {
+                    out.push_str("</thead>\n");
+                }
+            }
+            if children.len() > 1 {
+                out.push_str("</tbody>\n");
+            }
+            out.push_str("</table>\n");
+        }
+        ManNode::Blockquote { children } => {
+            out.push_str("<blockquote>\n");
+            for child in children {
+                write_block(out, child, options);
+            }
+            out.push_str("</blockquote>\n");
+        }
+        inline => {
+            out.push_str("<p>");
+            write_inlines(out, std::slice::from_ref(inline), options);
+            out.push_str("</p>\n");
+        }
+    }
+}
+
+/// The term of a definition list item and its description, which follows
+/// the first line break.
+fn split_term(children: &[ManNode]) -> (&[ManNode], &[ManNode]) {
+    match children
+        .iter()
+        .position(|n| matches!(n, ManNode::Text(text) if text == "\n"))
+    {
+        Some(i) => (&children[..i], &children[i + 1..]),
+        None => (children, &[]),
+    }
+}
+
+fn list(out: &mut String, tag: &str, items: &[ManNode], options: &HtmlOptions) {
+    out.push_str(&format!("<{}>\n", tag));
+    for item in items {
+        out.push_str("<li>");
+        // A disabled checkbox would need a label of its own; a symbol with
+        // its state spelled out reads the same.
+        match item.checked() {
+            Some(true) => out.push_str("<span role=\"img\" aria-label=\"done\">☑</span> "),
+            Some(false) => out.push_str("<span role=\"img\" aria-label=\"not done\">☐</span> "),
+            None => {}
+        }
+        write_inlines(out, item.children(), options);
+        out.push_str("</li>\n");
+    }
+    out.push_str(&format!("</{}>\n", tag));
+}
+
+fn write_inlines(out: &mut String, children: &[ManNode], options: &HtmlOptions) {
+    for child in children {
+        match child {
+            ManNode::Text(text) => out.push_str(&escape(text)),
+            ManNode::SoftBreak => out.push('\n'),
+            ManNode::LineBreak => out.push_str("<br>\n"),
+            ManNode::Bold(children) => enclose(out, "strong", children, options),
+            ManNode::Italic(children) => enclose(out, "em", children, options),
+            ManNode::Strikethrough(children) => enclose(out, "del", children, options),
+            ManNode::InlineCode(text) => out.push_str(&format!("<code>{}</code>", escape(text))),
+            // Roff means nothing to a browser.
+            ManNode::RawInline(_) => {}
+            ManNode::ManRef { name, section } => {
+                let reference = format!("<strong>{}</strong>({})", escape(name), escape(section));
+                match options.pages.get(&(name.clone(), section.clone())) {
+                    Some(href) => {
+                        out.push_str(&format!("<a href=\"{}\">{}</a>", escape(href), reference))
+                    }
+                    None => out.push_str(&reference),
+                }
+            }
+            ManNode::Uri {
+                url,
+                title,
+                children,
+            } => {
+                out.push_str(&format!("<a href=\"{}\"", escape(url)));
+                if let Some(title) = title {
+                    out.push_str(&format!(" title=\"{}\"", escape(title)));
+                }
+                out.push('>');
+                // A link has to say something to be found in a list of links.
+                let text = children.iter().map(ManNode::plain_text).collect::<String>();
+                if text.trim().is_empty() {
+                    out.push_str(&escape(url));
+                } else {
+                    write_inlines(out, children, options);
+                }
+                out.push_str("</a>");
+            }
+            ManNode::ListItem { children, .. } => write_inlines(out, children, options),
+            block => {
+                out.push('\n');
+                write_block(out, block, options);
+            }
+        }
+    }
+}
+
+fn enclose(out: &mut String, tag: &str, children: &[ManNode], options: &HtmlOptions) {
+    out.push_str(&format!("<{}>", tag));
+    write_inlines(out, children, options);
+    out.push_str(&format!("</{}>", tag));
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// What makes the HTML of a page hard to use with a screen reader that the
+/// renderer cannot fix by itself: headings that skip a level, links whose
+/// text says nothing out of context and tables with empty header cells.
+/// Each problem comes with the index of the top-level node it is in.
+pub fn accessibility_problems(nodes: &[ManNode]) -> Vec<(usize, String)> {
+    let mut problems = Vec::new();
+    let mut previous_level = 1;
+    for (i, node) in nodes.iter().enumerate() {
+        if let Some((level, title)) = heading_level(node) {
+            if level > previous_level + 1 {
+                problems.push((
+                    i,
+                    format!(
+                        "heading \"{}\" is level {} but the one before it is level {}",
+                        title,
+                        level - 1,
+                        previous_level - 1
+                    ),
+                ));
+            }
+            previous_level = level;
+        }
+        node.walk(&mut |node| match node {
+            ManNode::Uri { children, .. } => {
+                let text = children.iter().map(ManNode::plain_text).collect::<String>();
+                let text = text.trim().trim_end_matches(['.', ':', '!']).to_lowercase();
+                if VAGUE_LINK_TEXT.contains(&text.as_str()) {
+                    problems.push((
+                        i,
+                        format!("link text \"{}\" does not say where it leads", text),
+                    ));
+                }
+            }
+            ManNode::Table { children, .. } => {
+                let header = children.first().map_or(&[][..], ManNode::children);
+                for (column, cell) in header.iter().enumerate() {
+                    if cell.plain_text().trim().is_empty() {
+                        problems.push((
+                            i,
+                            format!("table has an empty header in column {}", column + 1),
+                        ));
+                    }
+                }
+            }
+            _ => {}
+        });
+    }
+    problems
+}
+
+/// [`html_page`] as a [`Renderer`].
+pub struct HtmlRenderer<'a> {
+    pub options: &'a HtmlOptions,
+}
+
+impl Renderer for HtmlRenderer<'_> {
+    fn render(&self, nodes: &[ManNode]) -> String {
+        html_page(nodes, self.options)
+    }
+
+    /// The section number and `html`, e.g. `1.html`, so that pages of the
+    /// same name in different sections do not overwrite each other.
+    fn extension(&self, nodes: &[ManNode]) -> String {
+        format!("{}.html", title_line(nodes).map_or(1, |t| t.section))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_page;
+
+    #[test]
+    fn test_html_page() {
+        let markdown = "---\nname: tool\nsection: 1\ndate: 2025-01-01\n---\n\n# NAME\n\n\
+                        **tool** - do _things_ & more, see [docs](https://example.com)\n\n\
+                        # OPTIONS\n\n---\n\n- **-v**\n  Be verbose.\n\n---\n\n\
+                        ## Exit & codes\n\n| a | b |\n|---|--:|\n| 1 | 2 |\n";
+        let (nodes, _) = parse_page(markdown, &None).unwrap();
+        let html = html_page(&nodes, &HtmlOptions::default());
+        let body = html.split_once("<body>\n").unwrap().1;
+        assert!(html.starts_with("<!DOCTYPE html>\n<html lang=\"en\">\n"));
+        assert!(html.contains("<title>tool(1)</title>\n"));
+        assert_eq!(
+            body,
+            "<a class=\"skip-link\" href=\"#content\">Skip to content</a>\n\
+             <header>\n<h1>tool(1)</h1>\n</header>\n\
+             <nav aria-label=\"Contents\">\n<ul>\n\
+             <li><a href=\"#name\">NAME</a></li>\n\
+             <li><a href=\"#options\">OPTIONS</a></li>\n\
+             </ul>\n</nav>\n\
+             <main id=\"content\" tabindex=\"-1\">\n\
+             <section aria-labelledby=\"name\">\n<h2 id=\"name\">NAME</h2>\n\
+             <p><strong>tool</strong> - do <em>things</em> &amp; more, see \
+             <a href=\"https://example.com\">docs</a></p>\n\
+             </section>\n\
+             <section aria-labelledby=\"options\">\n<h2 id=\"options\">OPTIONS</h2>\n\
+             <dl>\n<dt><strong>-v</strong></dt>\n<dd>Be verbose.</dd>\n</dl>\n\
+             <section aria-labelledby=\"exit-codes\">\n\
+             <h3 id=\"exit-codes\">Exit &amp; codes</h3>\n\
+             <table>\n<thead>\n<tr><th scope=\"col\">a</th>\
+             <th scope=\"col\" style=\"text-align:right\">b</th></tr>\n</thead>\n\
+             <tbody>\n<tr><td>1</td><td style=\"text-align:right\">2</td></tr>\n</tbody>\n\
+             </table>\n\
+             </section>\n</section>\n</main>\n\
+             <footer>\n<p>2025-01-01</p>\n</footer>\n</body>\n</html>\n"
+        );
+    }
+
+    #[test]
+    fn test_man_ref_links() {
+        let (nodes, _) = parse_page("# SEE ALSO\n\n**tool-build**(1), ls(1)\n", &None).unwrap();
+        let options = HtmlOptions {
+            pages: [(
+                ("tool-build".into(), "1".into()),
+                "tool-build.1.html".into(),
+            )]
+            .into(),
+            ..Default::default()
+        };
+        assert!(html_page(&nodes, &options).contains(
+            "<p><a href=\"tool-build.1.html\"><strong>tool-build</strong>(1)</a>, \
+             <strong>ls</strong>(1)</p>\n"
+        ));
+    }
+
+    #[test]
+    fn test_template_and_css() {
+        let (nodes, _) = parse_page("# NAME\n\nwrite {{title}} here\n", &None).unwrap();
+        let options = HtmlOptions {
+            template: Some(
+                "<html lang={{lang}}><head>{{head}}<title>{{title}} - site</title>\
+                            </head><body>{{content}}</body></html>"
+                    .into(),
+            ),
+            css: Some("site.css".into()),
+            ..Default::default()
+        };
+        let html = html_page(&nodes, &options);
+        assert!(html.starts_with("<html lang=en><head><meta charset=\"utf-8\">"));
+        assert!(html.contains("<link rel=\"stylesheet\" href=\"site.css\">"));
+        assert!(
+            html.contains("<title>Manual page - site</title></head><body><a class=\"skip-link\"")
+        );
+        assert!(html.contains("<p>write {{title}} here</p>"));
+        assert!(html.ends_with("</main>\n</body></html>"));
+    }
+
+    #[test]
+    fn test_html_pages() {
+        let (tool, _) = parse_page(
+            "---\nname: tool\nsection: 1\n---\n\n# SEE ALSO\n\ntool.conf(5)\n",
+            &None,
+        )
+        .unwrap();
+        let (conf, _) = parse_page(
+            "---\nname: tool.conf\nsection: 5\n---\n\n# NAME\n\nconf\n",
+            &None,
+        )
+        .unwrap();
+        let html = html_pages(&[&tool, &conf], &HtmlOptions::default());
+        let body = html.split_once("<body>\n").unwrap().1;
+        assert!(html.contains("<title>Manual pages</title>\n"));
+        assert_eq!(
+            body,
+            "<a class=\"skip-link\" href=\"#content\">Skip to content</a>\n\
+             <header>\n<h1>Manual pages</h1>\n</header>\n\
+             <nav class=\"pages\" aria-label=\"Pages\">\n<ul>\n\
+             <li><a href=\"#tool.1\">tool(1)</a></li>\n\
+             <li><a href=\"#tool.conf.5\">tool.conf(5)</a></li>\n\
+             </ul>\n</nav>\n\
+             <main id=\"content\" tabindex=\"-1\">\n\
+             <article id=\"tool.1\" aria-labelledby=\"tool.1-title\">\n\
+             <header>\n<h2 id=\"tool.1-title\">tool(1)</h2>\n</header>\n\
+             <nav aria-label=\"Contents of tool(1)\">\n<ul>\n\
+             <li><a href=\"#tool.1-see-also\">SEE ALSO</a></li>\n</ul>\n</nav>\n\
+             <section aria-labelledby=\"tool.1-see-also\">\n\
+             <h3 id=\"tool.1-see-also\">SEE ALSO</h3>\n\
+             <p><a href=\"#tool.conf.5\"><strong>tool.conf</strong>(5)</a></p>\n\
+             </section>\n</article>\n\
+             <article id=\"tool.conf.5\" aria-labelledby=\"tool.conf.5-title\">\n\
+             <header>\n<h2 id=\"tool.conf.5-title\">tool.conf(5)</h2>\n</header>\n\
+             <nav aria-label=\"Contents of tool.conf(5)\">\n<ul>\n\
+             <li><a href=\"#tool.conf.5-name\">NAME</a></li>\n</ul>\n</nav>\n\
+             <section aria-labelledby=\"tool.conf.5-name\">\n\
+             <h3 id=\"tool.conf.5-name\">NAME</h3>\n<p>conf</p>\n\
+             </section>\n</article>\n\
+             </main>\n</body>\n</html>\n"
+        );
+    }
+
+    #[test]
+    fn test_accessibility_problems() {
+        let markdown = "# NAME\n\ntool\n\n### Deep\n\nSee [here](https://example.com).\n\n\
+                        # DESCRIPTION\n\nSee [the manual](https://example.com).\n\n\
+                        |   | b |\n|---|---|\n| 1 | 2 |\n";
+        let (nodes, _) = parse_page(markdown, &None).unwrap();
+        let problems = accessibility_problems(&nodes);
+        assert_eq!(
+            problems.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            [2, 3, 6]
+        );
+        assert_eq!(
+            problems.into_iter().map(|(_, m)| m).collect::<Vec<_>>(),
+            [
+                "heading \"Deep\" is level 3 but the one before it is level 1",
+                "link text \"here\" does not say where it leads",
+                "table has an empty header in column 1",
+            ]
+        );
+        let (nodes, _) = parse_page("# A\n\n## B\n\n### C\n\n# D\n", &None).unwrap();
+        assert!(accessibility_problems(&nodes).is_empty());
+    }
+}
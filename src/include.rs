@@ -0,0 +1,89 @@
+//! `{{include-section: other.md#OPTIONS}}`: a paragraph that is replaced by
+//! the body of a section of another Markdown file, so that what several pages
+//! share, e.g. global flags, is written once.
+
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use crate::man_node::{ManNode, section_range};
+
+#[derive(Debug)]
+pub enum IncludeError {
+    ReadError(PathBuf, io::Error),
+    NoSection(PathBuf, String),
+    /// The file includes itself, directly or through others.
+    Cycle(PathBuf),
+}
+
+impl fmt::Display for IncludeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IncludeError::ReadError(file, e) => write!(
+                f,
+                "mdman: Could not read included file {}. Error: {}",
+                file.display(),
+                e
+            ),
+            IncludeError::NoSection(file, name) => {
+                write!(f, "mdman: No section {} in {}", name, file.display())
+            }
+            IncludeError::Cycle(file) => {
+                write!(f, "mdman: {} includes itself", file.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for IncludeError {}
+
+/// The file and section name of an include directive, if `node` is one: a
+/// paragraph of nothing but `{{include-section: FILE#SECTION}}`.
+pub fn directive(node: &ManNode) -> Option<(PathBuf, String)> {
+    let ManNode::Paragraph { .. } = node else {
+        return None;
+    };
+    let text = node.plain_text();
+    let inner = text
+        .trim()
+        .strip_prefix("{{")?
+        .strip_suffix("}}")?
+        .trim()
+        .strip_prefix("include-section:")?;
+    let (file, section) = inner.trim().rsplit_once('#')?;
+    let (file, section) = (file.trim(), section.trim());
+    (!file.is_empty() && !section.is_empty()).then(|| (PathBuf::from(file), section.to_string()))
+}
+
+/// Take the nodes of the section `name` out of `nodes`, without its heading.
+pub fn take_section_body(mut nodes: Vec<ManNode>, name: &str) -> Option<Vec<ManNode>> {
+    let range = section_range(&nodes, name)?;
+    Some(nodes.drain(range.start + 1..range.end).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_directive() {
+        let paragraph = |text: &str| ManNode::Paragraph {
+            children: vec![ManNode::Text(text.into())],
+        };
+        assert_eq!(
+            directive(&paragraph(
+                "{{include-section: ../global.md#GLOBAL OPTIONS}}\n"
+            )),
+            Some(("../global.md".into(), "GLOBAL OPTIONS".into()))
+        );
+        assert_eq!(
+            directive(&paragraph("See {{include-section: a.md#X}}")),
+            None
+        );
+        assert_eq!(directive(&paragraph("{{include-section: a.md}}")), None);
+        assert_eq!(
+            directive(&ManNode::Text("{{include-section: a.md#X}}".into())),
+            None
+        );
+    }
+}
@@ -0,0 +1,137 @@
+//! What `mdman info` reports about a page: its frontmatter and what can be
+//! read off the tree without rendering it.
+
+use std::path::Path;
+
+use serde::Serialize;
+
+use mdman::man_node::{ManNode, TitleLine, is_man_section, title_line};
+
+/// A mention of another man page, e.g. `ls(1)`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ManReference {
+    pub name: String,
+    pub section: String,
+}
+
+#[derive(Serialize)]
+pub struct PageInfo<'a> {
+    pub title: Option<&'a TitleLine>,
+    /// The file `mdman FILE` writes, e.g. `tool.1`.
+    pub output: String,
+    /// Section headings, in page order.
+    pub sections: Vec<&'a str>,
+    /// Other pages mentioned in the text, each once, in order of appearance.
+    pub references: Vec<ManReference>,
+}
+
+pub fn page_info<'a>(nodes: &'a [ManNode], file: &Path) -> PageInfo<'a> {
+    let title = title_line(nodes);
+    let section = title.map_or(1, |t| t.section);
+    let sections = nodes
+        .iter()
+        .filter_map(|node| match node {
+            ManNode::SectionHeading { title, .. } => Some(title.as_str()),
+            _ => None,
+        })
+        .collect();
+    let mut references = Vec::new();
+    for node in nodes {
+        for reference in references_in(&node.plain_text()) {
+            if !references.contains(&reference) {
+                references.push(reference);
+            }
+        }
+    }
+    PageInfo {
+        title,
+        output: format!("{}.{}", page_stem(file), section),
+        sections,
+        references,
+    }
+}
+
+impl PageInfo<'_> {
+    /// One `key: value` line per item, for people rather than scripts.
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        if let Some(title) = self.title {
+            out.push_str(&format!(
+                "name: {}\nsection: {}\n",
+                title.name, title.section
+            ));
+            if let Some(date) = &title.date {
+                out.push_str(&format!("date: {}\n", date));
+            }
+        }
+        out.push_str(&format!("output: {}\n", self.output));
+        out.push_str(&format!("sections: {}\n", self.sections.join(", ")));
+        let references = self
+            .references
+            .iter()
+            .map(|r| format!("{}({})", r.name, r.section))
+            .collect::<Vec<_>>();
+        out.push_str(&format!("references: {}\n", references.join(", ")));
+        out
+    }
+}
+
+/// `tool.1.md` -> `tool`.
+pub fn page_stem(file: &Path) -> String {
+    let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+    stem.split('.').next().unwrap_or_default().to_string()
+}
+
+/// Every `name(section)` in `text` where the section looks like a man
+/// section (see [`is_man_section`]).
+pub(crate) fn references_in(text: &str) -> Vec<ManReference> {
+    let is_name_char = |c: char| c.is_alphanumeric() || "._-+:".contains(c);
+    let mut references = Vec::new();
+    for (open, _) in text.match_indices('(') {
+        let before = &text[..open];
+        let name = &before[before.trim_end_matches(is_name_char).len()..];
+        let name = name.trim_start_matches(|c: char| !c.is_alphanumeric());
+        let Some((section, _)) = text[open + 1..].split_once(')') else {
+            continue;
+        };
+        if !name.is_empty() && is_man_section(section) {
+            references.push(ManReference {
+                name: name.to_string(),
+                section: section.to_string(),
+            });
+        }
+    }
+    references
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mdman::parse_page;
+
+    #[test]
+    fn test_references_in() {
+        let found =
+            references_in("See ls(1), (grep(1)) and File::Spec(3pm); not f(x), x(1, or (1).");
+        let names = found
+            .iter()
+            .map(|r| format!("{}({})", r.name, r.section))
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["ls(1)", "grep(1)", "File::Spec(3pm)"]);
+    }
+
+    #[test]
+    fn test_page_info() {
+        let markdown = "---\nname: tool\nsection: 8\n---\n\n# NAME\n\ntool - do things\n\n\
+                        # SEE ALSO\n\n**ls**(1), **tool.conf**(5), ls(1)\n\n```\nnot(1)\n```\n";
+        let (nodes, _) = parse_page(markdown, &None).unwrap();
+        let info = page_info(&nodes, Path::new("doc/tool.8.md"));
+        assert_eq!(info.output, "tool.8");
+        assert_eq!(info.sections, ["NAME", "SEE ALSO"]);
+        assert_eq!(
+            info.to_text(),
+            "name: tool\nsection: 8\noutput: tool.8\nsections: NAME, SEE ALSO\n\
+             references: ls(1), tool.conf(5)\n"
+        );
+    }
+}
@@ -0,0 +1,436 @@
+//! Convert Markdown to man pages.
+//!
+//! ```
+//! let roff = mdman::convert("# NAME\n\n**tool** - do things\n").unwrap();
+//! assert!(roff.starts_with(".SH NAME\n"));
+//! ```
+//!
+//! [`convert_with`] takes [`RoffOptions`] for the settings the `mdman` binary
+//! exposes as flags. The [`ManNode`] tree and [`ToRoff`] are public for tools
+//! that want to inspect or assemble pages themselves. Every output format,
+//! man pages included, is a [`render::Renderer`]; [`render::Format`] picks one.
+//!
+//! Conversion keeps no global state: every call parses into its own tree, and
+//! the trees, [`RoffOptions`], renderers and errors are all `Send + Sync`, so
+//! one set of options can serve pages converted on any number of threads.
+//! Relative `options-file` paths are resolved against the process's current
+//! directory, so pass a file path to [`parse_page`] when that may change.
+
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use markdown::{Constructs, ParseOptions};
+
+use crate::man_node::{ConvertState, Dropped, InputError, ManNode, convert_with_source_lines};
+pub use crate::roff::{RoffOptions, ToRoff};
+
+pub mod ansi;
+pub mod asciidoc;
+pub mod flags;
+pub mod fragment;
+pub mod html;
+pub mod include;
+pub mod man_node;
+pub mod options;
+pub mod org;
+pub mod qr;
+pub mod render;
+pub mod roff;
+pub mod texinfo;
+pub mod tldr;
+mod unicode;
+
+#[derive(Debug)]
+pub enum ConvertError {
+    Markdown(String),
+    /// Bad input, and the file it came from if known.
+    Input(InputError, Option<PathBuf>),
+    OptionsFile(options::OptionsFileError),
+    Include(include::IncludeError),
+    Snippet(fragment::SnippetError),
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConvertError::Markdown(e) => write!(f, "mdman: Could not parse Markdown. Error: {}", e),
+            ConvertError::Input(e, file) => write!(f, "{}", e.describe(file.as_deref())),
+            ConvertError::OptionsFile(e) => write!(f, "{}", e),
+            ConvertError::Include(e) => write!(f, "{}", e),
+            ConvertError::Snippet(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ConvertError {}
+
+impl From<InputError> for ConvertError {
+    fn from(e: InputError) -> Self {
+        ConvertError::Input(e, None)
+    }
+}
+
+impl From<options::OptionsFileError> for ConvertError {
+    fn from(e: options::OptionsFileError) -> Self {
+        ConvertError::OptionsFile(e)
+    }
+}
+
+impl From<fragment::SnippetError> for ConvertError {
+    fn from(e: fragment::SnippetError) -> Self {
+        ConvertError::Snippet(e)
+    }
+}
+
+impl From<include::IncludeError> for ConvertError {
+    fn from(e: include::IncludeError) -> Self {
+        ConvertError::Include(e)
+    }
+}
+
+/// Convert a Markdown page to roff with the default options. An
+/// `options-file` in the frontmatter is looked up relative to the current
+/// directory.
+pub fn convert(markdown: &str) -> Result<String, ConvertError> {
+    convert_with(markdown, &RoffOptions::default())
+}
+
+pub fn convert_with(markdown: &str, options: &RoffOptions) -> Result<String, ConvertError> {
+    let (man_nodes, _) = parse_page(markdown, &None)?;
+    let (preamble, chunks) = render_page(&man_nodes, options);
+    Ok(preamble + &chunks.concat())
+}
+
+/// Parse a Markdown page and pull in its options file. Also returns the
+/// Markdown line of every top-level node.
+pub fn parse_page(
+    md_content: &str,
+    file: &Option<PathBuf>,
+) -> Result<(Vec<ManNode>, Vec<Option<usize>>), ConvertError> {
+    parse_page_with_dropped(md_content, file).map(|(nodes, lines, _)| (nodes, lines))
+}
+
+/// The nodes of a page, the Markdown line of each, and what was left out.
+pub type PageWithDropped = (Vec<ManNode>, Vec<Option<usize>>, Vec<Dropped>);
+
+/// [`parse_page`], also returning the Markdown constructs that were left out
+/// because a man page cannot show them.
+pub fn parse_page_with_dropped(
+    md_content: &str,
+    file: &Option<PathBuf>,
+) -> Result<PageWithDropped, ConvertError> {
+    parse_including(md_content, file, &[])
+}
+
+/// Parse a page that is included by the files in `including`.
+fn parse_including(
+    md_content: &str,
+    file: &Option<PathBuf>,
+    including: &[PathBuf],
+) -> Result<PageWithDropped, ConvertError> {
+    let snippets = resolve_relative_to_input(file, Path::new(fragment::SNIPPET_DIR));
+    let md_content = fragment::expand(md_content, &snippets)?;
+    let md_content = unicode::nfc(&md_content);
+
+    let parse_options = ParseOptions {
+        constructs: Constructs {
+            frontmatter: true,
+            gfm_table: true,
+            gfm_strikethrough: true,
+            gfm_task_list_item: true,
+            gfm_footnote_definition: true,
+            gfm_label_start_footnote: true,
+            // Bare `https://...`, `www.` and email addresses become links.
+            gfm_autolink_literal: true,
+            // `$$` blocks are equations.
+            math_flow: true,
+            ..Constructs::default()
+        },
+        // `~/.config` and `~user` are paths, not deleted text.
+        gfm_strikethrough_single_tilde: false,
+        ..ParseOptions::gfm()
+    };
+
+    let markdown_ast = markdown::to_mdast(&md_content, &parse_options)
+        .map_err(|e| ConvertError::Markdown(e.to_string()))?;
+    let mut convert_state = ConvertState::new();
+    let (mut man_nodes, mut source_lines) =
+        convert_with_source_lines(&markdown_ast, &mut convert_state);
+    if let Some(e) = convert_state.take_error() {
+        return Err(ConvertError::Input(e, file.clone()));
+    }
+    let dropped = convert_state.take_dropped();
+
+    if let Some(options_file) = find_options_file(&man_nodes) {
+        let path = resolve_relative_to_input(file, &options_file);
+        let specs = options::load_options_file(&path)?;
+        let inserted = options::insert_options_section(&mut man_nodes, &specs);
+        let generated = std::iter::repeat_n(None, inserted.len());
+        source_lines.splice(inserted.start..inserted.start, generated);
+    }
+    include_sections(&mut man_nodes, &mut source_lines, file, including)?;
+
+    Ok((man_nodes, source_lines, dropped))
+}
+
+/// Replace every `{{include-section: FILE#SECTION}}` paragraph with the body
+/// of that section of FILE, which is looked up relative to the page and may
+/// include sections itself. The included nodes have no line in this page.
+fn include_sections(
+    man_nodes: &mut Vec<ManNode>,
+    source_lines: &mut Vec<Option<usize>>,
+    file: &Option<PathBuf>,
+    including: &[PathBuf],
+) -> Result<(), ConvertError> {
+    let mut i = 0;
+    while i < man_nodes.len() {
+        let Some((path, name)) = include::directive(&man_nodes[i]) else {
+            i += 1;
+            continue;
+        };
+        let path = resolve_relative_to_input(file, &path);
+        let canonical = |p: &Path| std::fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf());
+        let mut chain = including.to_vec();
+        chain.extend(file.as_deref().map(canonical));
+        if chain.contains(&canonical(&path)) {
+            return Err(include::IncludeError::Cycle(path).into());
+        }
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| include::IncludeError::ReadError(path.clone(), e))?;
+        let (nodes, _, _) = parse_including(&content, &Some(path.clone()), &chain)?;
+        let body = include::take_section_body(nodes, &name)
+            .ok_or(include::IncludeError::NoSection(path, name))?;
+        let len = body.len();
+        man_nodes.splice(i..i + 1, body);
+        source_lines.splice(i..i + 1, std::iter::repeat_n(None, len));
+        i += len;
+    }
+    Ok(())
+}
+
+/// Pages with at least this many top-level nodes are rendered with one thread
+/// per core; for anything smaller, starting the threads costs more than it saves.
+const PARALLEL_THRESHOLD: usize = 2000;
+
+/// Render a page, returning the preamble (preprocessor line) and the roff of
+/// every node separately.
+pub fn render_page(man_nodes: &[ManNode], roff_options: &RoffOptions) -> (String, Vec<String>) {
+    let mut preamble = roff::preprocessor_line(man_nodes, roff_options).unwrap_or_default();
+    if roff_options.stable {
+        preamble.push_str(&roff::stable_marker());
+    }
+    preamble.push_str(&roff::compat_macros(man_nodes, roff_options));
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut chunks = if man_nodes.len() < PARALLEL_THRESHOLD || threads == 1 {
+        render_nodes(man_nodes, roff_options)
+    } else {
+        render_in_parallel(man_nodes, roff_options, threads)
+    };
+    if roff_options.stable {
+        roff::normalize_stable(&mut chunks);
+    }
+    (preamble, chunks)
+}
+
+fn render_nodes(man_nodes: &[ManNode], roff_options: &RoffOptions) -> Vec<String> {
+    man_nodes
+        .iter()
+        .map(|n| n.to_roff_with(roff_options))
+        .collect()
+}
+
+/// Render batches of whole sections on separate threads. Nodes render
+/// independently of each other, so joining the batches in order gives the
+/// same output as rendering them one after the other.
+fn render_in_parallel(
+    man_nodes: &[ManNode],
+    roff_options: &RoffOptions,
+    threads: usize,
+) -> Vec<String> {
+    let batches = section_batches(man_nodes, threads);
+    thread::scope(|s| {
+        let handles = batches
+            .into_iter()
+            .map(|range| s.spawn(|| render_nodes(&man_nodes[range], roff_options)))
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    })
+}
+
+/// Split the nodes into about `count` ranges of similar size, each starting at
+/// a section heading (or the start of the page).
+fn section_batches(man_nodes: &[ManNode], count: usize) -> Vec<Range<usize>> {
+    let target = man_nodes.len().div_ceil(count);
+    let mut batches = Vec::new();
+    let mut start = 0;
+    for (i, node) in man_nodes.iter().enumerate() {
+        if i - start >= target && matches!(node, ManNode::SectionHeading { .. }) {
+            batches.push(start..i);
+            start = i;
+        }
+    }
+    batches.push(start..man_nodes.len());
+    batches
+}
+
+fn find_options_file(man_nodes: &[ManNode]) -> Option<PathBuf> {
+    man_nodes.iter().find_map(|node| match node {
+        ManNode::TitleLine(title_line) => title_line.options_file.clone(),
+        _ => None,
+    })
+}
+
+/// Paths in the frontmatter are relative to the Markdown file (or the current
+/// directory when reading from stdin).
+fn resolve_relative_to_input(file_like: &Option<PathBuf>, path: &Path) -> PathBuf {
+    match file_like.as_ref().and_then(|file| file.parent()) {
+        Some(dir) if path.is_relative() => dir.join(path),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejects_bad_input() {
+        let error = |markdown: &str| parse_page(markdown, &None).unwrap_err().to_string();
+        assert!(error("---\nsection: 1\n---\n").contains("missing field `name`"));
+        assert_eq!(
+            error("---\nname: tool\nsection: one\n---\n"),
+            "mdman: Could not parse the frontmatter at line 3. \
+             Error: section: invalid type: string \"one\", expected u8"
+        );
+        let file = Some(PathBuf::from("tool.md"));
+        let in_file = parse_page("---\nname: tool\nsection: 1\ndate: [1]\n---\n", &file);
+        assert!(
+            in_file
+                .unwrap_err()
+                .to_string()
+                .contains(" in tool.md at line 4. ")
+        );
+        assert_eq!(
+            error(&format!("# NAME\n\n{}x\n", "> ".repeat(500))),
+            "mdman: Markdown nested more than 100 levels deep at line 3"
+        );
+        let emphasis = "*".repeat(1000);
+        assert!(error(&format!("{}x{}", emphasis, emphasis)).contains("nested more than"));
+    }
+
+    #[test]
+    fn test_concurrent_conversion() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<ManNode>();
+        assert_send_sync::<RoffOptions>();
+        assert_send_sync::<ConvertError>();
+        assert_send_sync::<Box<dyn render::Renderer>>();
+
+        let pages = (0..64)
+            .map(|i| {
+                format!(
+                    "---\nname: tool{i}\nsection: 1\n---\n\n# NAME\n\ntool{i} - page {i}\n\n\
+                     # DESCRIPTION\n\nSee[^{i}] *this*.\n\n[^{i}]: Footnote {i}.\n"
+                )
+            })
+            .collect::<Vec<_>>();
+        let options = RoffOptions::default();
+        let expected = pages
+            .iter()
+            .map(|page| convert_with(page, &options).unwrap())
+            .collect::<Vec<_>>();
+        let converted = thread::scope(|s| {
+            let handles = pages
+                .chunks(8)
+                .map(|chunk| {
+                    s.spawn(|| {
+                        chunk
+                            .iter()
+                            .map(|page| convert_with(page, &options).unwrap())
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(converted, expected);
+        assert!(converted[9].contains("tool9 \\- page 9"));
+        assert!(converted[9].contains("Footnote 9\\&."));
+    }
+
+    #[test]
+    fn test_include_section() {
+        let dir = std::env::temp_dir().join(format!("mdman-include-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("shared")).unwrap();
+        std::fs::write(
+            dir.join("shared/global.md"),
+            "---\nname: global\nsection: 7\n---\n\n# GLOBAL OPTIONS\n\n\
+             - **-v**, **--verbose**\n  Say more.\n\n{{include-section: more.md#MORE}}\n\n\
+             # OTHER\n\nNot this.\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("shared/more.md"), "# MORE\n\nAnd this.\n").unwrap();
+        std::fs::write(dir.join("loop.md"), "{{include-section: loop.md#X}}\n").unwrap();
+        let page = |markdown: &str| parse_page(markdown, &Some(dir.join("tool.md")));
+        let included = page(
+            "# OPTIONS\n\n- **-n**\n  Dry run.\n\n{{include-section: shared/global.md#global options}}\n\n\
+             # SEE ALSO\n",
+        );
+        let missing = page("{{include-section: shared/global.md#NOPE}}\n");
+        let cycle = page("{{include-section: loop.md#X}}\n");
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let (nodes, lines) = included.unwrap();
+        let (_, chunks) = render_page(&nodes, &RoffOptions::default());
+        let roff = chunks.concat();
+        assert!(roff.contains("Dry run") && roff.contains("Say more") && roff.contains("And this"));
+        assert!(!roff.contains("Not this") && !roff.contains(".TH"));
+        assert_eq!(lines.len(), nodes.len());
+        assert_eq!(lines[3], None);
+        assert_eq!(
+            missing.unwrap_err().to_string(),
+            format!(
+                "mdman: No section NOPE in {}",
+                dir.join("shared/global.md").display()
+            )
+        );
+        assert_eq!(
+            cycle.unwrap_err().to_string(),
+            format!("mdman: {} includes itself", dir.join("loop.md").display())
+        );
+    }
+
+    #[test]
+    fn test_parallel_rendering() {
+        let mut markdown = String::new();
+        for section in 0..200 {
+            markdown.push_str(&format!("# SECTION {}\n\n", section));
+            for i in 0..10 {
+                markdown.push_str(&format!("Paragraph *{}* of [{}](url).\n\n", i, section));
+            }
+        }
+        let (nodes, _) = parse_page(&markdown, &None).unwrap();
+        assert!(nodes.len() >= PARALLEL_THRESHOLD);
+        let options = RoffOptions::default();
+
+        let batches = section_batches(&nodes, 4);
+        assert_eq!(batches.len(), 4);
+        assert!(
+            batches
+                .iter()
+                .all(|b| matches!(nodes[b.start], ManNode::SectionHeading { .. }))
+        );
+        assert_eq!(
+            render_in_parallel(&nodes, &options, 4),
+            render_nodes(&nodes, &options)
+        );
+    }
+}
@@ -0,0 +1,217 @@
+//! `mdman lint --check-urls`: whether the web links of the pages still lead
+//! somewhere. The requests are made with curl, several at a time.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::batch;
+use crate::external::find_executable;
+
+/// How long a single request may take by default.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Requests made at the same time. They mostly wait for the network, so this
+/// does not depend on the number of CPUs.
+const JOBS: usize = 8;
+
+/// How long a URL that worked is not asked for again.
+const CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug)]
+pub enum LinkError {
+    NoCurl,
+    CacheError(PathBuf, io::Error),
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkError::NoCurl => write!(f, "mdman: --check-urls needs curl in PATH"),
+            LinkError::CacheError(file, e) => write!(
+                f,
+                "mdman: Could not write the URL cache {}. Error: {}",
+                file.display(),
+                e
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LinkError {}
+
+/// What became of a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Status {
+    Live,
+    /// The server answered with this error status, e.g. 404.
+    Dead(u16),
+    /// No answer, with curl's reason.
+    Unreachable(String),
+}
+
+/// How links are checked.
+#[derive(Debug)]
+pub struct Checker {
+    pub timeout: Duration,
+    /// URLs starting with one of these are taken to be live without asking,
+    /// e.g. hosts only reachable from inside a company.
+    pub allow: Vec<String>,
+    /// A file of the URLs that worked and when, so they are not asked for
+    /// again for a day.
+    pub cache: Option<PathBuf>,
+}
+
+impl Checker {
+    /// The status of each of `urls`, in order.
+    pub fn check(&self, urls: &[String]) -> Result<Vec<Status>, LinkError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let mut cache = self
+            .cache
+            .as_ref()
+            .map(|file| read_cache(&fs::read_to_string(file).unwrap_or_default()))
+            .unwrap_or_default();
+        cache.retain(|(_, checked)| now.saturating_sub(*checked) < CACHE_TTL.as_secs());
+        let known = |url: &String| {
+            self.allow
+                .iter()
+                .any(|prefix| url.starts_with(prefix.as_str()))
+                || cache.iter().any(|(cached, _)| cached == url)
+        };
+
+        let to_fetch = urls.iter().filter(|url| !known(url)).collect::<Vec<_>>();
+        if !to_fetch.is_empty() && find_executable("curl").is_none() {
+            return Err(LinkError::NoCurl);
+        }
+        let fetched = batch::map(&to_fetch, JOBS, |url| fetch(url, self.timeout));
+        let mut fetched = to_fetch.into_iter().zip(fetched).collect::<Vec<_>>();
+        let statuses = urls
+            .iter()
+            .map(|url| match fetched.iter().position(|(u, _)| *u == url) {
+                Some(i) => fetched[i].1.clone(),
+                None => Status::Live,
+            })
+            .collect();
+
+        if let Some(file) = &self.cache {
+            fetched.retain(|(_, status)| *status == Status::Live);
+            cache.extend(fetched.into_iter().map(|(url, _)| (url.clone(), now)));
+            fs::write(file, write_cache(&cache))
+                .map_err(|e| LinkError::CacheError(file.clone(), e))?;
+        }
+        Ok(statuses)
+    }
+}
+
+/// Arguments for curl to request `url` with `method` and print the status
+/// code. With `--globoff` brackets and braces in the URL are not taken as
+/// curl's ranges, and after `--` a URL starting with `-` is not an option.
+fn curl_args(url: &str, timeout: Duration, method: &[&str]) -> Vec<String> {
+    let null = if cfg!(windows) { "NUL" } else { "/dev/null" };
+    let mut args = vec!["--silent", "--show-error", "--location", "--globoff"];
+    args.extend(["--output", null, "--write-out", "%{http_code}"]);
+    let max_time = timeout.as_secs().max(1).to_string();
+    args.extend(["--max-time", &max_time]);
+    args.extend(method);
+    args.extend(["--", url]);
+    args.into_iter().map(String::from).collect()
+}
+
+/// Ask for `url` with a HEAD request, and with a GET for the first byte if
+/// the server does not like that.
+fn fetch(url: &str, timeout: Duration) -> Status {
+    let request = |method: &[&str]| {
+        let output = Command::new("curl")
+            .args(curl_args(url, timeout, method))
+            .output();
+        match output {
+            Ok(output) if output.status.success() => {
+                let code = String::from_utf8_lossy(&output.stdout);
+                match code.trim().parse::<u16>() {
+                    Ok(code) if code < 400 => Status::Live,
+                    Ok(code) => Status::Dead(code),
+                    Err(_) => Status::Unreachable(format!("no status from the server: {}", code)),
+                }
+            }
+            Ok(output) => {
+                let error = String::from_utf8_lossy(&output.stderr);
+                let error = error.trim().trim_start_matches("curl: ");
+                Status::Unreachable(error.to_string())
+            }
+            Err(e) => Status::Unreachable(e.to_string()),
+        }
+    };
+    match request(&["--head"]) {
+        Status::Dead(_) => request(&["--range", "0-0"]),
+        status => status,
+    }
+}
+
+/// The lines `URL<tab>seconds since the epoch` of a cache file; lines that
+/// are not are left out.
+fn read_cache(text: &str) -> Vec<(String, u64)> {
+    text.lines()
+        .filter_map(|line| {
+            let (url, checked) = line.split_once('\t')?;
+            Some((url.to_string(), checked.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+fn write_cache(entries: &[(String, u64)]) -> String {
+    let mut entries = entries.to_vec();
+    entries.sort();
+    entries
+        .iter()
+        .map(|(url, checked)| format!("{}\t{}\n", url, checked))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache() {
+        let entries = vec![
+            ("https://b.example".to_string(), 20),
+            ("https://a.example".to_string(), 10),
+        ];
+        let text = write_cache(&entries);
+        assert_eq!(text, "https://a.example\t10\nhttps://b.example\t20\n");
+        assert_eq!(
+            read_cache(&(text + "garbage\nhttps://c.example\tsoon\n")),
+            [
+                ("https://a.example".to_string(), 10),
+                ("https://b.example".to_string(), 20)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_allowed_urls_are_not_fetched() {
+        let checker = Checker {
+            timeout: DEFAULT_TIMEOUT,
+            allow: vec!["https://intranet.example/".into()],
+            cache: None,
+        };
+        let statuses = checker
+            .check(&["https://intranet.example/wiki".into()])
+            .unwrap();
+        assert_eq!(statuses, [Status::Live]);
+    }
+
+    #[test]
+    fn test_curl_args_take_the_url_literally() {
+        let url = "https://example.com/-/a[1]{b}";
+        let args = curl_args(url, DEFAULT_TIMEOUT, &["--head"]);
+        assert!(args.contains(&"--globoff".to_string()));
+        assert_eq!(args[args.len() - 3..], ["--head", "--", url]);
+    }
+}
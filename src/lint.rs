@@ -0,0 +1,442 @@
+//! What `mdman lint` reports about single pages and about a set of pages that
+//! are shipped together.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Serialize, Serializer};
+
+use crate::info::{self, ManReference};
+use crate::links::{Checker, LinkError, Status};
+use mdman::man_node::{ManNode, TitleLine, section_range, title_line};
+
+/// One problem found by a check.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    #[serde(serialize_with = "serialize_path")]
+    pub file: PathBuf,
+    /// The Markdown line the problem is on, if it is on one.
+    pub line: Option<usize>,
+    /// Short name of the check, e.g. `orphan`.
+    pub check: &'static str,
+    pub message: String,
+}
+
+/// A path as it is displayed, also when it is not valid UTF-8.
+fn serialize_path<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&path.display())
+}
+
+/// A parsed page, the Markdown line of each of its nodes and the file it came
+/// from.
+pub struct Page<'a> {
+    pub file: &'a Path,
+    pub nodes: &'a [ManNode],
+    pub lines: &'a [Option<usize>],
+}
+
+impl Page<'_> {
+    fn finding(&self, index: Option<usize>, check: &'static str, message: String) -> Finding {
+        Finding {
+            file: self.file.to_path_buf(),
+            line: index.and_then(|i| self.lines.get(i).copied().flatten()),
+            check,
+            message,
+        }
+    }
+}
+
+/// Sections in the order man-pages(7) gives them. Other sections can go
+/// anywhere.
+const SECTION_ORDER: &[&str] = &[
+    "NAME",
+    "LIBRARY",
+    "SYNOPSIS",
+    "CONFIGURATION",
+    "DESCRIPTION",
+    "OPTIONS",
+    "EXIT STATUS",
+    "RETURN VALUE",
+    "ERRORS",
+    "ENVIRONMENT",
+    "FILES",
+    "ATTRIBUTES",
+    "VERSIONS",
+    "STANDARDS",
+    "HISTORY",
+    "NOTES",
+    "CAVEATS",
+    "BUGS",
+    "EXAMPLES",
+    "AUTHORS",
+    "REPORTING BUGS",
+    "COPYRIGHT",
+    "SEE ALSO",
+];
+
+/// Checks of the conventions for a single page: the NAME section reads
+/// `name - description`, the sections come in the usual order with their
+/// headings in capitals, and SEE ALSO is a list of `name(section)`.
+pub fn page(page: &Page) -> Vec<Finding> {
+    let mut findings = name_section(page);
+    findings.extend(section_order(page));
+    findings.extend(heading_case(page));
+    findings.extend(see_also(page));
+    findings
+}
+
+fn name_section(page: &Page) -> Vec<Finding> {
+    let Some(range) = section_range(page.nodes, "NAME") else {
+        let message = "the page has no NAME section".to_string();
+        return vec![page.finding(None, "name-section", message)];
+    };
+    let heading = Some(range.start);
+    let text = section_text(&page.nodes[range.start + 1..range.end]);
+    let Some((names, description)) = text.split_once(" - ") else {
+        let message = format!("NAME should read \"name - description\", not \"{}\"", text);
+        return vec![page.finding(heading, "name-section", message)];
+    };
+    let names = names.split(',').map(str::trim).collect::<Vec<_>>();
+    if names
+        .iter()
+        .any(|n| n.is_empty() || n.contains(char::is_whitespace))
+        || description.trim().is_empty()
+    {
+        let message = format!("NAME should read \"name - description\", not \"{}\"", text);
+        return vec![page.finding(heading, "name-section", message)];
+    }
+    match title_line(page.nodes) {
+        Some(title) if !names.contains(&title.name.as_str()) => {
+            let message = format!(
+                "NAME lists {}, but the frontmatter name is {}",
+                names.join(", "),
+                title.name
+            );
+            vec![page.finding(heading, "name-section", message)]
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// The text of a section on one line.
+fn section_text(nodes: &[ManNode]) -> String {
+    let text = nodes.iter().map(ManNode::plain_text).collect::<Vec<_>>();
+    text.join(" ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn section_order(page: &Page) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    // The latest section seen so far in the canonical order.
+    let mut latest: Option<(usize, &str)> = None;
+    for (i, node) in page.nodes.iter().enumerate() {
+        let ManNode::SectionHeading { title, .. } = node else {
+            continue;
+        };
+        let Some(rank) = SECTION_ORDER
+            .iter()
+            .position(|s| s.eq_ignore_ascii_case(title))
+        else {
+            continue;
+        };
+        match latest {
+            Some((latest_rank, latest_title)) if rank < latest_rank => {
+                let message = format!("{} should come before {}", title, latest_title);
+                findings.push(page.finding(Some(i), "section-order", message));
+            }
+            _ => latest = Some((rank, title)),
+        }
+    }
+    findings
+}
+
+fn heading_case(page: &Page) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for (i, node) in page.nodes.iter().enumerate() {
+        if let ManNode::SectionHeading { title, .. } = node
+            && *title != title.to_uppercase()
+        {
+            let message = format!("section heading \"{}\" should be in capitals", title);
+            findings.push(page.finding(Some(i), "heading-case", message));
+        }
+    }
+    findings
+}
+
+/// Every entry of SEE ALSO must be a `name(section)`, separated by commas,
+/// and listed only once.
+fn see_also(page: &Page) -> Vec<Finding> {
+    let Some(range) = section_range(page.nodes, "SEE ALSO") else {
+        return Vec::new();
+    };
+    let mut findings = Vec::new();
+    let mut seen: Vec<String> = Vec::new();
+    for i in range.start + 1..range.end {
+        let text = section_text(&page.nodes[i..i + 1]);
+        for entry in text.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let well_formed = match info::references_in(entry).as_slice() {
+                [reference] => format!("{}({})", reference.name, reference.section) == entry,
+                _ => false,
+            };
+            if !well_formed {
+                let message = format!(
+                    "SEE ALSO entry \"{}\" is not of the form name(section)",
+                    entry
+                );
+                findings.push(page.finding(Some(i), "see-also", message));
+            } else if seen.iter().any(|s| s == entry) {
+                let message = format!("{} is listed twice in SEE ALSO", entry);
+                findings.push(page.finding(Some(i), "see-also", message));
+            } else {
+                seen.push(entry.to_string());
+            }
+        }
+    }
+    findings
+}
+
+type Field = fn(&TitleLine) -> Option<&str>;
+
+/// Frontmatter fields that every page of a project should agree on.
+const SHARED_FIELDS: &[(&str, Field)] = &[
+    ("left-footer", |t| t.left_footer.as_deref()),
+    ("center-footer", |t| t.center_footer.as_deref()),
+    ("version", |t| t.version.as_deref()),
+];
+
+/// Checks across all pages of a project: the shared frontmatter fields must
+/// match, and every page must be named in the SEE ALSO section of another.
+pub fn project(pages: &[Page]) -> Vec<Finding> {
+    let mut findings = shared_fields(pages);
+    findings.extend(orphans(pages));
+    findings
+}
+
+/// Pages whose value of a shared field differs from the one most pages use
+/// (the first page's on a tie). A missing field counts as a value.
+fn shared_fields(pages: &[Page]) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for (field, get) in SHARED_FIELDS {
+        let values = pages
+            .iter()
+            .map(|page| title_line(page.nodes).and_then(get))
+            .collect::<Vec<_>>();
+        let count = |value: &Option<&str>| values.iter().filter(|v| *v == value).count();
+        let most = values.iter().map(count).max().unwrap_or_default();
+        let common = values.iter().find(|v| count(v) == most).copied().flatten();
+        for (page, value) in pages.iter().zip(&values) {
+            if *value != common {
+                findings.push(Finding {
+                    file: page.file.to_path_buf(),
+                    line: None,
+                    check: "shared-field",
+                    message: format!(
+                        "{} is {}, other pages have {}",
+                        field,
+                        describe(*value),
+                        describe(common)
+                    ),
+                });
+            }
+        }
+    }
+    findings
+}
+
+fn describe(value: Option<&str>) -> String {
+    value.map_or_else(|| "not set".into(), |v| format!("\"{}\"", v))
+}
+
+/// Pages that no other page lists under SEE ALSO. A single page has nothing
+/// to be referenced from, so it is never an orphan.
+fn orphans(pages: &[Page]) -> Vec<Finding> {
+    if pages.len() < 2 {
+        return Vec::new();
+    }
+    let see_also = pages
+        .iter()
+        .map(|page| see_also_references(page.nodes))
+        .collect::<Vec<_>>();
+    let mut findings = Vec::new();
+    for (i, page) in pages.iter().enumerate() {
+        let Some(title) = title_line(page.nodes) else {
+            continue;
+        };
+        let reference = ManReference {
+            name: title.name.clone(),
+            section: title.section.to_string(),
+        };
+        let referenced = see_also
+            .iter()
+            .enumerate()
+            .any(|(j, references)| j != i && references.contains(&reference));
+        if !referenced {
+            findings.push(Finding {
+                file: page.file.to_path_buf(),
+                line: None,
+                check: "orphan",
+                message: format!(
+                    "{}({}) is not listed in the SEE ALSO section of any other page",
+                    title.name, title.section
+                ),
+            });
+        }
+    }
+    findings
+}
+
+/// Web links of the pages that lead nowhere. Each URL is asked for once,
+/// however many pages link to it.
+pub fn dead_urls(pages: &[Page], checker: &Checker) -> Result<Vec<Finding>, LinkError> {
+    let mut links = Vec::new();
+    for page in pages {
+        for (i, node) in page.nodes.iter().enumerate() {
+            node.walk(&mut |n| {
+                if let ManNode::Uri { url, .. } = n
+                    && (url.starts_with("http://") || url.starts_with("https://"))
+                {
+                    links.push((page, i, url.clone()));
+                }
+            });
+        }
+    }
+    let mut urls = links
+        .iter()
+        .map(|(_, _, url)| url.clone())
+        .collect::<Vec<_>>();
+    urls.sort();
+    urls.dedup();
+    let statuses = checker.check(&urls)?;
+
+    let mut findings = Vec::new();
+    for (page, i, url) in &links {
+        let message = match &statuses[urls.binary_search(url).unwrap()] {
+            Status::Live => continue,
+            Status::Dead(code) => format!("{} returns {}", url, code),
+            Status::Unreachable(reason) => format!("{} cannot be reached: {}", url, reason),
+        };
+        findings.push(page.finding(Some(*i), "dead-url", message));
+    }
+    Ok(findings)
+}
+
+fn see_also_references(nodes: &[ManNode]) -> Vec<ManReference> {
+    let Some(start) = nodes.iter().position(|node| {
+        matches!(node, ManNode::SectionHeading { title, .. } if title.eq_ignore_ascii_case("SEE ALSO"))
+    }) else {
+        return Vec::new();
+    };
+    nodes[start + 1..]
+        .iter()
+        .take_while(|node| !matches!(node, ManNode::SectionHeading { .. }))
+        .flat_map(|node| info::references_in(&node.plain_text()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mdman::parse_page;
+
+    type Parsed<'a> = Vec<(&'a Path, Vec<ManNode>, Vec<Option<usize>>)>;
+
+    fn parse<'a>(pages: &[(&'a str, &str)]) -> Parsed<'a> {
+        pages
+            .iter()
+            .map(|(file, markdown)| {
+                let (nodes, lines) = parse_page(markdown, &None).unwrap();
+                (Path::new(*file), nodes, lines)
+            })
+            .collect()
+    }
+
+    fn as_pages<'a>(parsed: &'a Parsed) -> Vec<Page<'a>> {
+        parsed
+            .iter()
+            .map(|(file, nodes, lines)| Page { file, nodes, lines })
+            .collect()
+    }
+
+    fn show(f: &Finding) -> String {
+        match f.line {
+            Some(line) => format!("{}:{}: {}: {}", f.file.display(), line, f.check, f.message),
+            None => format!("{}: {}: {}", f.file.display(), f.check, f.message),
+        }
+    }
+
+    #[test]
+    fn test_page_checks() {
+        let findings = |markdown: &str| {
+            let parsed = parse(&[("tool.md", markdown)]);
+            page(&as_pages(&parsed)[0])
+                .iter()
+                .map(show)
+                .collect::<Vec<_>>()
+        };
+        let good = "---\nname: tool\nsection: 1\n---\n\n# NAME\n\n**tool**, **tl** - do things\n\n\
+                    # SYNOPSIS\n\ntool\n\n# EXTRA\n\nx\n\n# SEE ALSO\n\nls(1), **grep**(1)\n";
+        assert_eq!(findings(good), Vec::<String>::new());
+
+        assert_eq!(
+            findings("# DESCRIPTION\n\nx\n"),
+            ["tool.md: name-section: the page has no NAME section"]
+        );
+        assert_eq!(
+            findings("# NAME\n\ntool does things\n"),
+            [
+                "tool.md:1: name-section: NAME should read \"name - description\", not \"tool does things\""
+            ]
+        );
+        assert_eq!(
+            findings("---\nname: tool\nsection: 1\n---\n\n# NAME\n\nother - x\n"),
+            ["tool.md:6: name-section: NAME lists other, but the frontmatter name is tool"]
+        );
+        assert_eq!(
+            findings(
+                "# NAME\n\ntool - x\n\n# EXAMPLES\n\nx\n\n# Options\n\nx\n\n\
+                 # SEE ALSO\n\nls(1), grep, ls(1).\n\nls(1)\n"
+            ),
+            [
+                "tool.md:9: section-order: Options should come before EXAMPLES",
+                "tool.md:9: heading-case: section heading \"Options\" should be in capitals",
+                "tool.md:15: see-also: SEE ALSO entry \"grep\" is not of the form name(section)",
+                "tool.md:15: see-also: SEE ALSO entry \"ls(1).\" is not of the form name(section)",
+                "tool.md:17: see-also: ls(1) is listed twice in SEE ALSO",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_project_checks() {
+        let pages = [
+            (
+                "tool.md",
+                "---\nname: tool\nsection: 1\nversion: 1.2\ncenter-footer: Tool Manual\n---\n\n\
+                 # NAME\n\ntool - do things\n\n# SEE ALSO\n\n**tool.conf**(5), ls(1)\n",
+            ),
+            (
+                "tool.conf.md",
+                "---\nname: tool.conf\nsection: 5\nversion: 1.2\ncenter-footer: Tool Manual\n---\n\n\
+                 # NAME\n\ntool.conf - settings\n\n# DESCRIPTION\n\nRead by tool(1).\n",
+            ),
+            (
+                "tool-extra.md",
+                "---\nname: tool-extra\nsection: 1\nversion: 1.1\ncenter-footer: Tool Manual\n---\n\n\
+                 # NAME\n\ntool-extra - more\n\n# SEE ALSO\n\ntool(1)\n",
+            ),
+        ];
+        let parsed = parse(&pages);
+        let pages = as_pages(&parsed);
+        let findings = project(&pages).iter().map(show).collect::<Vec<_>>();
+        assert_eq!(
+            findings,
+            [
+                "tool-extra.md: shared-field: version is \"1.1\", other pages have \"1.2\"",
+                "tool-extra.md: orphan: tool-extra(1) is not listed in the SEE ALSO section of any other page",
+            ]
+        );
+
+        assert!(project(&pages[..1]).is_empty());
+    }
+}
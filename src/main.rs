@@ -1,24 +1,73 @@
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use std::io::{self, IsTerminal, Read, stdout};
-use std::process::{self, Command, Stdio};
-use std::{fs, io::Write, path::PathBuf};
+use std::process::{self, Command};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use std::{fs, io::Write, path::Path, path::PathBuf};
 
-use clap::{CommandFactory, Parser};
-use man_node::{ConvertState, ManNode, convert_markdown_node};
-use markdown::Constructs;
-use markdown::ParseOptions;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use mdman::ansi::AnsiRenderer;
+use mdman::html::{HtmlOptions, HtmlRenderer};
+use mdman::man_node::{self, ManNode, TableStyle, UrlStyle};
+use mdman::render::{Format, Renderer};
+use mdman::roff::{
+    self, DEFAULT_BLOCK_WIDTH, EscapeProfile, ManRenderer, RoffOptions, SoftBreaks,
+    TargetFormatter, WideBlocks,
+};
+use mdman::{ansi, flags, fragment, html, parse_page, parse_page_with_dropped, render_page};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
 
-mod man_node;
-mod roff;
-use crate::roff::ToRoff;
+use crate::manifest::Layout;
+use crate::profile::Profile;
+use crate::snippet::BuildSystem;
 
-// const TBL_PREPROCESSOR_INDICATOR: &str = "'\\\" t";
+mod batch;
+mod completions;
+mod corpus;
+mod diff;
+mod examples;
+mod external;
+mod glob;
+mod gzip;
+mod info;
+mod links;
+mod lint;
+mod manifest;
+mod portability;
+mod profile;
+mod snippet;
+mod suite;
+mod validate;
+
+/// `eprintln!` for what is said about a page, which stays with the page when
+/// several are converted at once (see [`batch`]).
+macro_rules! page_eprintln {
+    ($($arg:tt)*) => { batch::eprint(format_args!($($arg)*)) };
+}
+
+/// `println!` for the output of a page, see [`page_eprintln`].
+macro_rules! page_println {
+    ($($arg:tt)*) => { batch::print(format_args!($($arg)*)) };
+}
 
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, disable_version_flag = true)]
 struct Args {
-    /// Markdown file to convert.
-    file: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Option<Commands>,
+    /// Markdown files to convert (stdin if none); patterns like docs/*.md are expanded.
+    files: Vec<PathBuf>,
+    /// Pages converted at the same time [default: one per CPU].
+    #[arg(short, long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    jobs: Option<u16>,
+    /// Convert every .md file below the directories among FILES, into manN directories by default.
+    #[arg(short, long, conflicts_with_all = ["output", "stdout", "pager", "suite"])]
+    recursive: bool,
+    /// Convert the pages again whenever one of FILES changes, until interrupted; with --pager, refresh the preview.
+    #[arg(short, long, conflicts_with_all = ["check", "suite"])]
+    watch: bool,
     /// Override section number for output (e.g., 1 for general commands).
     #[arg(short, long, conflicts_with = "pager")]
     section: Option<u8>,
@@ -28,75 +77,1071 @@ struct Args {
     /// Output filename (Overrides automatic naming).
     #[arg(short, long, conflicts_with = "stdout")]
     output: Option<PathBuf>,
+    /// Compress man pages written to files with gzip, naming them e.g. tool.1.gz.
+    #[arg(short = 'z', long)]
+    gzip: bool,
+    /// Write automatically named files into DIR, creating it if needed.
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["output", "stdout", "pager"])]
+    output_dir: Option<PathBuf>,
+    /// Put man pages in a manN directory per section (sections) or not (flat) [default: flat].
+    #[arg(long, value_enum, value_name = "LAYOUT", conflicts_with_all = ["output", "stdout", "pager"])]
+    layout: Option<Layout>,
+    /// Date for pages without one in their frontmatter.
+    #[arg(long, value_enum, value_name = "SOURCE", default_value_t = DateFrom::Today)]
+    date_from: DateFrom,
     /// Preview the generated man page in a pager. (Overrides --output and --stdout).
     #[arg(short, long, conflicts_with = "output")]
     #[arg(conflicts_with = "stdout")]
     pager: bool,
+    /// Generate a page for a command and each of its subcommands from a YAML/JSON spec.
+    #[arg(long, value_name = "SPEC", conflicts_with_all = ["files", "stdout", "output", "pager"])]
+    suite: Option<PathBuf>,
+    /// Write the roff in a style that stays the same across mdman versions, see mdman(1).
+    #[arg(long)]
+    #[arg(conflicts_with_all = ["escape_profile", "soft_breaks", "target_formatter", "no_tbl_line"])]
+    stable_output: bool,
+    /// Which characters get escaped in prose [default: aggressive].
+    #[arg(long, value_enum, value_name = "PROFILE")]
+    escape_profile: Option<EscapeProfile>,
+    /// How line breaks within paragraphs are rendered.
+    #[arg(long, value_enum, value_name = "POLICY", default_value_t = SoftBreaks::Keep)]
+    soft_breaks: SoftBreaks,
+    /// What to do with code blocks wider than --block-width.
+    #[arg(long, value_enum, value_name = "MODE", default_value_t = WideBlocks::Ignore)]
+    wide_blocks: WideBlocks,
+    /// Column limit for code and literal blocks, and the width tables are fitted into.
+    #[arg(long, value_name = "COLUMNS", default_value_t = DEFAULT_BLOCK_WIDTH)]
+    block_width: usize,
+    /// Add PDF bookmarks for sections and subsections (used by groff -Tpdf).
+    #[arg(long)]
+    pdf_bookmarks: bool,
+    /// Draw a QR code below long web links in PDF and PostScript output (groff only).
+    #[arg(long)]
+    pdf_qr: bool,
+    /// Define fallbacks for .UR/.UE and .EX/.EE for man implementations without them.
+    #[arg(long)]
+    compat_macros: bool,
+    /// Set block quotes in italics.
+    #[arg(long)]
+    italic_quotes: bool,
+    /// Leave tbl out of the preprocessor line ('\" t) of pages with tables.
+    #[arg(long)]
+    no_tbl_line: bool,
+    /// Text for the middle of the page footer in print output, instead of the date.
+    #[arg(long, value_name = "TEXT")]
+    footer: Option<String>,
+    /// Leave the page numbers out of the footer in print output.
+    #[arg(long)]
+    no_page_numbers: bool,
+    /// Number subsections and the headings below them (2.1, 2.1.1) by their position.
+    #[arg(long)]
+    numbered_headings: bool,
+    /// Add a CONTENTS section after NAME that lists all sections and subsections.
+    #[arg(long)]
+    toc: bool,
+    /// Show links where they are (inline), as numbers with the URLs in a NOTES section (footnotes), or only their text (omit).
+    #[arg(long, value_enum, value_name = "STYLE", default_value_t = UrlStyle::Inline)]
+    urls: UrlStyle,
+    /// Do not put a table of the subcommands at the top of a SUBCOMMANDS section.
+    #[arg(long)]
+    no_subcommand_table: bool,
+    /// Frame of tables, over the frontmatter's table-style [default: allbox].
+    #[arg(long, value_enum, value_name = "STYLE")]
+    table_style: Option<TableStyle>,
+    /// Tune fonts, macros and requests for one formatter instead of a mix.
+    #[arg(long, value_enum, value_name = "FORMATTER")]
+    target_formatter: Option<TargetFormatter>,
+    /// Write section headings in capitals.
+    #[arg(long)]
+    uppercase_sections: bool,
+    /// Settings for the pages of a distribution's packages; other flags win over them.
+    #[arg(long, value_enum, value_name = "SYSTEM")]
+    profile: Option<Profile>,
+    /// List constructs that render differently on mandoc, groff and nroff instead of converting.
+    #[arg(long, conflicts_with_all = ["suite", "pager", "output", "validate"])]
+    portability_report: bool,
+    /// Also write the flags documented in OPTIONS as JSON to FILE ("-" for stdout only).
+    #[arg(long, value_name = "FILE", conflicts_with = "suite")]
+    export_options: Option<PathBuf>,
+    /// Print only the section NAME (e.g. OPTIONS) to stdout.
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["suite", "pager", "output"])]
+    section_only: Option<String>,
+    /// Check the generated page with mandoc or groff and report their warnings.
+    #[arg(long, conflicts_with = "suite")]
+    validate: bool,
+    /// Avoid what Debian's lintian warns about: fill in every .TH field and always escape "-".
+    #[arg(long)]
+    lintian: bool,
+    /// Format the page with "man --warnings" as lintian does, report what it says and fail if anything.
+    #[arg(long, conflicts_with_all = ["suite", "pager", "postprocess"])]
+    lintian_check: bool,
+    /// Fail instead of leaving out Markdown a man page cannot show (images, HTML, ...).
+    #[arg(long, conflicts_with = "suite")]
+    strict: bool,
+    /// Do not warn about Markdown left out of the page.
+    #[arg(short, long, conflicts_with = "strict")]
+    quiet: bool,
+    /// Write nothing; fail if the file the page would be written to is missing or out of date.
+    #[arg(long)]
+    #[arg(conflicts_with_all = ["suite", "pager", "stdout", "postprocess", "portability_report", "section_only"])]
+    check: bool,
+    /// Pipe the generated page through COMMAND (e.g. "groff -man -Tpdf") and write its output.
+    #[arg(long, value_name = "COMMAND")]
+    #[arg(conflicts_with_all = ["suite", "pager", "section_only", "portability_report"])]
+    postprocess: Option<String>,
+    /// Seconds an external formatter (--postprocess, --pager, --validate) may run (0: no limit).
+    #[arg(long, value_name = "SECONDS", default_value_t = external::DEFAULT_TIMEOUT.as_secs())]
+    timeout: u64,
+    /// Put HTML pages into FILE, at its {{content}}; {{title}}, {{lang}} and {{head}} are filled in too.
+    #[arg(long, value_name = "FILE")]
+    html_template: Option<PathBuf>,
+    /// Link the stylesheet URL from HTML pages.
+    #[arg(long, value_name = "URL")]
+    css: Option<String>,
+    /// Write all HTML pages into one file with an index of them (index.html unless --output or --stdout).
+    #[arg(long, conflicts_with_all = ["pager", "section_only", "export_options", "portability_report"])]
+    single_file: bool,
+    /// What to generate from the page.
+    #[arg(long, value_enum, default_value_t = Format::Man)]
+    #[arg(conflicts_with_all = ["suite", "pager", "section_only", "validate", "portability_report"])]
+    format: Format,
+    /// Print additional information (with --version: features, formats and formatters; with several files: the time taken).
+    #[arg(short, long)]
+    verbose: bool,
+    /// Print version.
+    #[arg(short = 'V', long)]
+    version: bool,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum DateFrom {
+    /// The day of the conversion.
+    Today,
+    /// The day the source file was last modified.
+    Mtime,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Convert every .md file under DIR and compare it with its NAME.expected.N sibling.
+    TestCorpus {
+        /// Directory with the Markdown sources and expected outputs.
+        dir: PathBuf,
+    },
+    /// Print a page's frontmatter, output file name, sections and references without converting it.
+    Info {
+        /// Markdown source of the page.
+        file: PathBuf,
+        /// Print JSON instead of one line per item.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Write a shell completion script for the flags documented in a page's OPTIONS section.
+    ExportCompletions {
+        /// Markdown source of the page.
+        file: PathBuf,
+        /// Shell to write the script for.
+        #[arg(long, value_enum)]
+        shell: completions::Shell,
+    },
+    /// Convert the pages listed in a project manifest.
+    Build {
+        /// Manifest of the project.
+        #[arg(long, default_value = manifest::MANIFEST_FILE)]
+        manifest: PathBuf,
+        /// Print the rules that install the man pages with SYSTEM instead of converting them.
+        #[arg(long, value_enum, value_name = "SYSTEM")]
+        emit_install_snippet: Option<BuildSystem>,
+    },
+    /// Convert pages and install them into the man directories below a prefix.
+    Install {
+        /// Markdown sources of the pages.
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Install into PREFIX/share/man/manN [default: the prefix in mdman.toml, or /usr/local].
+        #[arg(long, value_name = "PREFIX")]
+        prefix: Option<PathBuf>,
+        /// Compress the installed pages with gzip.
+        #[arg(long)]
+        gzip: bool,
+        /// Do not update the whatis database with mandb or makewhatis afterwards.
+        #[arg(long)]
+        no_index: bool,
+    },
+    /// Show what changed between two versions of a page, section by section.
+    Diff {
+        /// The earlier version.
+        old: PathBuf,
+        /// The later version.
+        new: PathBuf,
+        /// Compare the text as readers see it instead of the roff.
+        #[arg(long)]
+        rendered: bool,
+    },
+    /// Print the commands of the console and sh code blocks in a page's EXAMPLES section.
+    ExtractExamples {
+        /// Markdown source of the page.
+        file: PathBuf,
+        /// Print a shell script that runs each code block as a test instead.
+        #[arg(long)]
+        shell: bool,
+    },
+    /// Check the pages for the usual man page conventions, and that pages shipped together agree on their shared frontmatter and refer to each other.
+    Lint {
+        /// Markdown sources of the pages.
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Print the findings as a JSON array, for CI.
+        #[arg(long)]
+        json: bool,
+        /// Also report web links that return an error or cannot be reached (needs curl).
+        #[arg(long)]
+        check_urls: bool,
+        /// Take URLs starting with PREFIX to be live without asking (repeatable).
+        #[arg(long, value_name = "PREFIX", requires = "check_urls")]
+        allow_url: Vec<String>,
+        /// Remember in FILE which URLs worked, and ask for them again only after a day.
+        #[arg(long, value_name = "FILE", requires = "check_urls")]
+        url_cache: Option<PathBuf>,
+        /// Seconds a request for a URL may take.
+        #[arg(long, value_name = "SECONDS", requires = "check_urls")]
+        #[arg(default_value_t = links::DEFAULT_TIMEOUT.as_secs())]
+        url_timeout: u64,
+    },
+}
+
+/// Optional cargo features compiled into this binary.
+const FEATURES: &[&str] = &[];
+
 fn main() {
     let args = Args::parse();
 
-    let md_content = match get_md_content(&args.file) {
-        Ok(md) => md,
+    if args.version {
+        print_version(args.verbose);
+        return;
+    }
+
+    let profile = args.profile.map(Profile::settings);
+    let lintian = args.lintian || profile.is_some_and(|p| p.lintian);
+    let escape_profile = args
+        .escape_profile
+        .or(profile.map(|p| p.escape_profile))
+        .unwrap_or_default();
+    let roff_options = RoffOptions {
+        // lintian wants every `-` that is not a hyphen written as `\-`; the
+        // mandoc profile is minimal plus that.
+        escape_profile: match escape_profile {
+            EscapeProfile::Minimal if lintian => EscapeProfile::Mandoc,
+            profile => profile,
+        },
+        soft_breaks: args.soft_breaks,
+        wide_blocks: args.wide_blocks,
+        block_width: args.block_width,
+        pdf_bookmarks: args.pdf_bookmarks,
+        pdf_qr: args.pdf_qr,
+        compat_macros: args.compat_macros || profile.is_some_and(|p| p.compat_macros),
+        target: args.target_formatter.or(profile.map(|p| p.target)),
+        italic_quotes: args.italic_quotes,
+        tbl_line: !args.no_tbl_line,
+        footer: args.footer.clone(),
+        page_numbers: !args.no_page_numbers,
+        table_style: args.table_style,
+        lintian,
+        stable: false,
+    };
+    let roff_options = match args.stable_output {
+        true => roff_options.stable(),
+        false => roff_options,
+    };
+
+    match &args.command {
+        Some(Commands::TestCorpus { dir }) => {
+            handle_test_corpus(dir, &roff_options);
+            return;
+        }
+        Some(Commands::Info { file, json }) => {
+            print_info(file, *json);
+            return;
+        }
+        Some(Commands::ExportCompletions { file, shell }) => {
+            export_completions(file, *shell);
+            return;
+        }
+        Some(Commands::Build {
+            manifest,
+            emit_install_snippet,
+        }) => {
+            handle_build(manifest, *emit_install_snippet, &args, &roff_options);
+            return;
+        }
+        Some(Commands::Install {
+            files,
+            prefix,
+            gzip,
+            no_index,
+        }) => {
+            handle_install(
+                files,
+                prefix.as_deref(),
+                *gzip || compress_pages(&args),
+                !no_index,
+                &args,
+                &roff_options,
+            );
+            return;
+        }
+        Some(Commands::Diff { old, new, rendered }) => {
+            handle_diff(old, new, *rendered, &roff_options);
+            return;
+        }
+        Some(Commands::ExtractExamples { file, shell }) => {
+            extract_examples(file, *shell);
+            return;
+        }
+        Some(Commands::Lint {
+            files,
+            json,
+            check_urls,
+            allow_url,
+            url_cache,
+            url_timeout,
+        }) => {
+            let checker = check_urls.then(|| links::Checker {
+                timeout: Duration::from_secs(*url_timeout),
+                allow: allow_url.clone(),
+                cache: url_cache.clone(),
+            });
+            handle_lint(files, *json, checker.as_ref());
+            return;
+        }
+        None => {}
+    }
+
+    if let Some(spec_path) = &args.suite {
+        handle_suite(spec_path, &args, &roff_options);
+        return;
+    }
+
+    let inputs = match input_files(&args.files, args.recursive) {
+        Ok(files) if files.is_empty() => vec![None],
+        Ok(files) => files.into_iter().map(Some).collect::<Vec<_>>(),
         Err(e) => {
             eprintln!("{}", e);
             process::exit(1)
         }
     };
+    if args.single_file && args.format != Format::Html {
+        eprintln!("mdman: --single-file works with --format html only");
+        process::exit(1)
+    }
+    if inputs.len() > 1 {
+        let single = [
+            (args.output.is_some() && !args.single_file, "--output"),
+            (args.stdout && !args.single_file, "--stdout"),
+            (args.pager, "--pager"),
+            (args.section_only.is_some(), "--section-only"),
+            (args.export_options.is_some(), "--export-options"),
+            (args.postprocess.is_some(), "--postprocess"),
+        ];
+        if let Some((_, flag)) = single.iter().find(|(given, _)| *given) {
+            eprintln!("mdman: {} works with one input file only", flag);
+            process::exit(1)
+        }
+    }
 
-    let parse_options = ParseOptions {
-        constructs: Constructs {
-            frontmatter: true,
-            gfm_table: true,
-            ..Constructs::default()
-        },
-        ..ParseOptions::gfm()
-    };
+    if args.watch && args.pager {
+        watch_in_pager(&args, &inputs[0]);
+    }
+    if args.watch {
+        watch(&args, &inputs, &roff_options);
+    }
+    if !convert(&args, &inputs, &roff_options) {
+        process::exit(1)
+    }
+}
 
-    let markdown_ast = markdown::to_mdast(&md_content, &parse_options).unwrap();
-    let mut convert_state = ConvertState::new();
-    let man_nodes = convert_markdown_node(&markdown_ast, &mut convert_state);
+/// How long --watch waits for more changes before converting, as editors
+/// often save a file in several steps.
+const WATCH_SETTLE: Duration = Duration::from_millis(100);
+
+/// How often --watch --pager looks whether the user quit less.
+const PAGER_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The sources of --watch, watched with notify. Files are watched through
+/// their directories, since editors often save by replacing the file, and
+/// the directories given with --recursive with everything below them, so
+/// that pages added there are seen as well.
+struct SourceWatcher {
+    _watcher: RecommendedWatcher,
+    events: mpsc::Receiver<notify::Result<notify::Event>>,
+}
 
-    let section = args.section.unwrap_or_else(|| {
-        match man_nodes
+impl SourceWatcher {
+    fn new(files: &[&PathBuf], recursive_dirs: &[&PathBuf]) -> notify::Result<Self> {
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        let recursive_dirs = recursive_dirs
             .iter()
-            .find(|&node| matches!(node, ManNode::TitleLine(_)))
-        {
-            Some(ManNode::TitleLine(title_line)) => title_line.section,
-            _ => 1,
+            .map(std::path::absolute)
+            .collect::<io::Result<Vec<_>>>()?;
+        let mut dirs = BTreeSet::new();
+        for file in files {
+            let file = std::path::absolute(file)?;
+            let dir = file.parent().unwrap_or(Path::new("/")).to_path_buf();
+            if !recursive_dirs.iter().any(|top| dir.starts_with(top)) {
+                dirs.insert(dir);
+            }
+        }
+        for dir in &dirs {
+            watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        }
+        for dir in &recursive_dirs {
+            watcher.watch(dir, RecursiveMode::Recursive)?;
+        }
+        Ok(Self {
+            _watcher: watcher,
+            events,
+        })
+    }
+
+    /// The absolute paths that changed within `timeout`, once they stop
+    /// changing; empty if none did.
+    fn changed(&self, timeout: Duration) -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+        let mut deadline = Instant::now().checked_add(timeout);
+        loop {
+            let event = match deadline {
+                Some(deadline) => self
+                    .events
+                    .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                    .ok(),
+                None => self.events.recv().ok(),
+            };
+            let Some(event) = event else {
+                return paths;
+            };
+            // Reading a file is no change, and reads must not hold off the
+            // changes either.
+            if let Ok(event) = event
+                && !event.kind.is_access()
+            {
+                paths.extend(event.paths);
+                deadline = Some(Instant::now() + WATCH_SETTLE);
+            }
+        }
+    }
+}
+
+/// Watch `files`, or exit after saying why they cannot be watched.
+fn watch_sources(files: &[&PathBuf], recursive_dirs: &[&PathBuf]) -> SourceWatcher {
+    match SourceWatcher::new(files, recursive_dirs) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            eprintln!("mdman: Could not watch the input files. Error: {}", e);
+            process::exit(1)
+        }
+    }
+}
+
+/// Whether `file` is one of the absolute `paths`.
+fn is_among(file: &Path, paths: &[PathBuf]) -> bool {
+    std::path::absolute(file).is_ok_and(|file| paths.contains(&file))
+}
+
+/// Convert the pages, then again each time one of them changes. With
+/// --recursive the directories are searched for pages again, so pages added
+/// below them are converted too. Ends only when the process is interrupted.
+fn watch(args: &Args, inputs: &[Option<PathBuf>], roff_options: &RoffOptions) -> ! {
+    let files = inputs.iter().flatten().collect::<Vec<_>>();
+    if files.is_empty() {
+        eprintln!("mdman: --watch needs FILE; stdin cannot be watched");
+        process::exit(1)
+    }
+    let recursive_dirs = match args.recursive {
+        true => args.files.iter().filter(|file| file.is_dir()).collect(),
+        false => Vec::new(),
+    };
+    let watcher = watch_sources(&files, &recursive_dirs);
+
+    convert(args, inputs, roff_options);
+    eprintln!("mdman: watching for changes, press Ctrl-C to stop");
+    loop {
+        let paths = watcher.changed(Duration::MAX);
+        let inputs = match input_files(&args.files, args.recursive) {
+            Ok(files) => files,
+            Err(e) => {
+                eprintln!("{}", e);
+                continue;
+            }
+        };
+        let changed = inputs
+            .iter()
+            .filter(|file| is_among(file, &paths))
+            .map(|file| file.display().to_string())
+            .collect::<Vec<_>>();
+        if changed.is_empty() {
+            continue;
+        }
+        eprintln!("mdman: {} changed", changed.join(", "));
+        let inputs = inputs.into_iter().map(Some).collect::<Vec<_>>();
+        convert(args, &inputs, roff_options);
+    }
+}
+
+/// Show the built-in rendering of the page in `less -R`, and show it anew
+/// whenever the page changes. Ends when the user quits less.
+///
+/// Unlike [`handle_pager`] this never goes through man or mandoc: man starts
+/// a pager of its own, which cannot be stopped and restarted from here, and
+/// the built-in rendering needs neither of them to be installed.
+fn watch_in_pager(args: &Args, file: &Option<PathBuf>) -> ! {
+    let Some(file) = file else {
+        eprintln!("mdman: --watch needs FILE; stdin cannot be watched");
+        process::exit(1)
+    };
+    if external::find_executable("less").is_none() {
+        eprintln!("mdman: --watch --pager needs less in PATH");
+        process::exit(1)
+    }
+    let watcher = watch_sources(&[file], &[]);
+    // What went wrong goes on the page, which covers the terminal.
+    let render = || {
+        let (page, captured) = batch::capture(|| load_page(args, &Some(file.clone())));
+        let mut text = captured.stderr;
+        if let Some((man_nodes, _)) = page {
+            text.extend_from_slice(AnsiRenderer::for_terminal().render(&man_nodes).as_bytes());
+        }
+        text
+    };
+    let show = |text: &[u8]| match external::LivePager::spawn(Command::new("less").arg("-R"), text)
+    {
+        Ok(pager) => pager,
+        Err(e) => {
+            eprintln!("mdman: Error showing man page in pager: {}", e);
+            process::exit(1)
+        }
+    };
+
+    let mut pager = show(&render());
+    // less handles Ctrl-C itself; it must not end us and leave it behind.
+    let ((), _) = external::catch_interrupt(|| {
+        loop {
+            let paths = watcher.changed(PAGER_INTERVAL);
+            if pager.has_quit() {
+                process::exit(0)
+            }
+            if !is_among(file, &paths) {
+                continue;
+            }
+            let text = render();
+            pager.stop();
+            external::restore_cursor();
+            pager = show(&text);
         }
     });
+    unreachable!()
+}
+
+/// Convert the pages and, for more than one, print a summary. Returns
+/// whether all of them were converted.
+fn convert(args: &Args, inputs: &[Option<PathBuf>], roff_options: &RoffOptions) -> bool {
+    // All pages are parsed before any is written, so that they can share
+    // their glossaries.
+    let started = Instant::now();
+    let jobs = args.jobs.map_or_else(batch::default_jobs, usize::from);
+    let loaded = batch::map(inputs, jobs, |file| load_page(args, file));
+    let mut failed = Vec::new();
+    let mut pages = Vec::new();
+    for (file, page) in inputs.iter().zip(loaded) {
+        match page {
+            Some((man_nodes, source_lines)) => pages.push((file, man_nodes, source_lines)),
+            None => failed.push(file),
+        }
+    }
+    share_glossaries(pages.iter_mut().map(|(_, man_nodes, _)| man_nodes));
+    let Some(mut html_options) = html_options(args) else {
+        return false;
+    };
+    if args.format == Format::Html {
+        let no_links = HtmlOptions::default();
+        html_options.pages = html_files(pages.iter().map(|(file, man_nodes, _)| {
+            let extension = HtmlRenderer { options: &no_links }.extension(man_nodes);
+            (man_nodes.as_slice(), output_path(args, file, &extension))
+        }));
+    }
+    // With --single-file the pages go into one output, so a failure to
+    // write it is reported once rather than for each page.
+    let mut written = true;
+    if args.single_file {
+        if !args.quiet {
+            for (file, man_nodes, source_lines) in &pages {
+                warn_inaccessible(man_nodes, source_lines, file);
+            }
+        }
+        let nodes = pages
+            .iter()
+            .map(|(_, man_nodes, _)| man_nodes.as_slice())
+            .collect::<Vec<_>>();
+        written = write_single_file(args, &nodes, &html_options);
+    } else {
+        let emitted = batch::map(&pages, jobs, |(file, man_nodes, source_lines)| {
+            emit_page(
+                args,
+                file,
+                man_nodes,
+                source_lines,
+                roff_options,
+                &html_options,
+            )
+        });
+        for ((file, _, _), ok) in pages.iter().zip(emitted) {
+            if !ok {
+                failed.push(file);
+            }
+        }
+    }
+
+    if inputs.len() > 1 {
+        let done = if args.check { "checked" } else { "converted" };
+        let failed_files = failed
+            .iter()
+            .flat_map(|file| file.as_ref().map(|f| f.display().to_string()))
+            .collect::<Vec<_>>();
+        let took = match args.verbose {
+            true => format!(" in {:.2}s", started.elapsed().as_secs_f64()),
+            false => String::new(),
+        };
+        match failed.len() {
+            0 => eprintln!("mdman: {} {} pages{}", done, inputs.len(), took),
+            n => eprintln!(
+                "mdman: {} {} of {} pages{}, {} failed: {}",
+                done,
+                inputs.len() - n,
+                inputs.len(),
+                took,
+                n,
+                failed_files.join(", ")
+            ),
+        }
+    }
+    failed.is_empty() && written
+}
 
+/// Write the pages of the batch into one HTML file for --single-file,
+/// `index.html` unless the flags name another place. Returns whether it was
+/// written.
+fn write_single_file(args: &Args, pages: &[&[ManNode]], html_options: &HtmlOptions) -> bool {
+    let html = html::html_pages(pages, html_options);
+    let index = Some(PathBuf::from("index"));
+    write_output(args, &index, html.as_bytes(), "html", false)
+}
 
-    let roff = man_nodes.iter().map(|n| n.to_roff()).collect::<String>();
+/// The HTML settings from --html-template and --css, or `None` after saying
+/// why the template cannot be used.
+fn html_options(args: &Args) -> Option<HtmlOptions> {
+    let template = match &args.html_template {
+        Some(path) => match fs::read_to_string(path) {
+            Ok(template) if template.contains("{{content}}") => Some(template),
+            Ok(_) => {
+                eprintln!("mdman: {} has no {{{{content}}}}", path.display());
+                return None;
+            }
+            Err(e) => {
+                eprintln!("mdman: Could not read {}. Error: {}", path.display(), e);
+                return None;
+            }
+        },
+        None => None,
+    };
+    Some(HtmlOptions {
+        pages: BTreeMap::new(),
+        template,
+        css: args.css.clone(),
+    })
+}
 
-    if args.pager {
-        handle_pager(&roff);
+/// The HTML file of each page that is written to one, by the page's name and
+/// section, for links between the pages of a batch. All of them are in the
+/// same directory.
+fn html_files<'a>(
+    pages: impl Iterator<Item = (&'a [ManNode], Option<PathBuf>)>,
+) -> BTreeMap<(String, String), String> {
+    pages
+        .filter_map(|(man_nodes, path)| {
+            let title = man_node::title_line(man_nodes)?;
+            let file = path?.file_name()?.to_string_lossy().into_owned();
+            Some(((title.name.clone(), title.section.to_string()), file))
+        })
+        .collect()
+}
+
+/// The input files, with patterns the shell left alone expanded (see
+/// [`glob::expand`]) and, with --recursive, directories replaced by the
+/// Markdown files below them, except for those in snippet directories. A
+/// pattern that matches nothing is an error, and so is a directory without
+/// --recursive.
+fn input_files(files: &[PathBuf], recursive: bool) -> Result<Vec<PathBuf>, String> {
+    let mut inputs = Vec::new();
+    for file in files {
+        if file.is_dir() {
+            if !recursive {
+                return Err(format!(
+                    "mdman: {} is a directory; use --recursive to convert the pages below it",
+                    file.display()
+                ));
+            }
+            let found = corpus::markdown_files(file)
+                .map_err(|e| format!("mdman: Could not read {}. Error: {}", file.display(), e))?;
+            let pages = found
+                .into_iter()
+                .filter(|page| {
+                    let below = page.strip_prefix(file).unwrap_or(page);
+                    !below
+                        .components()
+                        .any(|c| c.as_os_str() == fragment::SNIPPET_DIR)
+                })
+                .collect::<Vec<_>>();
+            if pages.is_empty() {
+                return Err(format!("mdman: No Markdown files below {}", file.display()));
+            }
+            inputs.extend(pages);
+            continue;
+        }
+        if file.exists() || !glob::is_pattern(file) {
+            inputs.push(file.clone());
+            continue;
+        }
+        let matched = glob::expand(file);
+        if matched.is_empty() {
+            return Err(format!("mdman: No files match {}", file.display()));
+        }
+        inputs.extend(matched);
+    }
+    Ok(inputs)
+}
+
+/// Read and parse a page and apply the transformations asked for. Returns
+/// the nodes and the Markdown line of each, or `None` after saying on
+/// stderr why the page cannot be converted.
+fn load_page(args: &Args, file: &Option<PathBuf>) -> Option<(Vec<ManNode>, Vec<Option<usize>>)> {
+    let md_content = match get_md_content(file) {
+        Ok(md) => md,
+        Err(e) => {
+            page_eprintln!("{}", e);
+            return None;
+        }
+    };
+
+    let (mut man_nodes, mut source_lines, dropped) =
+        match parse_page_with_dropped(&md_content, file) {
+            Ok(page) => page,
+            Err(e) => {
+                page_eprintln!("{}", e);
+                return None;
+            }
+        };
+    if args.strict && !dropped.is_empty() {
+        report_dropped(&dropped, file);
+        return None;
+    }
+    if !args.quiet {
+        warn_dropped(&dropped, file);
+    }
+    transform_page(args, file, &mut man_nodes, &mut source_lines);
+    Some((man_nodes, source_lines))
+}
+
+/// Apply the transformations the flags ask for to a parsed page, keeping
+/// `source_lines` in step with the nodes.
+fn transform_page(
+    args: &Args,
+    file: &Option<PathBuf>,
+    man_nodes: &mut Vec<ManNode>,
+    source_lines: &mut Vec<Option<usize>>,
+) {
+    if args.date_from == DateFrom::Mtime
+        && let Some(file) = file
+    {
+        date_from_mtime(man_nodes, file);
+    }
+    if uppercase_sections(args) {
+        man_node::uppercase_sections(man_nodes);
+    }
+    if !args.no_subcommand_table
+        && let Some(table) = man_node::insert_subcommand_table(man_nodes)
+    {
+        source_lines.insert(table, None);
+    }
+    if args.numbered_headings {
+        man_node::number_headings(man_nodes);
+    }
+    if args.toc {
+        let inserted = man_node::insert_contents(man_nodes);
+        let generated = std::iter::repeat_n(None, inserted.len());
+        source_lines.splice(inserted.start..inserted.start, generated);
+    }
+    let notes = man_node::apply_url_style(man_nodes, args.urls);
+    source_lines.extend(notes.map(|_| None));
+}
+
+/// Give pages converted together the terms of each other's glossaries.
+fn share_glossaries<'a>(pages: impl Iterator<Item = &'a mut Vec<ManNode>>) {
+    let mut pages = pages.collect::<Vec<_>>();
+    if pages.len() < 2 {
         return;
     }
+    let mut nodes = pages
+        .iter_mut()
+        .map(|man_nodes| std::mem::take(*man_nodes))
+        .collect::<Vec<_>>();
+    man_node::merge_glossaries(&mut nodes);
+    for (page, man_nodes) in pages.into_iter().zip(nodes) {
+        *page = man_nodes;
+    }
+}
+
+/// Render a parsed page and write, show or check it as the flags say.
+/// Returns whether that went well; what went wrong is on stderr.
+fn emit_page(
+    args: &Args,
+    file: &Option<PathBuf>,
+    man_nodes: &[ManNode],
+    source_lines: &[Option<usize>],
+    roff_options: &RoffOptions,
+    html_options: &HtmlOptions,
+) -> bool {
+    if args.portability_report {
+        print_portability_report(man_nodes, source_lines, roff_options, file);
+        return true;
+    }
+
+    if let Some(path) = &args.export_options {
+        let flags = flags::document_flags(man_nodes);
+        let json = pretty_json(&flags);
+        if path.as_os_str() == "-" {
+            batch::write_stdout(json.as_bytes());
+            return true;
+        }
+        if let Err(e) = fs::write(path, json) {
+            page_eprintln!("mdman: Could not write {}. Error: {}", path.display(), e);
+            return false;
+        }
+    }
+
+    if let Some(name) = &args.section_only {
+        let Some(range) = man_node::section_range(man_nodes, name) else {
+            page_eprintln!("mdman: No section {} in the page", name);
+            return false;
+        };
+        let (_, chunks) = render_page(&man_nodes[range], roff_options);
+        batch::write_stdout(chunks.concat().as_bytes());
+        return true;
+    }
 
-    if args.stdout || args.file.is_none() {
-        _ = stdout().write_all(roff.as_bytes());
+    let renderer = args.format.renderer(roff_options, html_options);
+    if args.format == Format::Html && !args.quiet {
+        warn_inaccessible(man_nodes, source_lines, file);
+    }
+    if args.format != Format::Man {
+        let page = renderer.render(man_nodes);
+        if renderer.for_terminal() && args.output.is_none() {
+            batch::write_stdout(page.as_bytes());
+            return true;
+        }
+        return write_output(
+            args,
+            file,
+            page.as_bytes(),
+            &renderer.extension(man_nodes),
+            false,
+        );
+    }
+
+    if args.wide_blocks == WideBlocks::Warn {
+        for width in roff::wide_blocks(man_nodes, args.block_width) {
+            page_eprintln!(
+                "mdman: warning: code block is {} columns wide (limit {})",
+                width,
+                args.block_width
+            );
+        }
+    }
+
+    let roff = renderer.render(man_nodes);
+    let timeout = Some(Duration::from_secs(args.timeout)).filter(|t| !t.is_zero());
+
+    if args.validate {
+        // The source map needs to know where the output of each node starts.
+        let (preamble, chunks) = render_page(man_nodes, roff_options);
+        let source_map = validate::SourceMap::new(&preamble, &chunks, source_lines);
+        report_lint(&roff, &source_map, file, timeout);
+    }
+
+    // Things that do not parse are drift too, even if the committed file
+    // matches.
+    let check_failed = args.check && {
+        let (preamble, chunks) = render_page(man_nodes, roff_options);
+        let source_map = validate::SourceMap::new(&preamble, &chunks, source_lines);
+        !check_roff(&roff, &source_map, file, timeout)
+    };
+
+    let lintian_failed = args.lintian_check && {
+        let (preamble, chunks) = render_page(man_nodes, roff_options);
+        let source_map = validate::SourceMap::new(&preamble, &chunks, source_lines);
+        !report_man_warnings(&roff, &source_map, file, timeout)
+    };
+
+    // What groff should hyphenate the page as, if it has patterns for it.
+    let language = man_node::title_line(man_nodes)
+        .and_then(|t| t.lang.as_deref())
+        .and_then(roff::groff_language);
+
+    if args.pager {
+        handle_pager(&roff, man_nodes, timeout, language);
+        return true;
+    }
+
+    if let Some(command) = &args.postprocess {
+        let output = match external::postprocess(command, roff.as_bytes(), timeout, language) {
+            Ok(output) => output,
+            Err(e) => {
+                page_eprintln!("{}", e);
+                return false;
+            }
+        };
+        // Whatever the command made, it is not a page for section N, so
+        // there is no automatic file name.
+        match &args.output {
+            Some(path) => {
+                if let Err(e) = fs::write(path, output) {
+                    page_eprintln!("mdman: Could not write {}. Error: {}", path.display(), e);
+                    return false;
+                }
+            }
+            None => batch::write_stdout(&output),
+        }
+        return true;
+    }
+
+    let extension = match args.section {
+        Some(section) => section.to_string(),
+        None => renderer.extension(man_nodes),
+    };
+    // An --output of `tool.1.gz` is compressed with or without --gzip;
+    // stdout never is.
+    let compress = match &args.output {
+        Some(output) => gzip::is_gzip_name(output),
+        None => compress_pages(args) && !args.stdout && file.is_some(),
+    };
+    write_output(args, file, roff.as_bytes(), &extension, compress)
+        && !lintian_failed
+        && !check_failed
+}
+
+/// Give a page without a frontmatter date the day its source was last
+/// modified, in local time.
+fn date_from_mtime(man_nodes: &mut [ManNode], file: &Path) {
+    let Some(title_line) = man_nodes.iter_mut().find_map(|node| match node {
+        ManNode::TitleLine(title_line) if title_line.date.is_none() => Some(title_line),
+        _ => None,
+    }) else {
         return;
+    };
+    let modified = fs::metadata(file).and_then(|metadata| metadata.modified());
+    match modified.map(jiff::Timestamp::try_from) {
+        Ok(Ok(timestamp)) => {
+            let day = timestamp.to_zoned(jiff::tz::TimeZone::system());
+            title_line.date = Some(day.strftime("%Y-%m-%d").to_string());
+        }
+        _ => page_eprintln!(
+            "mdman: warning: no modification time for {}, using today's date",
+            file.display()
+        ),
+    }
+}
+
+fn uppercase_sections(args: &Args) -> bool {
+    args.uppercase_sections
+        || args
+            .profile
+            .is_some_and(|p| p.settings().uppercase_sections)
+}
+
+/// Whether man pages written to files are gzipped: --gzip or the profile.
+fn compress_pages(args: &Args) -> bool {
+    args.gzip || args.profile.is_some_and(|p| p.settings().gzip)
+}
+
+/// The --layout, or the one that mirrors `/usr/share/man` for --recursive.
+fn layout(args: &Args) -> Layout {
+    match args.layout {
+        Some(layout) => layout,
+        None if args.recursive => Layout::Sections,
+        None => Layout::default(),
+    }
+}
+
+/// The --output file or a file named after the input with `extension`, or
+/// `None` for stdout.
+fn output_path(args: &Args, file: &Option<PathBuf>, extension: &str) -> Option<PathBuf> {
+    match (&args.output, file) {
+        _ if args.stdout => None,
+        (Some(output), _) => Some(output.clone()),
+        (None, Some(file)) => {
+            let dir = args.output_dir.as_deref().unwrap_or(Path::new(""));
+            Some(layout(args).path(dir, &info::page_stem(file), extension, args.format))
+        }
+        (None, None) => None,
     }
+}
 
-    let out_path = match args.output {
-        Some(output) => output,
-        None => {
-            let stem = args
-                .file
-                .as_ref()
-                .unwrap()
-                .file_stem()
-                .unwrap()
-                .to_string_lossy();
-            let base_name = PathBuf::from(stem.split('.').next().unwrap());
-            base_name.with_extension(section.to_string())
+/// Write `content` to stdout, the --output file or a file named after the
+/// input with `extension`. With --check, compare it to that file instead.
+/// With `compress`, the file gets gzipped content and `.gz` at the end of its
+/// name. Returns whether the file was written (or is up to date).
+fn write_output(
+    args: &Args,
+    file: &Option<PathBuf>,
+    content: &[u8],
+    extension: &str,
+    compress: bool,
+) -> bool {
+    let out_path = output_path(args, file, extension).map(|path| match compress {
+        true if !gzip::is_gzip_name(&path) => gzip::gzip_name(&path),
+        _ => path,
+    });
+    let compressed;
+    let content = match compress {
+        true => {
+            compressed = gzip::compress(content);
+            &compressed
         }
+        false => content,
+    };
+    if args.check {
+        let Some(out_path) = out_path else {
+            page_eprintln!(
+                "mdman: --check needs --output to know which file to compare stdin with"
+            );
+            return false;
+        };
+        return check_output(&out_path, content);
+    }
+
+    let Some(out_path) = out_path else {
+        batch::write_stdout(content);
+        return true;
     };
-    let mut out_file = fs::File::create(&out_path).unwrap();
-    _ = out_file.write(roff.as_bytes());
+    let dir = out_path.parent().unwrap_or(Path::new(""));
+    if let Err(e) = fs::create_dir_all(dir).and_then(|_| fs::write(&out_path, content)) {
+        page_eprintln!(
+            "mdman: Could not write {}. Error: {}",
+            out_path.display(),
+            e
+        );
+        return false;
+    }
+    true
 }
 
 #[derive(Debug)]
@@ -160,40 +1205,773 @@ fn get_md_content(file_like: &Option<PathBuf>) -> Result<String, GetContentError
     }
 }
 
-fn handle_pager(roff: &str) {
+fn print_version(verbose: bool) {
+    println!("mdman {}", env!("CARGO_PKG_VERSION"));
+    if !verbose {
+        return;
+    }
+    let features = if FEATURES.is_empty() {
+        "none".to_string()
+    } else {
+        FEATURES.join(", ")
+    };
+    println!("features: {}", features);
+    let formats = Format::value_variants()
+        .iter()
+        .filter_map(|f| f.to_possible_value())
+        .map(|v| v.get_name().to_string())
+        .collect::<Vec<_>>();
+    println!("formats: {}", formats.join(", "));
+    println!("formatters:");
+    for name in external::FORMATTERS {
+        match external::find_executable(name) {
+            Some(path) => println!("  {}: {}", name, path.to_string_lossy()),
+            None => println!("  {}: not found", name),
+        }
+    }
+    #[cfg(target_os = "linux")]
+    println!("pager: man -l -");
     #[cfg(target_os = "macos")]
-    let pager_cmd = Command::new("mandoc")
-        .arg("-a")
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()
-        .and_then(|mut mandoc| {
-            mandoc.stdin.as_mut().unwrap().write_all(roff.as_bytes())?;
-            let output = mandoc.wait_with_output()?;
-            Command::new("less")
-                .stdin(Stdio::piped())
-                .spawn()
-                .and_then(|mut less| {
-                    less.stdin.as_mut().unwrap().write_all(&output.stdout)?;
-                    less.wait()?;
-                    Ok(())
-                })
-        });
+    println!("pager: mandoc -a | less");
+}
+
+fn handle_suite(spec_path: &Path, args: &Args, roff_options: &RoffOptions) {
+    let spec = match suite::load_suite_spec(spec_path) {
+        Ok(spec) => spec,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1)
+        }
+    };
+    let renderer = ManRenderer {
+        options: roff_options,
+    };
+    let dir = args.output_dir.as_deref().unwrap_or(Path::new(""));
+    let layout = args.layout.unwrap_or_default();
+    for page in suite::suite_pages(&spec) {
+        let roff = renderer.render(&page.nodes);
+        let out_path = layout.path(dir, &page.name, &spec.section.to_string(), Format::Man);
+        let parent = out_path.parent().unwrap_or(Path::new(""));
+        if let Err(e) = fs::create_dir_all(parent).and_then(|_| fs::write(&out_path, roff)) {
+            eprintln!(
+                "mdman: Could not write {}. Error: {}",
+                out_path.to_string_lossy(),
+                e
+            );
+            process::exit(1)
+        }
+    }
+}
+
+fn handle_build(
+    manifest_path: &Path,
+    snippet: Option<BuildSystem>,
+    args: &Args,
+    roff_options: &RoffOptions,
+) {
+    let profile = args.profile.map(Profile::settings);
+    let mut manifest = match manifest::load_manifest(manifest_path) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1)
+        }
+    };
+    // Flags win over the manifest, the manifest over the profile.
+    if let Some(dir) = &args.output_dir {
+        manifest.output_dir = dir.clone();
+    }
+    manifest.layout = args
+        .layout
+        .or(manifest.layout)
+        .or(profile.map(|p| p.layout));
+    let mut pages = Vec::new();
+    for page in &manifest.pages {
+        let path = Some(page.source.clone());
+        let (mut man_nodes, mut source_lines, dropped) = match get_md_content(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|md| parse_page_with_dropped(&md, &path).map_err(|e| e.to_string()))
+        {
+            Ok(page) => page,
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1)
+            }
+        };
+        if args.strict && !dropped.is_empty() {
+            report_dropped(&dropped, &path);
+            process::exit(1)
+        }
+        if !args.quiet {
+            warn_dropped(&dropped, &path);
+        }
+        page.apply_overrides(&mut man_nodes);
+        transform_page(args, &path, &mut man_nodes, &mut source_lines);
+        pages.push((page.stem(), man_nodes));
+    }
+    share_glossaries(pages.iter_mut().map(|(_, man_nodes)| man_nodes));
+
+    if let Some(system) = snippet {
+        // Build files live next to the manifest.
+        let dir = manifest_path.parent().unwrap_or(Path::new(""));
+        let installed = pages
+            .iter()
+            .map(|(stem, man_nodes)| {
+                let section = man_node::title_line(man_nodes).map_or(1, |t| t.section);
+                let mut file = manifest.output_path(stem, &section.to_string(), Format::Man);
+                if compress_pages(args) {
+                    file = gzip::gzip_name(&file);
+                }
+                let file = file.strip_prefix(dir).unwrap_or(&file).to_path_buf();
+                snippet::InstalledPage { file, section }
+            })
+            .collect::<Vec<_>>();
+        print!(
+            "{}",
+            snippet::install_snippet(system, &installed, &manifest.prefix)
+        );
+        return;
+    }
+
+    let Some(mut html_options) = html_options(args) else {
+        process::exit(1)
+    };
+    let no_links = HtmlOptions::default();
+    html_options.pages = html_files(pages.iter().map(|(stem, man_nodes)| {
+        let extension = HtmlRenderer { options: &no_links }.extension(man_nodes);
+        let path = manifest.output_path(stem, &extension, Format::Html);
+        (man_nodes.as_slice(), Some(path))
+    }));
+    for (stem, man_nodes) in &pages {
+        for &format in &manifest.formats {
+            let renderer = format.renderer(roff_options, &html_options);
+            let mut out_path = manifest.output_path(stem, &renderer.extension(man_nodes), format);
+            let mut content = renderer.render(man_nodes).into_bytes();
+            if format == Format::Man && compress_pages(args) {
+                out_path = gzip::gzip_name(&out_path);
+                content = gzip::compress(&content);
+            }
+            let written = out_path
+                .parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|_| fs::write(&out_path, content));
+            if let Err(e) = written {
+                eprintln!(
+                    "mdman: Could not write {}. Error: {}",
+                    out_path.display(),
+                    e
+                );
+                process::exit(1)
+            }
+        }
+    }
+}
+
+fn handle_test_corpus(dir: &Path, roff_options: &RoffOptions) {
+    let cases = match corpus::find_cases(dir) {
+        Ok(cases) => cases,
+        Err(e) => {
+            eprintln!("mdman: Could not read {}. Error: {}", dir.display(), e);
+            process::exit(1)
+        }
+    };
+
+    let (mut passed, mut failed, mut skipped) = (0, 0, 0);
+    for case in cases {
+        let name = case.markdown.display();
+        let Some(expected_path) = &case.expected else {
+            println!("skip {} (no expected output)", name);
+            skipped += 1;
+            continue;
+        };
+        let result = fs::read_to_string(&case.markdown)
+            .map_err(|e| format!("mdman: Could not read file {}. Error: {}", name, e))
+            .and_then(|md| parse_page(&md, &Some(case.markdown.clone())).map_err(|e| e.to_string()))
+            .and_then(|(nodes, _)| {
+                let expected = fs::read_to_string(expected_path).map_err(|e| {
+                    format!(
+                        "mdman: Could not read file {}. Error: {}",
+                        expected_path.display(),
+                        e
+                    )
+                })?;
+                let (preamble, chunks) = render_page(&nodes, roff_options);
+                Ok(corpus::diff(&expected, &(preamble + &chunks.concat())))
+            });
+        match result {
+            Ok(diff) if diff.is_empty() => {
+                println!("ok   {}", name);
+                passed += 1;
+            }
+            Ok(diff) => {
+                println!("FAIL {}\n{}", name, diff);
+                failed += 1;
+            }
+            Err(e) => {
+                println!("FAIL {}\n{}", name, e);
+                failed += 1;
+            }
+        }
+    }
+
+    println!("{} passed, {} failed, {} skipped", passed, failed, skipped);
+    if failed > 0 {
+        process::exit(1)
+    }
+}
+
+/// `value` as JSON indented by two spaces, with a final newline.
+fn pretty_json(value: &impl Serialize) -> String {
+    // Nothing mdman exports has map keys or values JSON cannot hold.
+    let mut json = serde_json::to_string_pretty(value).unwrap();
+    json.push('\n');
+    json
+}
+
+fn print_info(file: &Path, json: bool) {
+    let path = Some(file.to_path_buf());
+    let (man_nodes, _) = match get_md_content(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|md| parse_page(&md, &path).map_err(|e| e.to_string()))
+    {
+        Ok(page) => page,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1)
+        }
+    };
+    let info = info::page_info(&man_nodes, file);
+    if json {
+        print!("{}", pretty_json(&info));
+    } else {
+        print!("{}", info.to_text());
+    }
+}
+
+fn export_completions(file: &Path, shell: completions::Shell) {
+    let file = Some(file.to_path_buf());
+    let (man_nodes, _) = match get_md_content(&file)
+        .map_err(|e| e.to_string())
+        .and_then(|md| parse_page(&md, &file).map_err(|e| e.to_string()))
+    {
+        Ok(page) => page,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1)
+        }
+    };
+    let command = man_nodes
+        .iter()
+        .find_map(|node| match node {
+            ManNode::TitleLine(title_line) => Some(title_line.name.clone()),
+            _ => None,
+        })
+        .unwrap_or_else(|| info::page_stem(file.as_deref().unwrap()));
+    let flags = flags::document_flags(&man_nodes);
+    print!(
+        "{}",
+        completions::completion_script(shell, &command, &flags)
+    );
+}
+
+/// Render the pages and write each to `PREFIX/share/man/manN`, then update
+/// the whatis database of that directory if `index`.
+fn handle_install(
+    files: &[PathBuf],
+    prefix: Option<&Path>,
+    gzip: bool,
+    index: bool,
+    args: &Args,
+    roff_options: &RoffOptions,
+) {
+    let prefix = match prefix {
+        Some(prefix) => prefix.to_path_buf(),
+        None if Path::new(manifest::MANIFEST_FILE).exists() => {
+            match manifest::load_manifest(Path::new(manifest::MANIFEST_FILE)) {
+                Ok(manifest) => manifest.prefix,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(1)
+                }
+            }
+        }
+        None => PathBuf::from("/usr/local"),
+    };
+    let man_dir = prefix.join("share").join("man");
+
+    let renderer = ManRenderer {
+        options: roff_options,
+    };
+    for file in files {
+        let Some((man_nodes, _)) = load_page(args, &Some(file.clone())) else {
+            process::exit(1)
+        };
+        let section = man_node::title_line(&man_nodes).map_or(1, |t| t.section);
+        let name = format!(
+            "{}.{}",
+            info::page_stem(file),
+            renderer.extension(&man_nodes)
+        );
+        let dir = man_dir.join(format!("man{}", section));
+        let roff = renderer.render(&man_nodes);
+        let (path, content, other) = match gzip {
+            true => (
+                dir.join(format!("{}.gz", name)),
+                gzip::compress(roff.as_bytes()),
+                dir.join(&name),
+            ),
+            false => (
+                dir.join(&name),
+                roff.into_bytes(),
+                dir.join(format!("{}.gz", name)),
+            ),
+        };
+        if let Err(e) = fs::create_dir_all(&dir).and_then(|_| fs::write(&path, content)) {
+            eprintln!("mdman: Could not write {}. Error: {}", path.display(), e);
+            process::exit(1)
+        }
+        // man would find both, and might show the old one.
+        _ = fs::remove_file(other);
+        if args.verbose {
+            eprintln!("mdman: installed {}", path.display());
+        }
+    }
+
+    if index {
+        update_whatis(&man_dir);
+    }
+}
+
+/// Run mandb, or makewhatis on the BSDs and macOS, on `man_dir`, so that
+/// apropos(1) and man -k find the new pages. If there is neither, or it
+/// fails, that is only a warning: the pages are installed.
+fn update_whatis(man_dir: &Path) {
+    let mut command = if external::find_executable("mandb").is_some() {
+        let mut command = Command::new("mandb");
+        command.arg("--quiet").arg(man_dir);
+        command
+    } else if external::find_executable("makewhatis").is_some() {
+        let mut command = Command::new("makewhatis");
+        command.arg(man_dir);
+        command
+    } else {
+        return;
+    };
+    let program = command.get_program().to_string_lossy().to_string();
+    match command.status() {
+        Ok(status) if status.success() => {}
+        Ok(status) => eprintln!("mdman: warning: {} failed with {}", program, status),
+        Err(e) => eprintln!("mdman: warning: Could not run {}. Error: {}", program, e),
+    }
+}
+
+/// Print the changes between two versions of a page, and exit like diff(1):
+/// 0 if they render the same, 1 if not, 2 if a page cannot be read.
+fn handle_diff(old: &Path, new: &Path, rendered: bool, roff_options: &RoffOptions) {
+    let sections = |file: &Path| {
+        let path = Some(file.to_path_buf());
+        let (man_nodes, _) = match get_md_content(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|md| parse_page(&md, &path).map_err(|e| e.to_string()))
+        {
+            Ok(page) => page,
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(2)
+            }
+        };
+        diff::sections(&man_nodes, |nodes| {
+            if rendered {
+                ansi::strip_styles(&ansi::ansi_page(nodes, 80))
+            } else {
+                render_page(nodes, roff_options).1.concat()
+            }
+        })
+    };
+    let changes = diff::diff(&sections(old), &sections(new));
+    if changes.is_empty() {
+        return;
+    }
+    print!("--- {}\n+++ {}\n{}", old.display(), new.display(), changes);
+    process::exit(1)
+}
+
+fn extract_examples(file: &Path, shell: bool) {
+    let path = Some(file.to_path_buf());
+    let man_nodes = match get_md_content(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|md| parse_page(&md, &path).map_err(|e| e.to_string()))
+    {
+        Ok((man_nodes, _)) => man_nodes,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1)
+        }
+    };
+    let found = examples::examples(&man_nodes);
+    if found.is_empty() {
+        eprintln!(
+            "mdman: No console or sh code blocks in the EXAMPLES section of {}",
+            file.display()
+        );
+        process::exit(1)
+    }
+    if shell {
+        let page = match man_node::title_line(&man_nodes) {
+            Some(title) => format!("{}({})", title.name, title.section),
+            None => file.display().to_string(),
+        };
+        print!("{}", examples::shell_script(&page, &found));
+        return;
+    }
+    for (i, example) in found.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        if !example.description.is_empty() {
+            println!("# {}", example.description);
+        }
+        for command in &example.commands {
+            println!("{}", command);
+        }
+    }
+}
+
+fn handle_lint(files: &[PathBuf], json: bool, checker: Option<&links::Checker>) {
+    let mut parsed = Vec::new();
+    for file in files {
+        let path = Some(file.clone());
+        match get_md_content(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|md| parse_page(&md, &path).map_err(|e| e.to_string()))
+        {
+            Ok((nodes, lines)) => parsed.push((file, nodes, lines)),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1)
+            }
+        }
+    }
+    let pages = parsed
+        .iter()
+        .map(|(file, nodes, lines)| lint::Page { file, nodes, lines })
+        .collect::<Vec<_>>();
+    let mut findings = pages.iter().flat_map(lint::page).collect::<Vec<_>>();
+    findings.extend(lint::project(&pages));
+    if let Some(checker) = checker {
+        match lint::dead_urls(&pages, checker) {
+            Ok(dead) => findings.extend(dead),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1)
+            }
+        }
+    }
+    if json {
+        print!("{}", pretty_json(&findings));
+    }
+    for finding in findings.iter().filter(|_| !json) {
+        let file = finding.file.display();
+        match finding.line {
+            Some(line) => print!("{}:{}: ", file, line),
+            None => print!("{}: ", file),
+        }
+        println!("{}: {}", finding.check, finding.message);
+    }
+    if !findings.is_empty() {
+        process::exit(1);
+    }
+}
+
+fn report_dropped(dropped: &[man_node::Dropped], file: &Option<PathBuf>) {
+    let file = file
+        .as_ref()
+        .map(|f| f.display().to_string())
+        .unwrap_or_else(|| "<stdin>".into());
+    for node in dropped {
+        match (node.line, node.column) {
+            (Some(line), Some(column)) => page_eprintln!(
+                "mdman: {}:{}:{}: {} is not supported",
+                file,
+                line,
+                column,
+                node.construct
+            ),
+            _ => page_eprintln!("mdman: {}: {} is not supported", file, node.construct),
+        }
+    }
+}
+
+/// A warning for each thing in the page that the HTML output cannot make
+/// work with a screen reader.
+fn warn_inaccessible(nodes: &[ManNode], source_lines: &[Option<usize>], file: &Option<PathBuf>) {
+    let file = file
+        .as_ref()
+        .map(|f| f.display().to_string())
+        .unwrap_or_else(|| "<stdin>".into());
+    for (i, message) in html::accessibility_problems(nodes) {
+        match source_lines.get(i).copied().flatten() {
+            Some(line) => page_eprintln!("mdman: warning: {}:{}: {}", file, line, message),
+            None => page_eprintln!("mdman: warning: {}: {}", file, message),
+        }
+    }
+}
+
+/// One warning per kind of construct that was left out, with how often and
+/// where.
+fn warn_dropped(dropped: &[man_node::Dropped], file: &Option<PathBuf>) {
+    let file = file
+        .as_ref()
+        .map(|f| f.display().to_string())
+        .unwrap_or_else(|| "<stdin>".into());
+    let mut constructs: Vec<(&str, usize, Vec<String>)> = Vec::new();
+    for node in dropped {
+        let line = node.line.map_or_else(|| "?".into(), |l| l.to_string());
+        match constructs.iter_mut().find(|(c, _, _)| *c == node.construct) {
+            Some((_, count, lines)) => {
+                *count += 1;
+                if lines.last() != Some(&line) {
+                    lines.push(line);
+                }
+            }
+            None => constructs.push((node.construct, 1, vec![line])),
+        }
+    }
+    for (construct, count, lines) in constructs {
+        let times = match count {
+            1 => "once".to_string(),
+            n => format!("{} times", n),
+        };
+        let label = if lines.len() == 1 { "line" } else { "lines" };
+        page_eprintln!(
+            "mdman: warning: {}: {} left out {} ({} {})",
+            file,
+            construct,
+            times,
+            label,
+            lines.join(", ")
+        );
+    }
+}
+
+/// Print the linter's findings as `file:line: message`, pointing at the
+/// Markdown source.
+fn report_lint(
+    roff: &str,
+    source_map: &validate::SourceMap,
+    file: &Option<PathBuf>,
+    timeout: Option<Duration>,
+) {
+    let file = file
+        .as_ref()
+        .map(|f| f.display().to_string())
+        .unwrap_or_else(|| "<stdin>".into());
+    match validate::lint(roff, timeout) {
+        Ok(diagnostics) => print_diagnostics(&diagnostics, source_map, &file),
+        Err(e) => page_eprintln!("{}", e),
+    }
+}
+
+/// Whether `out_path` holds exactly `content`. Says why not on stderr.
+fn check_output(out_path: &Path, content: &[u8]) -> bool {
+    let existing = match fs::read(out_path) {
+        Ok(existing) => existing,
+        Err(e) => {
+            page_eprintln!("mdman: Could not read {}. Error: {}", out_path.display(), e);
+            return false;
+        }
+    };
+    if existing == content {
+        return true;
+    }
+    let old = existing.split(|&b| b == b'\n').collect::<Vec<_>>();
+    let new = content.split(|&b| b == b'\n').collect::<Vec<_>>();
+    let line = old
+        .iter()
+        .zip(&new)
+        .position(|(old, new)| old != new)
+        .unwrap_or(old.len().min(new.len()))
+        + 1;
+    page_eprintln!(
+        "mdman: {} is out of date, first difference at line {}",
+        out_path.display(),
+        line
+    );
+    false
+}
+
+/// Run the page through the linter for --check, if one is installed, and
+/// print its warnings. Returns whether the page is clean.
+fn check_roff(
+    roff: &str,
+    source_map: &validate::SourceMap,
+    file: &Option<PathBuf>,
+    timeout: Option<Duration>,
+) -> bool {
+    let file = file
+        .as_ref()
+        .map(|f| f.display().to_string())
+        .unwrap_or_else(|| "<stdin>".into());
+    match validate::lint(roff, timeout) {
+        Ok(diagnostics) => {
+            print_diagnostics(&diagnostics, source_map, &file);
+            diagnostics.is_empty()
+        }
+        // Checking against the file on disk works without a linter.
+        Err(validate::ValidateError::NoLinter) => true,
+        Err(e) => {
+            page_eprintln!("{}", e);
+            false
+        }
+    }
+}
+
+/// Print what `man --warnings` says about the page and a summary line.
+/// Returns whether the page is clean.
+fn report_man_warnings(
+    roff: &str,
+    source_map: &validate::SourceMap,
+    file: &Option<PathBuf>,
+    timeout: Option<Duration>,
+) -> bool {
+    let file = file
+        .as_ref()
+        .map(|f| f.display().to_string())
+        .unwrap_or_else(|| "<stdin>".into());
+    match validate::man_warnings(roff, timeout) {
+        Ok(diagnostics) => {
+            print_diagnostics(&diagnostics, source_map, &file);
+            match diagnostics.len() {
+                0 => page_eprintln!("mdman: {}: no warnings from man", file),
+                1 => page_eprintln!("mdman: {}: 1 warning from man", file),
+                n => page_eprintln!("mdman: {}: {} warnings from man", file, n),
+            }
+            diagnostics.is_empty()
+        }
+        Err(e) => {
+            page_eprintln!("{}", e);
+            false
+        }
+    }
+}
+
+fn print_diagnostics(
+    diagnostics: &[validate::Diagnostic],
+    source_map: &validate::SourceMap,
+    file: &str,
+) {
+    for diagnostic in diagnostics {
+        match diagnostic.line.and_then(|l| source_map.markdown_line(l)) {
+            Some(line) => page_eprintln!("mdman: {}:{}: {}", file, line, diagnostic.message),
+            None => page_eprintln!("mdman: {}: {}", file, diagnostic.message),
+        }
+    }
+}
+
+fn print_portability_report(
+    man_nodes: &[ManNode],
+    source_lines: &[Option<usize>],
+    roff_options: &RoffOptions,
+    file: &Option<PathBuf>,
+) {
+    let file = file
+        .as_ref()
+        .map(|f| f.display().to_string())
+        .unwrap_or_else(|| "<stdin>".into());
+    let findings = portability::report(man_nodes, source_lines, roff_options);
+    if findings.is_empty() {
+        page_println!("{}: renders the same on mandoc, groff and nroff", file);
+    }
+    for finding in findings {
+        match finding.line {
+            Some(line) => {
+                page_println!("{}:{}: {}: {}", file, line, finding.construct, finding.note)
+            }
+            None => page_println!("{}: {}: {}", file, finding.construct, finding.note),
+        }
+    }
+}
+
+fn handle_pager(roff: &str, nodes: &[ManNode], timeout: Option<Duration>, language: Option<&str>) {
+    // Without a formatter, fall back to the built-in terminal rendering.
+    let formatter = if cfg!(target_os = "macos") {
+        "mandoc"
+    } else {
+        "man"
+    };
+    if external::find_executable(formatter).is_none() {
+        page_ansi(nodes);
+        return;
+    }
+
+    // mandoc formats without a terminal, so it gets the timeout; the pager
+    // runs for as long as the user reads.
+    #[cfg(target_os = "macos")]
+    let pager_cmd = external::catch_interrupt(|| {
+        // mandoc does not hyphenate, whatever the language.
+        _ = language;
+        external::run_filter(
+            Command::new("mandoc")
+                .arg("-a")
+                .stdout(process::Stdio::piped()),
+            roff.as_bytes(),
+            timeout,
+        )
+        .and_then(|output| external::pipe_to(&mut Command::new("less"), &output.stdout))
+    });
 
     #[cfg(target_os = "linux")]
-    let pager_cmd = Command::new("man")
-        .arg("-l")
-        .arg("-") // read from stdin
-        .stdin(Stdio::piped())
-        .spawn()
-        .and_then(|mut man| {
-            man.stdin.as_mut().unwrap().write_all(roff.as_bytes())?;
-            man.wait()?;
-            Ok(())
-        });
+    let pager_cmd = {
+        _ = timeout;
+        let mut man = Command::new("man");
+        man.arg("-l");
+        // man-db adds MANROFFOPT to the groff command line.
+        if let Some(language) = language {
+            let mut options = std::env::var("MANROFFOPT").unwrap_or_default();
+            options.push_str(&format!(" -m{}", language));
+            man.env("MANROFFOPT", options.trim_start());
+        }
+        external::catch_interrupt(|| external::page(&mut man, roff.as_bytes()))
+    };
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        _ = (roff, timeout, language);
+        page_ansi(nodes);
+        return;
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    finish_pager(pager_cmd);
+}
+
+/// Show the built-in rendering in `less -R`, or print it if there is no less.
+fn page_ansi(nodes: &[ManNode]) {
+    let page = AnsiRenderer::for_terminal().render(nodes);
+    if external::find_executable("less").is_none() {
+        _ = stdout().write_all(page.as_bytes());
+        return;
+    }
+    finish_pager(external::catch_interrupt(|| {
+        external::page(Command::new("less").arg("-R"), page.as_bytes())
+    }));
+}
 
-    if let Err(e) = pager_cmd {
-        eprintln!("mdman: Error showing man page in pager: {}", e);
-        std::process::exit(1);
+/// Report how the pager went. A pager killed by a signal may not have restored
+/// the terminal, so at least bring the cursor back. Pagers like less handle
+/// Ctrl-C themselves; if it quit one anyway, exit as if it had quit us.
+fn finish_pager((result, interrupted): (io::Result<process::ExitStatus>, bool)) {
+    let status = match result {
+        Ok(status) => status,
+        Err(e) => {
+            eprintln!("mdman: Error showing man page in pager: {}", e);
+            process::exit(1);
+        }
+    };
+    if status.code().is_none() {
+        external::restore_cursor();
+        if interrupted {
+            process::exit(130);
+        }
     }
 }
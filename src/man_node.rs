@@ -1,8 +1,14 @@
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
 use markdown::mdast::{
-    AlignKind, Code, Emphasis, Heading, InlineCode, Link, List, ListItem, Node, Paragraph, Root,
-    Strong, Table, TableCell, TableRow, Text, Yaml,
+    AlignKind, Blockquote, Code, Delete, Emphasis, FootnoteDefinition, FootnoteReference, Heading,
+    Html, InlineCode, Link, List, ListItem, Math, Node, Paragraph, Root, Strong, Table, TableCell,
+    TableRow, Text, Yaml,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug)]
 pub enum ManNode {
@@ -15,14 +21,42 @@ pub enum ManNode {
         title: String,
         children: Vec<ManNode>,
     },
+    /// `###` and deeper, which man pages have no macro for.
+    MinorHeading {
+        level: u8,
+        title: String,
+        children: Vec<ManNode>,
+    },
     Paragraph {
         children: Vec<ManNode>,
     },
     Text(String),
-    Bold(String),
-    Italic(String),
-    CodeBlock(String),
+    /// A line break in the source that is not a hard break.
+    SoftBreak,
+    /// A hard break: two trailing spaces or a backslash at the end of a line.
+    LineBreak,
+    Bold(Vec<ManNode>),
+    Italic(Vec<ManNode>),
+    /// `~~deleted~~` text.
+    Strikethrough(Vec<ManNode>),
+    CodeBlock {
+        /// The info string's first word, e.g. `console` for ```` ```console ````.
+        lang: Option<String>,
+        code: String,
+    },
+    /// Lines kept as written but in the regular font, from ```` ```literal ```` blocks.
+    NoFill(String),
+    /// A diagram for the pic preprocessor, from ```` ```pic ```` blocks.
+    Pic(String),
+    /// An equation for the eqn preprocessor, from ```` ```math ```` and `$$`
+    /// blocks.
+    Equation(String),
     InlineCode(String),
+    /// Roff emitted without escaping, from `` `...`{=roff} `` spans.
+    RawInline(String),
+    /// Lines of roff emitted as written, from ```` ```roff ```` blocks and
+    /// `<!-- roff: ... -->` comments.
+    RawBlock(String),
     BulletList {
         children: Vec<ManNode>,
     },
@@ -30,6 +64,9 @@ pub enum ManNode {
         children: Vec<ManNode>,
     },
     ListItem {
+        /// Whether the box of a task list item (`- [x] done`) is ticked;
+        /// `None` for ordinary items.
+        checked: Option<bool>,
         children: Vec<ManNode>,
     },
     Uri {
@@ -37,8 +74,16 @@ pub enum ManNode {
         title: Option<String>,
         children: Vec<ManNode>,
     },
+    /// A reference to another man page, e.g. `grep(1)` or `**grep**(1)`.
+    ManRef {
+        name: String,
+        section: String,
+    },
     Table {
         align: Vec<TableAlign>,
+        /// The frontmatter's `table-style`, if it has one.
+        style: Option<TableStyle>,
+        layout: TableLayout,
         children: Vec<ManNode>,
     },
     TableRow(Vec<ManNode>),
@@ -46,9 +91,472 @@ pub enum ManNode {
     DefinitionList {
         children: Vec<ManNode>,
     },
+    /// A block quote; its children are blocks (paragraphs, code, lists).
+    Blockquote {
+        children: Vec<ManNode>,
+    },
+}
+
+impl ManNode {
+    /// Child nodes of container variants; empty for leaves.
+    pub fn children(&self) -> &[ManNode] {
+        match self {
+            ManNode::SectionHeading { children, .. }
+            | ManNode::SubsectionHeading { children, .. }
+            | ManNode::MinorHeading { children, .. }
+            | ManNode::Paragraph { children }
+            | ManNode::BulletList { children }
+            | ManNode::NumberedList { children }
+            | ManNode::ListItem { children, .. }
+            | ManNode::Uri { children, .. }
+            | ManNode::Table { children, .. }
+            | ManNode::DefinitionList { children }
+            | ManNode::Blockquote { children }
+            | ManNode::Bold(children)
+            | ManNode::Italic(children)
+            | ManNode::Strikethrough(children)
+            | ManNode::TableRow(children)
+            | ManNode::TableCell(children) => children,
+            _ => &[],
+        }
+    }
+
+    /// Mutable [`ManNode::children`]; `None` for leaves.
+    pub fn children_mut(&mut self) -> Option<&mut Vec<ManNode>> {
+        match self {
+            ManNode::SectionHeading { children, .. }
+            | ManNode::SubsectionHeading { children, .. }
+            | ManNode::MinorHeading { children, .. }
+            | ManNode::Paragraph { children }
+            | ManNode::BulletList { children }
+            | ManNode::NumberedList { children }
+            | ManNode::ListItem { children, .. }
+            | ManNode::Uri { children, .. }
+            | ManNode::Table { children, .. }
+            | ManNode::DefinitionList { children }
+            | ManNode::Blockquote { children }
+            | ManNode::Bold(children)
+            | ManNode::Italic(children)
+            | ManNode::Strikethrough(children)
+            | ManNode::TableRow(children)
+            | ManNode::TableCell(children) => Some(children),
+            _ => None,
+        }
+    }
+
+    /// Bold plain text.
+    pub fn bold(text: impl Into<String>) -> ManNode {
+        ManNode::Bold(vec![ManNode::Text(text.into())])
+    }
+
+    /// Italic plain text.
+    pub fn italic(text: impl Into<String>) -> ManNode {
+        ManNode::Italic(vec![ManNode::Text(text.into())])
+    }
+
+    /// A code block without a language.
+    pub fn code_block(code: impl Into<String>) -> ManNode {
+        ManNode::CodeBlock {
+            lang: None,
+            code: code.into(),
+        }
+    }
+
+    /// A list item that is not a task.
+    pub fn list_item(children: Vec<ManNode>) -> ManNode {
+        ManNode::ListItem {
+            checked: None,
+            children,
+        }
+    }
+
+    /// Whether a task list item is ticked; `None` for anything else.
+    pub fn checked(&self) -> Option<bool> {
+        match self {
+            ManNode::ListItem { checked, .. } => *checked,
+            _ => None,
+        }
+    }
+
+    /// Visit this node and all of its descendants, depth-first.
+    pub fn walk<'a>(&'a self, f: &mut impl FnMut(&'a ManNode)) {
+        let mut stack = vec![self];
+        while let Some(node) = stack.pop() {
+            f(node);
+            stack.extend(node.children().iter().rev());
+        }
+    }
+
+    /// How many levels of nodes this one holds, itself included.
+    pub fn depth(&self) -> usize {
+        let mut deepest = 0;
+        let mut stack = vec![(self, 1)];
+        while let Some((node, depth)) = stack.pop() {
+            deepest = deepest.max(depth);
+            stack.extend(node.children().iter().map(|child| (child, depth + 1)));
+        }
+        deepest
+    }
+
+    /// The text of this node and its descendants, without any formatting.
+    pub fn plain_text(&self) -> String {
+        match self {
+            ManNode::Text(text) | ManNode::InlineCode(text) => text.clone(),
+            ManNode::SoftBreak | ManNode::LineBreak => " ".into(),
+            ManNode::ManRef { name, section } => format!("{}({})", name, section),
+            other => other.children().iter().map(ManNode::plain_text).collect(),
+        }
+    }
+}
+
+/// Make sure no node is nested deeper than [`MAX_DEPTH`], which is what
+/// [`crate::parse_page`] lets through and the renderers are known to handle.
+pub fn check_depth(nodes: &[ManNode]) -> Result<(), InputError> {
+    if nodes.iter().all(|node| node.depth() <= MAX_DEPTH) {
+        Ok(())
+    } else {
+        Err(InputError::TooDeep { line: None })
+    }
+}
+
+/// The frontmatter of the page, if it has any.
+pub fn title_line(nodes: &[ManNode]) -> Option<&TitleLine> {
+    nodes.iter().find_map(|n| match n {
+        ManNode::TitleLine(title_line) => Some(title_line),
+        _ => None,
+    })
+}
+
+/// Position of the section heading called `name` (case-insensitive).
+pub fn find_section(nodes: &[ManNode], name: &str) -> Option<usize> {
+    nodes.iter().position(
+        |n| matches!(n, ManNode::SectionHeading { title, .. } if title.eq_ignore_ascii_case(name)),
+    )
+}
+
+/// The section called `name`: its heading and everything up to the next one.
+pub fn section_range(nodes: &[ManNode], name: &str) -> Option<Range<usize>> {
+    let start = find_section(nodes, name)?;
+    let end = nodes[start + 1..]
+        .iter()
+        .position(|n| matches!(n, ManNode::SectionHeading { .. }))
+        .map_or(nodes.len(), |offset| start + 1 + offset);
+    Some(start..end)
+}
+
+/// Write section titles in capitals, e.g. `See also` as `SEE ALSO`.
+pub fn uppercase_sections(nodes: &mut [ManNode]) {
+    for node in nodes {
+        if let ManNode::SectionHeading { title, .. } = node {
+            *title = title.to_uppercase();
+        }
+    }
+}
+
+/// Prefix subsection titles and the minor headings below them with their
+/// number, counting sections from one: the second subsection of the third
+/// section becomes `3.2 Title`, a `###` heading in it `3.2.1 Title`.
+pub fn number_headings(nodes: &mut [ManNode]) {
+    // counters[0] counts sections, counters[1] subsections of the current
+    // section, and so on.
+    let mut counters: Vec<usize> = Vec::new();
+    for node in nodes {
+        let (depth, title) = match node {
+            ManNode::SectionHeading { .. } => {
+                let sections = counters.first().copied().unwrap_or(0);
+                counters = vec![sections + 1];
+                continue;
+            }
+            ManNode::SubsectionHeading { title, .. } => (2, title),
+            ManNode::MinorHeading { level, title, .. } => (usize::from(*level), title),
+            _ => continue,
+        };
+        counters.resize(depth, 0);
+        counters[depth - 1] += 1;
+        let number = counters
+            .iter()
+            .map(usize::to_string)
+            .collect::<Vec<_>>()
+            .join(".");
+        *title = format!("{} {}", number, title);
+    }
+}
+
+/// Insert a CONTENTS section after NAME (or at the start of the page) that
+/// lists the title of every section with its subsections indented below it.
+/// Returns the positions of the inserted nodes.
+pub fn insert_contents(nodes: &mut Vec<ManNode>) -> Range<usize> {
+    let mut lines = Vec::new();
+    for node in nodes.iter() {
+        match node {
+            ManNode::SectionHeading { title, .. } => lines.push(title.clone()),
+            ManNode::SubsectionHeading { title, .. } => lines.push(format!("    {}", title)),
+            _ => {}
+        }
+    }
+    let idx = match section_range(nodes, "NAME") {
+        Some(name) => name.end,
+        None => nodes
+            .iter()
+            .take_while(|n| matches!(n, ManNode::TitleLine(_)))
+            .count(),
+    };
+    let heading = ManNode::SectionHeading {
+        title: "CONTENTS".into(),
+        children: vec![],
+    };
+    nodes.splice(idx..idx, [heading, ManNode::NoFill(lines.join("\n"))]);
+    idx..idx + 2
+}
+
+/// How links are shown.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum UrlStyle {
+    /// Where they are, with `.UR`/`.UE`.
+    #[default]
+    Inline,
+    /// As a number like `[1]` after the link text, with the URLs listed in a
+    /// NOTES section at the end, as on paper.
+    Footnotes,
+    /// Only the link text.
+    Omit,
+}
+
+/// Replace every link with `replace(url, text)`, depth-first.
+fn replace_uris(
+    nodes: &mut Vec<ManNode>,
+    replace: &mut impl FnMut(&str, Vec<ManNode>) -> Vec<ManNode>,
+) {
+    let mut i = 0;
+    while i < nodes.len() {
+        if let Some(children) = nodes[i].children_mut() {
+            replace_uris(children, replace);
+        }
+        if let ManNode::Uri { url, children, .. } = &mut nodes[i] {
+            let replacement = replace(url, std::mem::take(children));
+            let count = replacement.len();
+            nodes.splice(i..i + 1, replacement);
+            i += count;
+        } else {
+            i += 1;
+        }
+    }
+}
+
+/// Whether a link shows its own target, like `<https://example.com>`.
+fn is_autolink(url: &str, text: &[ManNode]) -> bool {
+    let text = text.iter().map(ManNode::plain_text).collect::<String>();
+    [
+        url,
+        url.trim_start_matches("mailto:"),
+        url.trim_start_matches("http://"),
+    ]
+    .contains(&text.as_str())
+}
+
+/// Show links as `style` says. For footnotes, returns the positions of the
+/// inserted section: NOTES, or REFERENCES if the page has a NOTES section of
+/// its own. Links that show their target anyway are left as they are, and a
+/// URL linked more than once gets one number. The numbers follow on from
+/// those of the page's GFM footnotes, and are listed with the same `[n]`.
+pub fn apply_url_style(nodes: &mut Vec<ManNode>, style: UrlStyle) -> Range<usize> {
+    let end = nodes.len()..nodes.len();
+    match style {
+        UrlStyle::Inline => end,
+        UrlStyle::Omit => {
+            replace_uris(nodes, &mut |_, text| text);
+            end
+        }
+        UrlStyle::Footnotes => {
+            let first = footnote_count(nodes) + 1;
+            let mut notes: Vec<(String, String)> = Vec::new();
+            replace_uris(nodes, &mut |url, mut text| {
+                if is_autolink(url, &text) {
+                    return vec![ManNode::Uri {
+                        url: url.to_string(),
+                        title: None,
+                        children: text,
+                    }];
+                }
+                let number = match notes.iter().position(|(u, _)| u == url) {
+                    Some(i) => first + i,
+                    None => {
+                        let plain = text.iter().map(ManNode::plain_text).collect();
+                        notes.push((url.to_string(), plain));
+                        first + notes.len() - 1
+                    }
+                };
+                text.push(ManNode::Text(format!("[{}]", number)));
+                text
+            });
+            if notes.is_empty() {
+                return end;
+            }
+            let title = match find_section(nodes, "NOTES") {
+                Some(_) => "REFERENCES",
+                None => "NOTES",
+            };
+            // Laid out like the NOTES of GFM footnotes (see
+            // `ConvertState::take_notes`).
+            let items = notes.into_iter().zip(first..).map(|((url, text), number)| {
+                ManNode::list_item(vec![
+                    ManNode::Text(format!("[{}]", number)),
+                    ManNode::Text("\n".into()),
+                    ManNode::Text(text),
+                    ManNode::LineBreak,
+                    ManNode::Text(url),
+                ])
+            });
+            nodes.push(ManNode::SectionHeading {
+                title: title.into(),
+                children: vec![],
+            });
+            nodes.push(ManNode::DefinitionList {
+                children: items.collect(),
+            });
+            end.start..nodes.len()
+        }
+    }
+}
+
+/// How many GFM footnotes the page has: the items of the NOTES section made
+/// for them, which are `[1]`, `[2]` and so on. Every reference has a
+/// definition, or it would not have been parsed as one.
+fn footnote_count(nodes: &[ManNode]) -> usize {
+    let is_first_note = |item: &ManNode| {
+        matches!(item.children(), [ManNode::Text(number), ManNode::Text(newline), ..]
+            if number == "[1]" && newline == "\n")
+    };
+    nodes
+        .windows(2)
+        .find_map(|pair| match pair {
+            [
+                ManNode::SectionHeading { title, .. },
+                ManNode::DefinitionList { children },
+            ] if title == "NOTES" && children.first().is_some_and(is_first_note) => {
+                Some(children.len())
+            }
+            _ => None,
+        })
+        .unwrap_or(0)
+}
+
+/// Level of a heading node, counting `#` as 1; `None` for other nodes.
+fn heading_level(node: &ManNode) -> Option<(u8, &str)> {
+    match node {
+        ManNode::SectionHeading { title, .. } => Some((1, title)),
+        ManNode::SubsectionHeading { title, .. } => Some((2, title)),
+        ManNode::MinorHeading { level, title, .. } => Some((*level, title)),
+        _ => None,
+    }
+}
+
+/// Put a table of the subcommands at the top of the SUBCOMMANDS section, each
+/// with the first sentence of the paragraph below its heading. Subcommands
+/// are the headings one level below SUBCOMMANDS, e.g. the `###` headings of a
+/// `## SUBCOMMANDS` subsection. Sections without subcommands, or that start
+/// with a table already, are left alone. Returns the position of the table.
+pub fn insert_subcommand_table(nodes: &mut Vec<ManNode>) -> Option<usize> {
+    let start = nodes.iter().position(|n| {
+        heading_level(n).is_some_and(|(_, title)| title.eq_ignore_ascii_case("SUBCOMMANDS"))
+    })?;
+    let (level, _) = heading_level(&nodes[start])?;
+    let end = nodes[start + 1..]
+        .iter()
+        .position(|n| heading_level(n).is_some_and(|(l, _)| l <= level))
+        .map_or(nodes.len(), |offset| start + 1 + offset);
+    if matches!(nodes.get(start + 1), Some(ManNode::Table { .. })) {
+        return None;
+    }
+
+    let cell = |node| ManNode::TableCell(vec![node]);
+    let mut rows = vec![ManNode::TableRow(vec![
+        cell(ManNode::Text("Command".into())),
+        cell(ManNode::Text("Description".into())),
+    ])];
+    for (i, node) in nodes.iter().enumerate().take(end).skip(start + 1) {
+        let Some((l, name)) = heading_level(node).filter(|(l, _)| *l == level + 1) else {
+            continue;
+        };
+        let summary = nodes[i + 1..end]
+            .iter()
+            .take_while(|n| heading_level(n).is_none_or(|(deeper, _)| deeper > l))
+            .find(|n| matches!(n, ManNode::Paragraph { .. }))
+            .map(|n| first_sentence(&n.plain_text()))
+            .unwrap_or_default();
+        rows.push(ManNode::TableRow(vec![
+            cell(ManNode::bold(name)),
+            cell(ManNode::Text(summary)),
+        ]));
+    }
+    if rows.len() == 1 {
+        return None;
+    }
+    let table = ManNode::Table {
+        align: vec![TableAlign::Left, TableAlign::Left],
+        style: title_line(nodes).and_then(|t| t.table_style),
+        layout: TableLayout::default(),
+        children: rows,
+    };
+    nodes.insert(start + 1, table);
+    Some(start + 1)
+}
+
+/// The first sentence of `text` on one line, without its full stop.
+fn first_sentence(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let first = match text.find(". ") {
+        Some(end) => &text[..end],
+        None => &text,
+    };
+    first.trim_end_matches('.').to_string()
+}
+
+/// What separates an inline node from the one before it in the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Spacing {
+    /// The nodes touch, as in `` `word`(1) ``; also used for the first node.
+    None,
+    /// Blanks, as in `` `word` (1) ``.
+    Space,
+    /// A line break in the source.
+    LineBreak,
+}
+
+/// An inline node and how it is separated from its predecessor.
+#[derive(Debug)]
+pub(crate) struct InlineRun<'a> {
+    pub node: &'a ManNode,
+    pub spacing: Spacing,
 }
 
-#[derive(Debug, Deserialize)]
+/// Pair the inline children of a paragraph, list item or link with the
+/// spacing the author put between them. The roff renderer needs it where a
+/// link or page reference goes on a macro line of its own and the blanks
+/// around it are lost; the other renderers write the text with its blanks.
+pub(crate) fn inline_runs(children: &[ManNode]) -> Vec<InlineRun<'_>> {
+    let mut runs = Vec::with_capacity(children.len());
+    let mut previous: Option<&ManNode> = None;
+    for node in children {
+        let spacing = match (previous, node) {
+            (None, _) => Spacing::None,
+            (Some(ManNode::SoftBreak | ManNode::LineBreak), _)
+            | (_, ManNode::SoftBreak | ManNode::LineBreak) => Spacing::LineBreak,
+            (Some(ManNode::Text(before)), _) if before.ends_with(char::is_whitespace) => {
+                Spacing::Space
+            }
+            (_, ManNode::Text(text)) if text.starts_with('\n') => Spacing::LineBreak,
+            (_, ManNode::Text(text)) if text.starts_with(char::is_whitespace) => Spacing::Space,
+            _ => Spacing::None,
+        };
+        runs.push(InlineRun { node, spacing });
+        previous = Some(node);
+    }
+    runs
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all(serialize = "kebab-case"))]
 pub struct TitleLine {
     pub name: String,
     pub section: u8,
@@ -57,6 +565,42 @@ pub struct TitleLine {
     pub left_footer: Option<String>,
     #[serde(alias = "center-footer")]
     pub center_footer: Option<String>,
+    /// YAML/JSON file describing the flags to render into the OPTIONS section.
+    #[serde(alias = "options-file")]
+    pub options_file: Option<PathBuf>,
+    /// Language of the page (e.g. `de`), used for hyphenation.
+    pub lang: Option<String>,
+    /// Frame of the page's tables.
+    #[serde(alias = "table-style", skip_serializing)]
+    pub table_style: Option<TableStyle>,
+    /// Version of the software the page describes. Not rendered; `mdman lint`
+    /// checks that all pages of a project agree on it.
+    pub version: Option<String>,
+}
+
+/// Column widths and tbl options for one table, from a `<!-- table: ... -->`
+/// comment before it.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TableLayout {
+    /// Width of each column in ens; `None` leaves it to the renderer.
+    pub widths: Vec<Option<usize>>,
+    /// Spread the table over the whole line (tbl's `expand`).
+    pub expand: bool,
+}
+
+/// The frame tbl draws around a table and its cells.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TableStyle {
+    /// A box around the table and rules between all cells.
+    #[default]
+    Allbox,
+    /// A box around the table only.
+    Box,
+    /// A double box around the table only.
+    Doublebox,
+    /// No rules at all, as in most traditional man pages.
+    None,
 }
 
 #[derive(Debug)]
@@ -78,57 +622,333 @@ impl From<&AlignKind> for TableAlign {
     }
 }
 
-pub struct ConvertState {
+/// Nesting deeper than this (lists in lists, quotes in quotes, emphasis in
+/// emphasis) is refused rather than followed until the stack runs out.
+pub const MAX_DEPTH: usize = 100;
+
+/// Markdown that cannot be turned into a page.
+#[derive(Debug)]
+pub enum InputError {
+    /// `line` is the line of the Markdown file the YAML error points at.
+    Frontmatter {
+        error: serde_yaml::Error,
+        line: Option<usize>,
+    },
+    TooDeep {
+        line: Option<usize>,
+    },
+}
+
+impl InputError {
+    /// The message, naming `file` as the page the error is in.
+    pub fn describe(&self, file: Option<&Path>) -> String {
+        let file = file.map_or(String::new(), |f| format!(" in {}", f.display()));
+        let at = |line: &Option<usize>| line.map_or(String::new(), |l| format!(" at line {}", l));
+        match self {
+            InputError::Frontmatter { error, line } => {
+                // The YAML error's own location counts from the frontmatter.
+                let mut message = error.to_string();
+                if let Some(location) = error.location() {
+                    let suffix =
+                        format!(" at line {} column {}", location.line(), location.column());
+                    if let Some(stripped) = message.strip_suffix(&suffix) {
+                        message = stripped.to_string();
+                    }
+                }
+                format!(
+                    "mdman: Could not parse the frontmatter{}{}. Error: {}",
+                    file,
+                    at(line),
+                    message
+                )
+            }
+            InputError::TooDeep { line } => format!(
+                "mdman: Markdown{} nested more than {} levels deep{}",
+                file,
+                MAX_DEPTH,
+                at(line)
+            ),
+        }
+    }
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.describe(None))
+    }
+}
+
+impl std::error::Error for InputError {}
+
+/// Markdown that was left out of the page because it has no man page
+/// equivalent, e.g. an image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dropped {
+    /// What was dropped, e.g. `image`.
+    pub construct: &'static str,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+/// What the conversion of one page remembers from node to node. Every call
+/// to [`crate::parse_page`] makes its own.
+#[derive(Default)]
+pub(crate) struct ConvertState {
     in_definition_list: bool,
+    /// Whether the text of a link is being converted; it keeps its `name(1)`s.
+    in_link: bool,
+    table_style: Option<TableStyle>,
+    /// Layout from a table comment, waiting for the next table.
+    table_layout: Option<TableLayout>,
+    /// Title of the subsection (or section) being converted.
+    location: String,
+    /// Terms of index comments and where they were found.
+    index: Vec<(String, String)>,
+    /// Terms and definitions of glossary comments.
+    glossary: Vec<(String, String)>,
+    depth: usize,
+    /// The first problem with the input; conversion goes on without the
+    /// offending part.
+    error: Option<InputError>,
+    /// Footnote identifiers in the order of their first reference.
+    footnote_refs: Vec<String>,
+    /// Converted footnote definitions and their identifiers.
+    footnotes: Vec<(String, Vec<ManNode>)>,
+    /// Nodes that were not converted, in document order.
+    dropped: Vec<Dropped>,
 }
 
 impl ConvertState {
     pub fn new() -> Self {
         Self {
             in_definition_list: false,
+            in_link: false,
+            table_style: None,
+            table_layout: None,
+            location: String::new(),
+            index: Vec::new(),
+            glossary: Vec::new(),
+            depth: 0,
+            error: None,
+            footnote_refs: Vec::new(),
+            footnotes: Vec::new(),
+            dropped: Vec::new(),
         }
     }
     fn toggle_in_definition_list(&mut self) {
         self.in_definition_list = !self.in_definition_list
     }
+
+    fn fail(&mut self, error: InputError) {
+        self.error.get_or_insert(error);
+    }
+
+    /// Why the input could not be converted completely, if it could not.
+    pub fn take_error(&mut self) -> Option<InputError> {
+        self.error.take()
+    }
+
+    fn drop_node(&mut self, construct: &'static str, node: &Node) {
+        let position = node.position();
+        self.dropped.push(Dropped {
+            construct,
+            line: position.map(|p| p.start.line),
+            column: position.map(|p| p.start.column),
+        });
+    }
+
+    /// What was left out of the page.
+    pub fn take_dropped(&mut self) -> Vec<Dropped> {
+        std::mem::take(&mut self.dropped)
+    }
+
+    /// The number of a footnote, counting in the order of first reference.
+    fn footnote_number(&mut self, identifier: &str) -> usize {
+        match self.footnote_refs.iter().position(|id| id == identifier) {
+            Some(i) => i + 1,
+            None => {
+                self.footnote_refs.push(identifier.to_string());
+                self.footnote_refs.len()
+            }
+        }
+    }
+
+    /// The GLOSSARY section for the glossary comments of the page.
+    fn take_glossary(&mut self) -> Vec<ManNode> {
+        glossary_section(std::mem::take(&mut self.glossary))
+    }
+
+    /// The INDEX section: every indexed term, sorted, with the subsections
+    /// it appears in.
+    fn take_index(&mut self) -> Vec<ManNode> {
+        let mut entries = std::mem::take(&mut self.index);
+        if entries.is_empty() {
+            return vec![];
+        }
+        entries.sort_by_cached_key(|(term, _)| term.to_lowercase());
+        let mut items: Vec<(String, Vec<String>)> = Vec::new();
+        for (term, location) in entries {
+            match items.last_mut() {
+                Some((last, locations)) if *last == term => {
+                    if !locations.contains(&location) {
+                        locations.push(location);
+                    }
+                }
+                _ => items.push((term, vec![location])),
+            }
+        }
+        let items = items
+            .into_iter()
+            .map(|(term, locations)| {
+                ManNode::list_item(vec![
+                    ManNode::Text(term),
+                    ManNode::Text("\n".into()),
+                    ManNode::Text(locations.join(", ")),
+                ])
+            })
+            .collect();
+        vec![
+            ManNode::SectionHeading {
+                title: "INDEX".into(),
+                children: vec![],
+            },
+            ManNode::DefinitionList { children: items },
+        ]
+    }
+
+    /// The NOTES section for the footnotes referenced so far, to go at the end
+    /// of the page. Definitions nothing refers to are left out, as in GFM.
+    fn take_notes(&mut self) -> Vec<ManNode> {
+        let mut definitions = std::mem::take(&mut self.footnotes);
+        let items = self
+            .footnote_refs
+            .iter()
+            .enumerate()
+            .filter_map(|(i, id)| {
+                let at = definitions.iter().position(|(def, _)| def == id)?;
+                let (_, children) = definitions.swap_remove(at);
+                let mut item = vec![
+                    ManNode::Text(format!("[{}]", i + 1)),
+                    ManNode::Text("\n".into()),
+                ];
+                item.extend(children);
+                Some(ManNode::list_item(item))
+            })
+            .collect::<Vec<_>>();
+        if items.is_empty() {
+            return vec![];
+        }
+        vec![
+            ManNode::SectionHeading {
+                title: "NOTES".into(),
+                children: vec![],
+            },
+            ManNode::DefinitionList { children: items },
+        ]
+    }
+}
+
+pub(crate) fn convert_markdown_node(node: &Node, state: &mut ConvertState) -> Vec<ManNode> {
+    if state.depth == MAX_DEPTH {
+        let line = node.position().map(|p| p.start.line);
+        state.fail(InputError::TooDeep { line });
+        return vec![];
+    }
+    state.depth += 1;
+    let nodes = convert_node(node, state);
+    state.depth -= 1;
+    nodes
 }
 
-pub fn convert_markdown_node(node: &Node, state: &mut ConvertState) -> Vec<ManNode> {
+fn convert_node(node: &Node, state: &mut ConvertState) -> Vec<ManNode> {
     match node {
-        Node::Root(Root { children, .. }) => children
-            .iter()
-            .flat_map(|x| convert_markdown_node(x, state))
-            .collect(),
-        Node::Yaml(Yaml { value, .. }) => {
-            let title_line = serde_yaml::from_str::<TitleLine>(value).unwrap();
-            vec![ManNode::TitleLine(title_line)]
+        Node::Root(Root { children, .. }) => {
+            let mut nodes = children
+                .iter()
+                .flat_map(|x| convert_markdown_node(x, state))
+                .collect::<Vec<_>>();
+            nodes.extend(state.take_notes());
+            nodes
         }
+        Node::Yaml(Yaml {
+            value, position, ..
+        }) => match serde_yaml::from_str::<TitleLine>(value) {
+            Ok(title_line) => {
+                state.table_style = title_line.table_style;
+                vec![ManNode::TitleLine(title_line)]
+            }
+            Err(e) => {
+                // The YAML starts on the line after the opening `---`.
+                let line = e
+                    .location()
+                    .zip(position.as_ref())
+                    .map(|(location, position)| position.start.line + location.line());
+                state.fail(InputError::Frontmatter { error: e, line });
+                vec![]
+            }
+        },
         Node::Heading(Heading {
             depth, children, ..
         }) => {
-            let title = children.iter().map(extract_simple_text).collect();
-            let heading = if *depth == 1 {
-                ManNode::SectionHeading {
+            let title: String = children.iter().map(extract_simple_text).collect();
+            if *depth <= 2 {
+                state.location = title.clone();
+            }
+            let heading = match depth {
+                1 => ManNode::SectionHeading {
                     title,
                     children: vec![],
-                }
-            } else {
-                ManNode::SubsectionHeading {
+                },
+                2 => ManNode::SubsectionHeading {
                     title,
                     children: vec![],
-                }
+                },
+                level => ManNode::MinorHeading {
+                    level: *level,
+                    title,
+                    children: vec![],
+                },
             };
             vec![heading]
         }
+        Node::Math(Math { value, .. }) => vec![ManNode::Equation(value.to_string())],
         Node::Paragraph(Paragraph { children, .. }) => {
-            let inlines = children
-                .iter()
-                .flat_map(|x| convert_markdown_node(x, state))
-                .collect();
+            let inlines = convert_inlines(children, state);
             vec![ManNode::Paragraph { children: inlines }]
         }
-        Node::Code(Code { value, .. }) => {
-            vec![ManNode::CodeBlock(value.to_string())]
+        Node::Code(Code { value, lang, .. }) => match lang.as_deref() {
+            Some("literal") => vec![ManNode::NoFill(value.to_string())],
+            Some("pic") => vec![ManNode::Pic(value.to_string())],
+            Some("math") => vec![ManNode::Equation(value.to_string())],
+            Some("roff") => vec![ManNode::RawBlock(value.to_string())],
+            lang => vec![ManNode::CodeBlock {
+                lang: lang.map(str::to_string),
+                code: value.to_string(),
+            }],
+        },
+        Node::Html(Html { value, .. }) => {
+            if let Some(layout) = table_comment(value) {
+                state.table_layout = Some(layout);
+                return vec![];
+            }
+            if let Some((term, definition)) = glossary_comment(value) {
+                let entry = (term.to_string(), definition.to_string());
+                state.glossary.push(entry);
+                return vec![];
+            }
+            if let Some(term) = directive(value, "index:").filter(|t| !t.is_empty()) {
+                let entry = (term.to_string(), state.location.clone());
+                state.index.push(entry);
+                return vec![];
+            }
+            // Other HTML means nothing in a man page.
+            if !value.trim_start().starts_with("<!--") {
+                state.drop_node("HTML", node);
+            }
+            roff_comment(value)
+                .map(|roff| ManNode::RawBlock(roff.to_string()))
+                .into_iter()
+                .collect()
         }
         Node::List(List {
             children, ordered, ..
@@ -136,37 +956,48 @@ pub fn convert_markdown_node(node: &Node, state: &mut ConvertState) -> Vec<ManNo
             let items = children
                 .iter()
                 .flat_map(|x| convert_markdown_node(x, state))
-                .collect();
+                .collect::<Vec<_>>();
 
-            let man_node = match (ordered, state.in_definition_list) {
+            let definitions = state.in_definition_list
+                || (!items.is_empty() && items.iter().all(is_definition_item));
+            let man_node = match (ordered, definitions) {
                 (true, _) => ManNode::NumberedList { children: items },
-                (false, true) => ManNode::DefinitionList { children: items },
+                (false, true) => ManNode::DefinitionList {
+                    children: items.into_iter().map(split_definition_term).collect(),
+                },
                 (false, false) => ManNode::BulletList { children: items },
             };
             vec![man_node]
         }
-        Node::ListItem(ListItem { children, .. }) => {
-            let mut items = Vec::new();
-            for child in children {
-                let p_nodes = convert_markdown_node(child, state);
-                for n in p_nodes {
-                    match n {
-                        ManNode::Paragraph { children } => items.extend(children),
-                        _ => items.push(n),
-                    }
-                }
-            }
-            vec![ManNode::ListItem { children: items }]
+        Node::ListItem(ListItem {
+            children, checked, ..
+        }) => vec![ManNode::ListItem {
+            checked: *checked,
+            children: item_children(children, state),
+        }],
+        Node::FootnoteReference(FootnoteReference { identifier, .. }) => {
+            let number = state.footnote_number(identifier);
+            vec![ManNode::Text(format!("[{}]", number))]
+        }
+        Node::FootnoteDefinition(FootnoteDefinition {
+            identifier,
+            children,
+            ..
+        }) => {
+            let children = item_children(children, state);
+            state.footnotes.push((identifier.clone(), children));
+            vec![]
         }
-        Node::Text(Text { value, .. }) => vec![ManNode::Text(value.to_string())],
+        Node::Text(Text { value, .. }) => split_soft_breaks(value),
+        Node::Break(_) => vec![ManNode::LineBreak],
         Node::Emphasis(Emphasis { children, .. }) => {
-            // TODO: Now no support for nested formatting.
-            let text = children.iter().map(extract_simple_text).collect();
-            vec![ManNode::Italic(text)]
+            vec![ManNode::Italic(convert_inlines(children, state))]
         }
         Node::Strong(Strong { children, .. }) => {
-            let text = children.iter().map(extract_simple_text).collect();
-            vec![ManNode::Bold(text)]
+            vec![ManNode::Bold(convert_inlines(children, state))]
+        }
+        Node::Delete(Delete { children, .. }) => {
+            vec![ManNode::Strikethrough(convert_inlines(children, state))]
         }
         Node::InlineCode(InlineCode { value, .. }) => vec![ManNode::InlineCode(value.to_string())],
         Node::Link(Link {
@@ -175,10 +1006,9 @@ pub fn convert_markdown_node(node: &Node, state: &mut ConvertState) -> Vec<ManNo
             title,
             ..
         }) => {
-            let items = children
-                .iter()
-                .flat_map(|x| convert_markdown_node(x, state))
-                .collect();
+            let in_link = std::mem::replace(&mut state.in_link, true);
+            let items = convert_inlines(children, state);
+            state.in_link = in_link;
             vec![ManNode::Uri {
                 url: url.clone(),
                 title: title.clone(),
@@ -195,6 +1025,8 @@ pub fn convert_markdown_node(node: &Node, state: &mut ConvertState) -> Vec<ManNo
             let table_align = align.iter().map(Into::into).collect();
             vec![ManNode::Table {
                 align: table_align,
+                style: state.table_style,
+                layout: state.table_layout.take().unwrap_or_default(),
                 children: items,
             }]
         }
@@ -206,33 +1038,377 @@ pub fn convert_markdown_node(node: &Node, state: &mut ConvertState) -> Vec<ManNo
             vec![ManNode::TableRow(items)]
         }
         Node::TableCell(TableCell { children, .. }) => {
-            let items = children
-                .iter()
-                .flat_map(|x| convert_markdown_node(x, state))
-                .collect();
+            let items = convert_inlines(children, state);
             vec![ManNode::TableCell(items)]
         }
+        Node::Blockquote(Blockquote { children, .. }) => vec![ManNode::Blockquote {
+            children: children
+                .iter()
+                .flat_map(|x| convert_markdown_node(x, state))
+                .collect(),
+        }],
         Node::ThematicBreak(_) => {
             state.toggle_in_definition_list();
             vec![]
         }
+        // Only there for link references, which are dropped themselves.
+        Node::Definition(_) => vec![],
         _ => {
-            // dbg!(&node);
+            let construct = match node {
+                Node::Image(_) => "image",
+                Node::ImageReference(_) => "image reference",
+                Node::LinkReference(_) => "link reference",
+                Node::InlineMath(_) => "inline math",
+                Node::Toml(_) => "TOML frontmatter",
+                _ => "MDX",
+            };
+            state.drop_node(construct, node);
             vec![]
         }
     }
 }
 
+fn split_soft_breaks(text: &str) -> Vec<ManNode> {
+    let mut nodes = Vec::new();
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            nodes.push(ManNode::SoftBreak);
+        }
+        if !line.is_empty() {
+            nodes.push(ManNode::Text(line.to_string()));
+        }
+    }
+    nodes
+}
+
+/// Convert a whole document like `convert_markdown_node`, additionally
+/// returning for every top-level node the Markdown line of the block it came
+/// from.
+/// The blocks of a list item or footnote, with paragraphs unwrapped to their
+/// inline content.
+fn item_children(children: &[Node], state: &mut ConvertState) -> Vec<ManNode> {
+    let mut items = Vec::new();
+    let mut after_paragraph = false;
+    for child in children {
+        let p_nodes = convert_markdown_node(child, state);
+        for n in p_nodes {
+            match n {
+                ManNode::Paragraph { children } => {
+                    // Keep consecutive paragraphs apart instead of gluing
+                    // their words together.
+                    if after_paragraph {
+                        items.push(ManNode::Text("\n".into()));
+                    }
+                    items.extend(children);
+                    after_paragraph = true;
+                }
+                _ => {
+                    items.push(n);
+                    after_paragraph = false;
+                }
+            }
+        }
+    }
+    items
+}
+
+pub(crate) fn convert_with_source_lines(
+    root: &Node,
+    state: &mut ConvertState,
+) -> (Vec<ManNode>, Vec<Option<usize>>) {
+    let mut nodes = Vec::new();
+    let mut lines = Vec::new();
+    for child in root.children().into_iter().flatten() {
+        let converted = convert_markdown_node(child, state);
+        let line = child.position().map(|p| p.start.line);
+        lines.extend(std::iter::repeat_n(line, converted.len()));
+        nodes.extend(converted);
+    }
+    let notes = state.take_notes();
+    lines.extend(std::iter::repeat_n(None, notes.len()));
+    nodes.extend(notes);
+    let glossary = state.take_glossary();
+    lines.extend(std::iter::repeat_n(None, glossary.len()));
+    nodes.extend(glossary);
+    let index = state.take_index();
+    lines.extend(std::iter::repeat_n(None, index.len()));
+    nodes.extend(index);
+    (nodes, lines)
+}
+
+/// Whether a list item reads as a definition without `---` around its list:
+/// a first line of just bold, italic and code, starting with bold or code
+/// (`**-o**, **--output** *FILE*`), then the description on the lines after it.
+fn is_definition_item(item: &ManNode) -> bool {
+    let children = item.children();
+    let is_break =
+        |n: &ManNode| matches!(n, ManNode::SoftBreak) || matches!(n, ManNode::Text(t) if t == "\n");
+    let Some(end) = children.iter().position(is_break) else {
+        return false;
+    };
+    let (term, description) = (&children[..end], &children[end + 1..]);
+    let term_like = term.iter().all(|n| match n {
+        ManNode::Bold(_) | ManNode::Italic(_) | ManNode::InlineCode(_) => true,
+        ManNode::Text(text) => text
+            .chars()
+            .all(|c| c.is_whitespace() || ",=[]|<>".contains(c)),
+        _ => false,
+    });
+    item.checked().is_none()
+        && matches!(
+            term.first(),
+            Some(ManNode::Bold(_) | ManNode::InlineCode(_))
+        )
+        && term_like
+        && !description.iter().all(|n| n.plain_text().trim().is_empty())
+}
+
+/// The first line of a definition list item is the term, so its line break
+/// must survive whatever soft break policy is used for the rest.
+fn split_definition_term(mut item: ManNode) -> ManNode {
+    if let ManNode::ListItem { children, .. } = &mut item
+        && let Some(first_break) = children
+            .iter_mut()
+            .find(|n| matches!(n, ManNode::SoftBreak))
+    {
+        *first_break = ManNode::Text("\n".into());
+    }
+    item
+}
+
+/// Marker directly after an inline code span that turns it into raw roff.
+const RAW_ROFF_MARKER: &str = "{=roff}";
+
+/// The rest of a `<!-- keyword ... -->` comment, trimmed.
+fn directive<'a>(html: &'a str, keyword: &str) -> Option<&'a str> {
+    let comment = html.trim().strip_prefix("<!--")?.strip_suffix("-->")?;
+    Some(comment.trim_start().strip_prefix(keyword)?.trim())
+}
+
+/// The term and definition in a `<!-- mdman:gloss term: definition -->` comment.
+fn glossary_comment(html: &str) -> Option<(&str, &str)> {
+    let (term, definition) = directive(html, "mdman:gloss")?.split_once(':')?;
+    let (term, definition) = (term.trim(), definition.trim());
+    (!term.is_empty() && !definition.is_empty()).then_some((term, definition))
+}
+
+/// A GLOSSARY section with the terms in alphabetical order; a term defined
+/// more than once keeps its first definition.
+fn glossary_section(mut entries: Vec<(String, String)>) -> Vec<ManNode> {
+    if entries.is_empty() {
+        return vec![];
+    }
+    // A stable sort keeps the first definition of a term first.
+    entries.sort_by_cached_key(|(term, _)| term.to_lowercase());
+    entries.dedup_by(|(term, _), (first, _)| term == first);
+    let items = entries
+        .into_iter()
+        .map(|(term, definition)| {
+            ManNode::list_item(vec![
+                ManNode::Text(term),
+                ManNode::Text("\n".into()),
+                ManNode::Text(definition),
+            ])
+        })
+        .collect();
+    vec![
+        ManNode::SectionHeading {
+            title: "GLOSSARY".into(),
+            children: vec![],
+        },
+        ManNode::DefinitionList { children: items },
+    ]
+}
+
+/// Give every page with a GLOSSARY section the terms of all of them, so that
+/// the pages of a set define the same terms the same way.
+pub fn merge_glossaries(pages: &mut [Vec<ManNode>]) {
+    let mut entries = Vec::new();
+    for nodes in pages.iter() {
+        let Some(range) = section_range(nodes, "GLOSSARY") else {
+            continue;
+        };
+        for node in &nodes[range] {
+            if let ManNode::DefinitionList { children } = node {
+                for item in children {
+                    let text = item.plain_text();
+                    if let Some((term, definition)) = text.split_once('\n') {
+                        entries.push((term.to_string(), definition.to_string()));
+                    }
+                }
+            }
+        }
+    }
+    // Only the lists are replaced, so the pages keep their number of nodes.
+    for nodes in pages.iter_mut() {
+        let Some(range) = section_range(nodes, "GLOSSARY") else {
+            continue;
+        };
+        let list = nodes[range.clone()]
+            .iter()
+            .rposition(|n| matches!(n, ManNode::DefinitionList { .. }));
+        if let Some(offset) = list
+            && let Some(merged) = glossary_section(entries.clone()).pop()
+        {
+            nodes[range.start + offset] = merged;
+        }
+    }
+}
+
+/// The roff in a `<!-- roff: ... -->` comment.
+fn roff_comment(html: &str) -> Option<&str> {
+    directive(html, "roff:")
+}
+
+/// The layout in a `<!-- table: 30 - 20 expand -->` comment: a width in ens
+/// for each column (`-` to leave one alone), and `expand`. Comments with
+/// anything else in them are not table comments.
+fn table_comment(html: &str) -> Option<TableLayout> {
+    let mut layout = TableLayout::default();
+    for word in directive(html, "table:")?.split_whitespace() {
+        match word {
+            "expand" => layout.expand = true,
+            "-" => layout.widths.push(None),
+            width => layout
+                .widths
+                .push(Some(width.parse().ok().filter(|&w| w > 0)?)),
+        }
+    }
+    Some(layout)
+}
+
+fn convert_inlines(children: &[Node], state: &mut ConvertState) -> Vec<ManNode> {
+    let mut inlines = Vec::new();
+    for node in children
+        .iter()
+        .flat_map(|x| convert_markdown_node(x, state))
+    {
+        // A roff comment within a paragraph stays on the line.
+        if let ManNode::RawBlock(roff) = node {
+            inlines.push(ManNode::RawInline(roff));
+            continue;
+        }
+        if let ManNode::Text(text) = &node
+            && let Some(rest) = text.strip_prefix(RAW_ROFF_MARKER)
+            && let Some(ManNode::InlineCode(code)) = inlines.last_mut()
+        {
+            let raw = std::mem::take(code);
+            *inlines.last_mut().unwrap() = ManNode::RawInline(raw);
+            if !rest.is_empty() {
+                inlines.push(ManNode::Text(rest.to_string()));
+            }
+            continue;
+        }
+        // A comment that left nothing behind leaves the spaces around it.
+        if let ManNode::Text(text) = &node
+            && text.starts_with(' ')
+            && let Some(ManNode::Text(before)) = inlines.last()
+            && before.ends_with(' ')
+        {
+            inlines.push(ManNode::Text(text.trim_start().to_string()));
+            continue;
+        }
+        inlines.push(node);
+    }
+    if state.in_link {
+        inlines
+    } else {
+        find_man_refs(inlines)
+    }
+}
+
+/// Turn `name(section)` in text, and bold names followed by `(section)` as
+/// in `**grep**(1)`, into [`ManNode::ManRef`]s.
+fn find_man_refs(inlines: Vec<ManNode>) -> Vec<ManNode> {
+    let mut out: Vec<ManNode> = Vec::with_capacity(inlines.len());
+    for node in inlines {
+        let ManNode::Text(text) = node else {
+            out.push(node);
+            continue;
+        };
+        let mut done = 0;
+        if let Some((section, len)) = section_at(&text)
+            && let Some(ManNode::Bold(children)) = out.last()
+            && let [ManNode::Text(name)] = children.as_slice()
+            && is_page_name(name)
+        {
+            let name = name.clone();
+            out.pop();
+            out.push(ManNode::ManRef { name, section });
+            done = len;
+        }
+        for (open, _) in text.match_indices('(') {
+            if open < done {
+                continue;
+            }
+            let before = &text[done..open];
+            let name = &before[before.trim_end_matches(is_page_name_char).len()..];
+            let name = name.trim_start_matches(|c: char| !c.is_alphanumeric());
+            let Some((section, len)) = section_at(&text[open..]) else {
+                continue;
+            };
+            if !is_page_name(name) {
+                continue;
+            }
+            let start = open - name.len();
+            if start > done {
+                out.push(ManNode::Text(text[done..start].to_string()));
+            }
+            out.push(ManNode::ManRef {
+                name: name.to_string(),
+                section,
+            });
+            done = open + len;
+        }
+        if done < text.len() {
+            out.push(ManNode::Text(text[done..].to_string()));
+        }
+    }
+    out
+}
+
+fn is_page_name_char(c: char) -> bool {
+    c.is_alphanumeric() || "._-+:".contains(c)
+}
+
+fn is_page_name(name: &str) -> bool {
+    name.starts_with(char::is_alphanumeric)
+        && name.chars().all(is_page_name_char)
+        && name.chars().any(char::is_alphabetic)
+}
+
+/// Whether `section` looks like a man section: a digit, maybe followed by a
+/// suffix as in `3p` or `1ssl`.
+pub fn is_man_section(section: &str) -> bool {
+    section.starts_with(|c: char| ('1'..='9').contains(&c))
+        && section.len() <= 8
+        && section.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// The section of a `(section)` at the start of `text`, and the length of it
+/// with the parentheses.
+fn section_at(text: &str) -> Option<(String, usize)> {
+    let (section, _) = text.strip_prefix('(')?.split_once(')')?;
+    is_man_section(section).then(|| (section.to_string(), section.len() + 2))
+}
+
 fn extract_simple_text(node: &Node) -> String {
-    match node {
-        Node::Text(Text { value, .. }) => value.to_string(),
-        // For any inline element that might wrap text, simply extract its text.
-        Node::Emphasis(Emphasis { children, .. }) | Node::Strong(Strong { children, .. }) => {
-            children.iter().map(extract_simple_text).collect()
+    // Headings are not depth-checked, so walk them without recursion.
+    let mut text = String::new();
+    let mut stack = vec![node];
+    while let Some(node) = stack.pop() {
+        match node {
+            Node::Text(Text { value, .. }) | Node::InlineCode(InlineCode { value, .. }) => {
+                text.push_str(value)
+            }
+            // For any inline element that might wrap text, simply extract its text.
+            Node::Emphasis(Emphasis { children, .. })
+            | Node::Strong(Strong { children, .. })
+            | Node::Delete(Delete { children, .. }) => stack.extend(children.iter().rev()),
+            _ => {}
         }
-        Node::InlineCode(InlineCode { value, .. }) => value.to_string(),
-        _ => String::new(),
     }
+    text
 }
 
 #[cfg(test)]
@@ -247,6 +1423,47 @@ mod tests {
         convert_markdown_node(&ast, &mut convert_state)
     }
 
+    #[test]
+    fn test_inline_runs() {
+        let nodes = parse("`word`(1) and `word` (1)\nnext\n");
+        let ManNode::Paragraph { children } = &nodes[0] else {
+            panic!("Expected paragraph");
+        };
+        let spacing = inline_runs(children)
+            .iter()
+            .map(|run| run.spacing)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            spacing,
+            [
+                Spacing::None,
+                Spacing::None,
+                Spacing::Space,
+                Spacing::Space,
+                Spacing::LineBreak,
+                Spacing::LineBreak,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_blockquote_with_code() {
+        let nodes = parse("> note\n>\n> ```\n> key = 1\n> ```\n");
+        let ManNode::Blockquote { children } = &nodes[0] else {
+            panic!("Expected blockquote");
+        };
+        assert!(matches!(&children[0], ManNode::Paragraph { .. }));
+        assert!(matches!(&children[1], ManNode::CodeBlock { code, .. } if code == "key = 1"));
+    }
+
+    #[test]
+    fn test_section_range() {
+        let nodes = parse("# NAME\n\nx\n\n# OPTIONS\n\n## Flags\n\ny\n\n# SEE ALSO\n");
+        assert_eq!(section_range(&nodes, "options"), Some(2..5));
+        assert_eq!(section_range(&nodes, "see also"), Some(5..6));
+        assert_eq!(section_range(&nodes, "EXAMPLES"), None);
+    }
+
     #[test]
     fn test_heading_conversion() {
         let nodes = parse("# Hello\n");
@@ -267,6 +1484,31 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_soft_breaks() {
+        let nodes = parse("one\ntwo\n\n---\n\n- **-h**\n  Print\n  help\n\n---\n");
+        match &nodes[0] {
+            ManNode::Paragraph { children } => assert!(matches!(
+                children.as_slice(),
+                [ManNode::Text(a), ManNode::SoftBreak, ManNode::Text(b)] if a == "one" && b == "two"
+            )),
+            _ => panic!("Expected paragraph"),
+        }
+        match &nodes[1] {
+            ManNode::DefinitionList { children } => assert!(matches!(
+                children[0].children(),
+                [
+                    ManNode::Bold(_),
+                    ManNode::Text(term_break),
+                    ManNode::Text(_),
+                    ManNode::SoftBreak,
+                    ManNode::Text(_)
+                ] if term_break == "\n"
+            )),
+            _ => panic!("Expected definition list"),
+        }
+    }
+
     #[test]
     fn test_bold_text() {
         let nodes = parse("**Bold**");
@@ -274,7 +1516,243 @@ mod tests {
             ManNode::Paragraph { children } => children,
             _ => panic!("Expected paragraph"),
         };
-        assert!(matches!(&paragraph[0], ManNode::Bold(text) if text == "Bold"));
+        assert!(
+            matches!(&paragraph[0], ManNode::Bold(children) if children[0].plain_text() == "Bold")
+        );
+    }
+
+    #[test]
+    fn test_nested_inlines() {
+        let nodes = parse("**bold `code`** *see [docs](url)*");
+        let paragraph = nodes[0].children();
+        assert!(matches!(
+            &paragraph[0],
+            ManNode::Bold(children)
+                if matches!(children.as_slice(), [ManNode::Text(_), ManNode::InlineCode(_)])
+        ));
+        assert!(matches!(
+            &paragraph[2],
+            ManNode::Italic(children)
+                if matches!(children.as_slice(), [ManNode::Text(_), ManNode::Uri { .. }])
+        ));
+    }
+
+    #[test]
+    fn test_strikethrough() {
+        let nodes = parse("~~old~~ new");
+        assert!(matches!(
+            &nodes[0].children()[0],
+            ManNode::Strikethrough(children) if children[0].plain_text() == "old"
+        ));
+    }
+
+    #[test]
+    fn test_check_depth() {
+        let nested = |levels| {
+            (1..levels).fold(ManNode::Text("x".into()), |node, _| {
+                ManNode::Bold(vec![node])
+            })
+        };
+        assert_eq!(nested(MAX_DEPTH).depth(), MAX_DEPTH);
+        assert!(check_depth(&[nested(MAX_DEPTH)]).is_ok());
+        assert!(check_depth(&[nested(MAX_DEPTH + 1)]).is_err());
+    }
+
+    #[test]
+    fn test_heading_levels() {
+        let nodes = parse("# A\n\n## B\n\n### C\n\n###### F\n");
+        assert!(matches!(
+            nodes.as_slice(),
+            [
+                ManNode::SectionHeading { .. },
+                ManNode::SubsectionHeading { .. },
+                ManNode::MinorHeading { level: 3, .. },
+                ManNode::MinorHeading { level: 6, title, .. },
+            ] if title == "F"
+        ));
+    }
+
+    #[test]
+    fn test_hard_breaks() {
+        let nodes = parse("one  \ntwo\\\nthree\nfour");
+        assert!(matches!(
+            nodes[0].children(),
+            [
+                ManNode::Text(_),
+                ManNode::LineBreak,
+                ManNode::Text(_),
+                ManNode::LineBreak,
+                ManNode::Text(_),
+                ManNode::SoftBreak,
+                ManNode::Text(_),
+            ]
+        ));
+    }
+
+    #[test]
+    fn test_footnotes() {
+        let nodes =
+            parse("# NAME\n\nb[^b] a[^a] b[^b]\n\n[^a]: First.\n[^b]: Second.\n[^c]: Unused.\n");
+        let text = nodes[1]
+            .children()
+            .iter()
+            .map(ManNode::plain_text)
+            .collect::<String>();
+        assert_eq!(text, "b[1] a[2] b[1]");
+        assert!(matches!(&nodes[2], ManNode::SectionHeading { title, .. } if title == "NOTES"));
+        let notes = nodes[3].children();
+        assert_eq!(notes.len(), 2);
+        assert_eq!(notes[0].plain_text(), "[1]\nSecond.");
+        assert_eq!(notes[1].plain_text(), "[2]\nFirst.");
+    }
+
+    #[test]
+    fn test_number_headings() {
+        let markdown = "# NAME\n\n# SYNTAX\n\n## Lexer\n\n### Tokens\n\n### Comments\n\n\
+                        ## Parser\n\n#### Deep\n\n# SEE ALSO\n\n## Other\n";
+        let (mut nodes, _) = crate::parse_page(markdown, &None).unwrap();
+        number_headings(&mut nodes);
+        let titles = nodes
+            .iter()
+            .filter_map(|n| match n {
+                ManNode::SectionHeading { title, .. }
+                | ManNode::SubsectionHeading { title, .. }
+                | ManNode::MinorHeading { title, .. } => Some(title.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            titles,
+            [
+                "NAME",
+                "SYNTAX",
+                "2.1 Lexer",
+                "2.1.1 Tokens",
+                "2.1.2 Comments",
+                "2.2 Parser",
+                "2.2.0.1 Deep",
+                "SEE ALSO",
+                "3.1 Other"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_insert_contents() {
+        let markdown = "# NAME\n\ntool\n\n# DESCRIPTION\n\n## Files\n\n### Minor\n\n# SEE ALSO\n";
+        let (mut nodes, _) = crate::parse_page(markdown, &None).unwrap();
+        assert_eq!(insert_contents(&mut nodes), 2..4);
+        assert!(matches!(&nodes[2], ManNode::SectionHeading { title, .. } if title == "CONTENTS"));
+        assert!(
+            matches!(&nodes[3], ManNode::NoFill(text) if text == "NAME\nDESCRIPTION\n    Files\nSEE ALSO")
+        );
+
+        let mut nodes = vec![ManNode::SubsectionHeading {
+            title: "Only".into(),
+            children: vec![],
+        }];
+        assert_eq!(insert_contents(&mut nodes), 0..2);
+    }
+
+    #[test]
+    fn test_apply_url_style() {
+        let markdown = "# DESCRIPTION\n\nSee the [manual](https://a.example), \
+                        <https://b.example> and\n- the [manual](https://a.example) and \
+                        *[FAQ](https://c.example)*\n\n# NOTES\n\nNone.\n";
+        let page = |style| {
+            let (mut nodes, _) = crate::parse_page(markdown, &None).unwrap();
+            let inserted = apply_url_style(&mut nodes, style);
+            (nodes, inserted)
+        };
+        let text = |nodes: &[ManNode]| {
+            let text = nodes.iter().map(|n| match n {
+                ManNode::SectionHeading { title, .. } => title.clone(),
+                ManNode::DefinitionList { children } => {
+                    let items = children.iter().map(ManNode::plain_text);
+                    items.collect::<Vec<_>>().join(" / ").replace('\n', " ")
+                }
+                n => n.plain_text(),
+            });
+            text.collect::<Vec<_>>().join(" | ")
+        };
+
+        let (nodes, inserted) = page(UrlStyle::Footnotes);
+        assert_eq!(inserted, 5..7);
+        assert_eq!(
+            text(&nodes),
+            "DESCRIPTION | See the manual[1], https://b.example and | the manual[1] and FAQ[2] \
+             | NOTES | None. | REFERENCES | [1] manual https://a.example / [2] FAQ https://c.example"
+        );
+        let mut links = 0;
+        nodes.iter().for_each(|n| {
+            n.walk(&mut |n| links += matches!(n, ManNode::Uri { .. }) as usize);
+        });
+        assert_eq!(links, 1);
+
+        let (mut nodes, _) = crate::parse_page(
+            "# NAME\n\nSee [site](https://a.example) and a note[^n].\n\n[^n]: Note.\n",
+            &None,
+        )
+        .unwrap();
+        assert_eq!(apply_url_style(&mut nodes, UrlStyle::Footnotes), 4..6);
+        assert_eq!(
+            text(&nodes),
+            "NAME | See site[2] and a note[1]. | NOTES | [1] Note. | REFERENCES | [2] site https://a.example"
+        );
+
+        let (nodes, inserted) = page(UrlStyle::Omit);
+        assert_eq!(inserted, 5..5);
+        assert_eq!(
+            text(&nodes),
+            "DESCRIPTION | See the manual, https://b.example and | the manual and FAQ | NOTES | None."
+        );
+    }
+
+    #[test]
+    fn test_insert_subcommand_table() {
+        let markdown = "# NAME\n\ntool\n\n# DESCRIPTION\n\n## SUBCOMMANDS\n\n\
+                        ### build\n\nBuild the\nproject. Slowly.\n\n#### Flags\n\nx\n\n\
+                        ### run\n\n- list\n\n# SEE ALSO\n\n## other\n\ny\n";
+        let (mut nodes, _) = crate::parse_page(markdown, &None).unwrap();
+        assert_eq!(insert_subcommand_table(&mut nodes), Some(4));
+        let ManNode::Table { children, .. } = &nodes[4] else {
+            panic!("no table: {:?}", nodes[4]);
+        };
+        let rows = children
+            .iter()
+            .map(|row| {
+                let cells = row.children().iter().map(ManNode::plain_text);
+                cells.collect::<Vec<_>>().join(" | ")
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            [
+                "Command | Description",
+                "build | Build the project",
+                "run | "
+            ]
+        );
+        // A second run finds the table and leaves the page as it is.
+        assert_eq!(insert_subcommand_table(&mut nodes), None);
+
+        let (mut nodes, _) = crate::parse_page("# SUBCOMMANDS\n\nNone yet.\n", &None).unwrap();
+        assert_eq!(insert_subcommand_table(&mut nodes), None);
+    }
+
+    #[test]
+    fn test_structural_definition_lists() {
+        let is_definitions =
+            |markdown| matches!(parse(markdown)[0], ManNode::DefinitionList { .. });
+        assert!(is_definitions(
+            "- **-o**, **--output** *FILE*\n  Write to FILE.\n- `--color`[=*WHEN*]\n\n  Colorize.\n"
+        ));
+        // Anything else on the first line, a missing description or a
+        // single plain item keeps the list a bullet list.
+        assert!(!is_definitions("- **Note** that\n  this is prose.\n"));
+        assert!(!is_definitions("- **-v**\n- **-q**\n"));
+        assert!(!is_definitions("- **-v**\n  Verbose.\n- plain\n  item\n"));
+        assert!(!is_definitions("- [ ] **-v**\n  Verbose.\n"));
     }
 
     #[test]
@@ -284,6 +1762,168 @@ mod tests {
         assert!(matches!(nodes[0], ManNode::BulletList { .. }));
     }
 
+    #[test]
+    fn test_dropped() {
+        let markdown = "# NAME\n\n![logo](logo.png) tool <b>x</b>\n\n<!-- comment -->\n\n\
+                        [tool][site]\n\n[site]: https://example.com\n";
+        let (nodes, _, dropped) = crate::parse_page_with_dropped(markdown, &None).unwrap();
+        assert_eq!(nodes.len(), 3);
+        let dropped = dropped
+            .iter()
+            .map(|d| (d.construct, d.line.unwrap(), d.column.unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            dropped,
+            [
+                ("image", 3, 1),
+                ("HTML", 3, 24),
+                ("HTML", 3, 28),
+                ("link reference", 7, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_index() {
+        let markdown = "# FORMAT\n\nA <!-- index: record --> record.\n\n## Fields\n\n\
+                        <!-- index: field -->\n\nA field <!-- index: record --> of one.\n\n\
+                        <!-- index: Alpha -->\n\n## More\n\nA <!-- index: record -->x.\n";
+        let (nodes, _) = crate::parse_page(markdown, &None).unwrap();
+        assert_eq!(nodes[1].plain_text(), "A record.");
+        assert!(matches!(&nodes[6], ManNode::SectionHeading { title, .. } if title == "INDEX"));
+        let entries = nodes[7]
+            .children()
+            .iter()
+            .map(ManNode::plain_text)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            [
+                "Alpha\nFields",
+                "field\nFields",
+                "record\nFORMAT, Fields, More"
+            ]
+        );
+        assert_eq!(nodes.len(), 8);
+    }
+
+    #[test]
+    fn test_glossary() {
+        let markdown = "# FORMAT\n\n<!-- mdman:gloss record: One line of the file. -->\n\n\
+                        Text <!-- mdman:gloss Field: A part: of a record. --> here.\n\n\
+                        <!-- mdman:gloss record: Redefined. -->\n<!-- mdman:gloss nothing -->\n";
+        let glossary = |markdown: &str| {
+            let (nodes, _) = crate::parse_page(markdown, &None).unwrap();
+            let range = section_range(&nodes, "GLOSSARY").unwrap();
+            assert_eq!(range.end, nodes.len());
+            nodes
+        };
+        let nodes = glossary(markdown);
+        assert_eq!(nodes[1].plain_text(), "Text here.");
+        let entries = |nodes: &[ManNode]| {
+            nodes
+                .last()
+                .unwrap()
+                .children()
+                .iter()
+                .map(ManNode::plain_text)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            entries(&nodes),
+            [
+                "Field\nA part: of a record.",
+                "record\nOne line of the file."
+            ]
+        );
+
+        let other = glossary("# NAME\n\n<!-- mdman:gloss alias: Another name. -->\n");
+        let without = crate::parse_page("# NAME\n\ntext\n", &None).unwrap().0;
+        let mut pages = vec![nodes, other, without];
+        merge_glossaries(&mut pages);
+        assert_eq!(entries(&pages[0]), entries(&pages[1]));
+        assert_eq!(
+            entries(&pages[1]),
+            [
+                "alias\nAnother name.",
+                "Field\nA part: of a record.",
+                "record\nOne line of the file."
+            ]
+        );
+        assert_eq!(pages[2].len(), 2);
+    }
+
+    #[test]
+    fn test_man_refs() {
+        let markdown = "**ls**(1), grep(1p) and [see git(1)](url), not f(x), `ls(1)` or 2(3).\n";
+        let (nodes, _) = crate::parse_page(markdown, &None).unwrap();
+        let children = nodes[0].children();
+        let man_ref = |node: &ManNode| match node {
+            ManNode::ManRef { name, section } => Some(format!("{}({})", name, section)),
+            _ => None,
+        };
+        let refs = children.iter().filter_map(man_ref).collect::<Vec<_>>();
+        assert_eq!(refs, ["ls(1)", "grep(1p)"]);
+        assert!(matches!(&children[1], ManNode::Text(text) if text == ", "));
+        assert!(
+            matches!(&children[4], ManNode::Uri { children, .. } if children.iter().all(|c| man_ref(c).is_none()))
+        );
+    }
+
+    #[test]
+    fn test_table_comment() {
+        assert_eq!(
+            table_comment("<!-- table: 30 - expand -->"),
+            Some(TableLayout {
+                widths: vec![Some(30), None],
+                expand: true,
+            })
+        );
+        assert_eq!(table_comment("<!-- table: wide -->"), None);
+        assert_eq!(table_comment("<!-- table: 0 -->"), None);
+        assert_eq!(table_comment("<!-- roff: .sp -->"), None);
+    }
+
+    #[test]
+    fn test_raw_roff_blocks() {
+        let nodes = parse(
+            "```roff\n.SY tool\n.YS\n```\n\n<!-- roff:\n.sp 2\n-->\n\n<!-- note -->\n\n\
+             a <!-- roff: \\c --> b\n",
+        );
+        assert!(matches!(&nodes[0], ManNode::RawBlock(roff) if roff == ".SY tool\n.YS"));
+        assert!(matches!(&nodes[1], ManNode::RawBlock(roff) if roff == ".sp 2"));
+        assert!(matches!(
+            nodes[2].children(),
+            [ManNode::Text(_), ManNode::RawInline(roff), ManNode::Text(_)] if roff == "\\c"
+        ));
+        assert_eq!(nodes.len(), 3);
+    }
+
+    #[test]
+    fn test_raw_inline_roff() {
+        let nodes = parse("say `\\*(lq`{=roff}hi, `code`{x}");
+        let para = match &nodes[0] {
+            ManNode::Paragraph { children } => children,
+            _ => panic!("Expected paragraph"),
+        };
+        assert!(matches!(&para[1], ManNode::RawInline(raw) if raw == "\\*(lq"));
+        assert!(matches!(&para[2], ManNode::Text(text) if text == "hi, "));
+        assert!(matches!(&para[3], ManNode::InlineCode(code) if code == "code"));
+    }
+
+    #[test]
+    fn test_literal_block() {
+        let nodes = parse("```literal\n+--+\n|  |\n```\n\n```sh\nls\n```");
+        assert!(matches!(&nodes[0], ManNode::NoFill(text) if text == "+--+\n|  |"));
+        assert!(matches!(&nodes[1], ManNode::CodeBlock { code: text, .. } if text == "ls"));
+    }
+
+    #[test]
+    fn test_pic_block() {
+        let nodes = parse("```pic\nbox \"A\"; arrow; box \"B\"\n```");
+        assert!(matches!(&nodes[0], ManNode::Pic(text) if text == "box \"A\"; arrow; box \"B\""));
+    }
+
     #[test]
     fn test_inline_code() {
         let nodes = parse("`code`");
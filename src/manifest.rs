@@ -0,0 +1,269 @@
+//! The `mdman.toml` project manifest read by `mdman build`: which pages to
+//! convert, what to override in their frontmatter, and where to write them.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Deserializer};
+
+use crate::info::page_stem;
+use mdman::man_node::{ManNode, TitleLine};
+use mdman::render::Format;
+
+pub const MANIFEST_FILE: &str = "mdman.toml";
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Manifest {
+    /// Directory the pages are written to, relative to the manifest.
+    #[serde(default = "default_output_dir")]
+    pub output_dir: PathBuf,
+    /// [`Layout::Flat`] if not given.
+    pub layout: Option<Layout>,
+    /// Formats every page is generated in.
+    #[serde(default = "default_formats")]
+    pub formats: Vec<Format>,
+    /// Installation prefix; man pages belong in `PREFIX/share/man/manN`.
+    #[serde(default = "default_prefix")]
+    pub prefix: PathBuf,
+    #[serde(default, rename = "page")]
+    pub pages: Vec<ManifestPage>,
+}
+
+/// How generated files are arranged in the output directory.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    /// All files side by side.
+    #[default]
+    Flat,
+    /// Man pages in a `manN` directory per section, as below `share/man`.
+    Sections,
+}
+
+impl Layout {
+    /// Where a page rendered with `format` goes below `dir`, e.g.
+    /// `man/man1/tool.1`.
+    pub fn path(self, dir: &Path, stem: &str, extension: &str, format: Format) -> PathBuf {
+        let file = format!("{}.{}", stem, extension);
+        match (self, format) {
+            (Layout::Sections, Format::Man) => dir.join(format!("man{}", extension)).join(file),
+            _ => dir.join(file),
+        }
+    }
+}
+
+/// One `[[page]]` entry: a Markdown source and the frontmatter fields to
+/// override in it.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ManifestPage {
+    /// Markdown file, relative to the manifest.
+    pub source: PathBuf,
+    pub name: Option<String>,
+    pub section: Option<u8>,
+    #[serde(default, deserialize_with = "date")]
+    pub date: Option<String>,
+    pub left_footer: Option<String>,
+    pub center_footer: Option<String>,
+    pub version: Option<String>,
+}
+
+fn default_output_dir() -> PathBuf {
+    PathBuf::from(".")
+}
+
+fn default_formats() -> Vec<Format> {
+    vec![Format::Man]
+}
+
+fn default_prefix() -> PathBuf {
+    PathBuf::from("/usr/local")
+}
+
+/// A date as written in the frontmatter: a TOML date such as `2025-01-01`,
+/// or a string for anything else.
+fn date<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Date {
+        Date(toml::value::Datetime),
+        Text(String),
+    }
+    Ok(Some(match Date::deserialize(deserializer)? {
+        Date::Date(date) => date.to_string(),
+        Date::Text(text) => text,
+    }))
+}
+
+#[derive(Debug)]
+pub enum ManifestError {
+    ReadError(String, io::Error),
+    /// Not TOML, or not a manifest: the line of the problem if known, and
+    /// the problem.
+    ParseError(String, Option<usize>, String),
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ManifestError::ReadError(file, e) => {
+                write!(f, "mdman: Could not read manifest {}. Error: {}", file, e)
+            }
+            ManifestError::ParseError(file, Some(line), e) => {
+                write!(
+                    f,
+                    "mdman: Could not parse manifest {} at line {}. Error: {}",
+                    file, line, e
+                )
+            }
+            ManifestError::ParseError(file, None, e) => {
+                write!(f, "mdman: Could not parse manifest {}. Error: {}", file, e)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ManifestError {}
+
+/// Read a manifest. Paths in it are made relative to the directory it is in.
+pub fn load_manifest(path: &Path) -> Result<Manifest, ManifestError> {
+    let file = path.to_string_lossy().to_string();
+    let content =
+        fs::read_to_string(path).map_err(|e| ManifestError::ReadError(file.clone(), e))?;
+    let mut manifest = parse_manifest(&content, &file)?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    manifest.output_dir = dir.join(&manifest.output_dir);
+    for page in &mut manifest.pages {
+        page.source = dir.join(&page.source);
+    }
+    Ok(manifest)
+}
+
+/// Parse the content of a manifest; `file` is only used in errors.
+pub fn parse_manifest(content: &str, file: &str) -> Result<Manifest, ManifestError> {
+    toml::from_str(content).map_err(|e| {
+        let line = e
+            .span()
+            .map(|span| content[..span.start].matches('\n').count() + 1);
+        ManifestError::ParseError(file.to_string(), line, e.message().trim_end().to_string())
+    })
+}
+
+impl Manifest {
+    /// Where a page rendered with `format` goes, e.g. `man/man1/tool.1`.
+    pub fn output_path(&self, stem: &str, extension: &str, format: Format) -> PathBuf {
+        let layout = self.layout.unwrap_or_default();
+        layout.path(&self.output_dir, stem, extension, format)
+    }
+}
+
+impl ManifestPage {
+    /// The name of the generated files: `name` if given, the source file's
+    /// otherwise.
+    pub fn stem(&self) -> String {
+        self.name.clone().unwrap_or_else(|| page_stem(&self.source))
+    }
+
+    /// Replace the frontmatter fields set in the entry. Pages without
+    /// frontmatter are left alone.
+    pub fn apply_overrides(&self, nodes: &mut [ManNode]) {
+        let Some(ManNode::TitleLine(title)) = nodes
+            .iter_mut()
+            .find(|node| matches!(node, ManNode::TitleLine(_)))
+        else {
+            return;
+        };
+        let TitleLine {
+            name,
+            section,
+            date,
+            left_footer,
+            center_footer,
+            version,
+            ..
+        } = title;
+        if let Some(value) = &self.name {
+            *name = value.clone();
+        }
+        if let Some(value) = self.section {
+            *section = value;
+        }
+        for (field, value) in [
+            (date, &self.date),
+            (left_footer, &self.left_footer),
+            (center_footer, &self.center_footer),
+            (version, &self.version),
+        ] {
+            if value.is_some() {
+                *field = value.clone();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mdman::parse_page;
+
+    #[test]
+    fn test_parse_manifest() {
+        let manifest = parse_manifest(
+            "# Pages of the tool\noutput-dir = \"build/man\"\nlayout = \"sections\"\n\
+             formats = [\n  \"man\",\n  \"texinfo\", # for makeinfo\n]\n\n\
+             [[page]]\nsource = 'doc/tool.md'\ndate = 2025-01-01\n\n\
+             [[page]]\nsource = \"doc/tool.conf.md\"\nsection = 5\ncenter-footer = \"Tool \\u00e9\"\n",
+            MANIFEST_FILE,
+        )
+        .unwrap();
+        assert_eq!(manifest.output_dir, Path::new("build/man"));
+        assert_eq!(manifest.layout, Some(Layout::Sections));
+        assert_eq!(manifest.formats, [Format::Man, Format::Texinfo]);
+        assert_eq!(manifest.prefix, Path::new("/usr/local"));
+        assert_eq!(manifest.pages.len(), 2);
+        assert_eq!(manifest.pages[0].date.as_deref(), Some("2025-01-01"));
+        assert_eq!(manifest.pages[1].section, Some(5));
+        assert_eq!(manifest.pages[1].center_footer.as_deref(), Some("Tool é"));
+        assert_eq!(
+            manifest.output_path("tool", "1", Format::Man),
+            Path::new("build/man/man1/tool.1")
+        );
+        assert_eq!(
+            manifest.output_path("tool", "texi", Format::Texinfo),
+            Path::new("build/man/tool.texi")
+        );
+
+        let error = |toml: &str| parse_manifest(toml, MANIFEST_FILE).unwrap_err().to_string();
+        assert!(
+            error("formats = [\"man\"]\n\n[[page]]\nsource = \"a.md\" x\n")
+                .starts_with("mdman: Could not parse manifest mdman.toml at line 4. Error: ")
+        );
+        assert!(error("formats = []\n[formats.x]\n").contains("at line 2"));
+        assert!(error("[[page]]\nsorce = \"a.md\"\n").contains("unknown field `sorce`"));
+        assert!(error("layout = \"tree\"\n").contains("unknown variant `tree`"));
+    }
+
+    #[test]
+    fn test_apply_overrides() {
+        let markdown = "---\nname: tool\nsection: 1\ndate: 2024-01-01\n---\n\n# NAME\n\ntool\n";
+        let (mut nodes, _) = parse_page(markdown, &None).unwrap();
+        let page = parse_manifest(
+            "[[page]]\nsource = \"tool.md\"\nsection = 8\nversion = \"2.0\"\n",
+            MANIFEST_FILE,
+        )
+        .unwrap()
+        .pages
+        .remove(0);
+        page.apply_overrides(&mut nodes);
+        let ManNode::TitleLine(title) = &nodes[0] else {
+            panic!("no title line");
+        };
+        assert_eq!(title.section, 8);
+        assert_eq!(title.date.as_deref(), Some("2024-01-01"));
+        assert_eq!(title.version.as_deref(), Some("2.0"));
+    }
+}
@@ -0,0 +1,234 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+
+use serde::{Deserialize, Deserializer};
+
+use crate::man_node::{ManNode, find_section};
+
+/// A single command-line flag, as described in an external options file.
+#[derive(Debug, Deserialize)]
+pub struct OptionSpec {
+    pub name: String,
+    pub short: Option<String>,
+    pub arg: Option<String>,
+    #[serde(default, deserialize_with = "scalar_to_string")]
+    pub default: Option<String>,
+    #[serde(default)]
+    pub description: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OptionsFile {
+    List(Vec<OptionSpec>),
+    Table { options: Vec<OptionSpec> },
+}
+
+/// Accept numbers and booleans for `default`, not only strings.
+fn scalar_to_string<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Option::<serde_yaml::Value>::deserialize(deserializer)?;
+    Ok(match value {
+        Some(serde_yaml::Value::String(s)) => Some(s),
+        Some(serde_yaml::Value::Number(n)) => Some(n.to_string()),
+        Some(serde_yaml::Value::Bool(b)) => Some(b.to_string()),
+        _ => None,
+    })
+}
+
+impl OptionSpec {
+    /// The long flag, e.g. `--output` for a `name` of `output` or `--output`.
+    pub fn long_flag(&self) -> String {
+        with_dashes(&self.name, "--")
+    }
+
+    /// The short flag, e.g. `-o` for a `short` of `o` or `-o`.
+    pub fn short_flag(&self) -> Option<String> {
+        self.short.as_deref().map(|s| with_dashes(s, "-"))
+    }
+
+    fn to_list_item(&self) -> ManNode {
+        let mut children = Vec::new();
+        if let Some(short) = self.short_flag() {
+            children.push(ManNode::bold(short));
+            children.push(ManNode::Text(", ".into()));
+        }
+        children.push(ManNode::bold(self.long_flag()));
+        if let Some(arg) = &self.arg {
+            children.push(ManNode::Text(" ".into()));
+            children.push(ManNode::italic(arg.clone()));
+        }
+        children.push(ManNode::Text(format!("\n{}", self.description.trim())));
+        if let Some(default) = &self.default {
+            children.push(ManNode::Text(" (default: ".into()));
+            children.push(ManNode::italic(default.clone()));
+            children.push(ManNode::Text(")".into()));
+        }
+        ManNode::list_item(children)
+    }
+}
+
+fn with_dashes(name: &str, dashes: &str) -> String {
+    if name.starts_with('-') {
+        name.to_string()
+    } else {
+        format!("{}{}", dashes, name)
+    }
+}
+
+#[derive(Debug)]
+pub enum OptionsFileError {
+    ReadError(String, io::Error),
+    ParseError(String, serde_yaml::Error),
+}
+
+impl fmt::Display for OptionsFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OptionsFileError::ReadError(file, e) => {
+                write!(
+                    f,
+                    "mdman: Could not read options file {}. Error: {}",
+                    file, e
+                )
+            }
+            OptionsFileError::ParseError(file, e) => {
+                write!(
+                    f,
+                    "mdman: Could not parse options file {}. Error: {}",
+                    file, e
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for OptionsFileError {}
+
+/// Parse an options file. JSON is accepted as well, since it is valid YAML.
+pub fn parse_options(content: &str) -> Result<Vec<OptionSpec>, serde_yaml::Error> {
+    match serde_yaml::from_str::<OptionsFile>(content)? {
+        OptionsFile::List(options) | OptionsFile::Table { options } => Ok(options),
+    }
+}
+
+pub fn load_options_file(path: &Path) -> Result<Vec<OptionSpec>, OptionsFileError> {
+    let file = path.to_string_lossy().to_string();
+    let content =
+        fs::read_to_string(path).map_err(|e| OptionsFileError::ReadError(file.clone(), e))?;
+    parse_options(&content).map_err(|e| OptionsFileError::ParseError(file, e))
+}
+
+pub fn options_definition_list(specs: &[OptionSpec]) -> ManNode {
+    ManNode::DefinitionList {
+        children: specs.iter().map(OptionSpec::to_list_item).collect(),
+    }
+}
+
+/// Insert the rendered options at the start of the OPTIONS section. If the page
+/// has no OPTIONS section, one is created after DESCRIPTION (or at the end).
+/// Returns the positions of the inserted nodes.
+pub fn insert_options_section(nodes: &mut Vec<ManNode>, specs: &[OptionSpec]) -> Range<usize> {
+    let list = options_definition_list(specs);
+
+    if let Some(idx) = find_section(nodes, "OPTIONS") {
+        nodes.insert(idx + 1, list);
+        return idx + 1..idx + 2;
+    }
+
+    let idx = find_section(nodes, "DESCRIPTION")
+        .and_then(|desc| {
+            nodes[desc + 1..]
+                .iter()
+                .position(|n| matches!(n, ManNode::SectionHeading { .. }))
+                .map(|offset| desc + 1 + offset)
+        })
+        .unwrap_or(nodes.len());
+    let heading = ManNode::SectionHeading {
+        title: "OPTIONS".into(),
+        children: vec![],
+    };
+    nodes.splice(idx..idx, [heading, list]);
+    idx..idx + 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roff::ToRoff;
+
+    fn heading(title: &str) -> ManNode {
+        ManNode::SectionHeading {
+            title: title.into(),
+            children: vec![],
+        }
+    }
+
+    fn titles(nodes: &[ManNode]) -> Vec<String> {
+        nodes
+            .iter()
+            .map(|n| match n {
+                ManNode::SectionHeading { title, .. } => title.clone(),
+                ManNode::DefinitionList { .. } => "<options>".into(),
+                _ => "<other>".into(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_yaml_list() {
+        let specs = parse_options(
+            "- name: output\n  short: o\n  arg: FILE\n  description: Write to FILE.\n- name: --jobs\n  default: 4\n",
+        )
+        .unwrap();
+        assert_eq!(specs.len(), 2);
+        assert_eq!(specs[0].short_flag().as_deref(), Some("-o"));
+        assert_eq!(specs[0].long_flag(), "--output");
+        assert_eq!(specs[1].long_flag(), "--jobs");
+        assert_eq!(specs[1].default.as_deref(), Some("4"));
+    }
+
+    #[test]
+    fn test_parse_json_table() {
+        let specs =
+            parse_options(r#"{"options": [{"name": "verbose", "description": "Be loud."}]}"#)
+                .unwrap();
+        assert_eq!(specs[0].long_flag(), "--verbose");
+        assert_eq!(specs[0].description, "Be loud.");
+    }
+
+    #[test]
+    fn test_option_roff() {
+        let specs = parse_options(
+            "- name: output\n  short: o\n  arg: FILE\n  default: out.1\n  description: Write to FILE.\n",
+        )
+        .unwrap();
+        let roff = options_definition_list(&specs).to_roff();
+        assert_eq!(
+            roff,
+            ".TP\n\\fB\\-o\\fP, \\fB\\-\\-output\\fP \\fIFILE\\fP\nWrite to FILE\\&. (default: \\fIout\\&.1\\fP)\n\n"
+        );
+    }
+
+    #[test]
+    fn test_insert_into_existing_section() {
+        let mut nodes = vec![heading("NAME"), heading("OPTIONS"), heading("SEE ALSO")];
+        assert_eq!(insert_options_section(&mut nodes, &[]), 2..3);
+        assert_eq!(titles(&nodes), ["NAME", "OPTIONS", "<options>", "SEE ALSO"]);
+    }
+
+    #[test]
+    fn test_insert_after_description() {
+        let mut nodes = vec![heading("NAME"), heading("DESCRIPTION"), heading("SEE ALSO")];
+        insert_options_section(&mut nodes, &[]);
+        assert_eq!(
+            titles(&nodes),
+            ["NAME", "DESCRIPTION", "OPTIONS", "<options>", "SEE ALSO"]
+        );
+    }
+}
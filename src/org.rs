@@ -0,0 +1,278 @@
+use crate::man_node::{ManNode, title_line};
+use crate::render::Renderer;
+use crate::unicode;
+
+/// Render a page as an Org-mode document. The frontmatter becomes a file
+/// property drawer plus `#+TITLE` and friends, sections become headlines.
+pub fn org_page(nodes: &[ManNode]) -> String {
+    let mut out = String::new();
+    if let Some(title) = title_line(nodes) {
+        out.push_str(":PROPERTIES:\n");
+        out.push_str(&format!(":MAN_NAME: {}\n", title.name));
+        out.push_str(&format!(":MAN_SECTION: {}\n", title.section));
+        if let Some(source) = &title.left_footer {
+            out.push_str(&format!(":MAN_SOURCE: {}\n", source));
+        }
+        if let Some(manual) = &title.center_footer {
+            out.push_str(&format!(":MAN_MANUAL: {}\n", manual));
+        }
+        out.push_str(":END:\n");
+        out.push_str(&format!("#+TITLE: {}({})\n", title.name, title.section));
+        if let Some(date) = &title.date {
+            out.push_str(&format!("#+DATE: {}\n", date));
+        }
+        if let Some(lang) = &title.lang {
+            out.push_str(&format!("#+LANGUAGE: {}\n", lang));
+        }
+    }
+
+    for node in nodes {
+        match node {
+            ManNode::TitleLine(_) => {}
+            ManNode::SectionHeading { title, .. } => {
+                separate(&mut out);
+                out.push_str(&format!("* {}\n", title));
+            }
+            ManNode::SubsectionHeading { title, .. } => {
+                separate(&mut out);
+                out.push_str(&format!("** {}\n", title));
+            }
+            ManNode::MinorHeading { level, title, .. } => {
+                separate(&mut out);
+                out.push_str(&format!("{} {}\n", "*".repeat(usize::from(*level)), title));
+            }
+            block => {
+                separate(&mut out);
+                write_block(&mut out, block);
+            }
+        }
+    }
+    out
+}
+
+fn separate(out: &mut String) {
+    if !out.is_empty() {
+        out.push('\n');
+    }
+}
+
+fn write_block(out: &mut String, node: &ManNode) {
+    match node {
+        ManNode::Paragraph { children } => {
+            write_inlines(out, children);
+            end_line(out);
+        }
+        ManNode::CodeBlock { code, .. } | ManNode::Equation(code) => block(out, "EXAMPLE", code),
+        ManNode::NoFill(text) => block(out, "VERSE", text),
+        ManNode::Pic(_) => {
+            // Org has no pic; leave a trace for whoever reads the source.
+            out.push_str("# pic diagram omitted\n");
+        }
+        ManNode::RawBlock(_) => out.push_str("# raw roff omitted\n"),
+        ManNode::BulletList { children } => {
+            for item in children {
+                let marker = match item.checked() {
+                    Some(true) => "- [X] ",
+                    Some(false) => "- [ ] ",
+                    None => "- ",
+                };
+                list_item(out, marker, item);
+            }
+        }
+        ManNode::NumberedList { children } => {
+            for (i, item) in children.iter().enumerate() {
+                list_item(out, &format!("{}. ", i + 1), item);
+            }
+        }
+        ManNode::DefinitionList { children } => {
+            for item in children {
+                let mut text = String::new();
+                write_inlines(&mut text, item.children());
+                let (term, description) = text.split_once('\n').unwrap_or((&text, ""));
+                let description = description.trim().replace('\n', "\n  ");
+                out.push_str(&format!("- {} :: {}", term.trim(), description));
+                end_line(out);
+            }
+        }
+        ManNode::Table { children, .. } => {
+            let rows = children
+                .iter()
+                .map(|row| {
+                    row.children()
+                        .iter()
+                        .map(|cell| {
+                            let mut text = String::new();
+                            write_inlines(&mut text, cell.children());
+                            text.replace('|', "\\vert{}")
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            // Org lines the columns up as the terminal shows them, so wide
+            // characters count twice and combining marks not at all.
+            let mut widths = Vec::new();
+            for row in &rows {
+                widths.resize(widths.len().max(row.len()), 0);
+                for (width, cell) in widths.iter_mut().zip(row) {
+                    *width = (*width).max(unicode::display_width(cell));
+                }
+            }
+            for (i, row) in rows.iter().enumerate() {
+                let cells = widths
+                    .iter()
+                    .enumerate()
+                    .map(|(column, width)| {
+                        let cell = row.get(column).map_or("", String::as_str);
+                        let padding = width - unicode::display_width(cell);
+                        format!("{}{}", cell, " ".repeat(padding))
+                    })
+                    .collect::<Vec<_>>();
+                out.push_str(&format!("| {} |\n", cells.join(" | ")));
+                if i == 0 {
+                    let rule = widths
+                        .iter()
+                        .map(|width| "-".repeat(width + 2))
+                        .collect::<Vec<_>>();
+                    out.push_str(&format!("|{}|\n", rule.join("+")));
+                }
+            }
+        }
+        ManNode::Blockquote { children } => {
+            out.push_str("#+BEGIN_QUOTE\n");
+            for (i, child) in children.iter().enumerate() {
+                if i > 0 {
+                    out.push('\n');
+                }
+                write_block(out, child);
+            }
+            out.push_str("#+END_QUOTE\n");
+        }
+        inline => {
+            write_inlines(out, std::slice::from_ref(inline));
+            end_line(out);
+        }
+    }
+}
+
+/// A `#+BEGIN_...` block. Lines that Org would read as headlines or keywords
+/// are protected with a comma, which Org strips again.
+fn block(out: &mut String, kind: &str, content: &str) {
+    out.push_str(&format!("#+BEGIN_{}\n", kind));
+    for line in content.lines() {
+        if line.starts_with('*') || line.starts_with("#+") {
+            out.push(',');
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.push_str(&format!("#+END_{}\n", kind));
+}
+
+/// Blocks in a list item are indented to the item's text to stay part of it.
+fn list_item(out: &mut String, marker: &str, item: &ManNode) {
+    let mut content = String::new();
+    write_inlines(&mut content, item.children());
+    end_line(&mut content);
+    let indent = " ".repeat(marker.len());
+    for (i, line) in content.lines().enumerate() {
+        if i == 0 {
+            out.push_str(marker);
+        } else if !line.is_empty() {
+            out.push_str(&indent);
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+}
+
+fn write_inlines(out: &mut String, children: &[ManNode]) {
+    for child in children {
+        match child {
+            ManNode::Text(text) => out.push_str(text),
+            ManNode::SoftBreak => out.push('\n'),
+            ManNode::LineBreak => out.push_str("\\\\\n"),
+            ManNode::Bold(children) => enclose(out, '*', children),
+            ManNode::Italic(children) => enclose(out, '/', children),
+            ManNode::Strikethrough(children) => enclose(out, '+', children),
+            ManNode::InlineCode(text) => out.push_str(&format!("~{}~", text)),
+            // Roff means nothing to Org.
+            ManNode::RawInline(_) => {}
+            ManNode::ManRef { name, section } => out.push_str(&format!("*{}*({})", name, section)),
+            ManNode::Uri { url, children, .. } => {
+                let mut text = String::new();
+                write_inlines(&mut text, children);
+                if text == *url {
+                    out.push_str(&format!("[[{}]]", url));
+                } else {
+                    out.push_str(&format!("[[{}][{}]]", url, text));
+                }
+            }
+            ManNode::ListItem { children, .. } => write_inlines(out, children),
+            block => {
+                end_line(out);
+                write_block(out, block);
+            }
+        }
+    }
+}
+
+fn enclose(out: &mut String, mark: char, children: &[ManNode]) {
+    out.push(mark);
+    write_inlines(out, children);
+    out.push(mark);
+}
+
+fn end_line(out: &mut String) {
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+/// [`org_page`] as a [`Renderer`].
+pub struct OrgRenderer;
+
+impl Renderer for OrgRenderer {
+    fn render(&self, nodes: &[ManNode]) -> String {
+        org_page(nodes)
+    }
+
+    fn extension(&self, _nodes: &[ManNode]) -> String {
+        "org".into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_page;
+
+    #[test]
+    fn test_org_page() {
+        let markdown = "---\nname: tool\nsection: 1\ndate: 2025-01-01\n---\n\n# NAME\n\n\
+                        **tool** - do _things_, see [docs](https://example.com)\n\n\
+                        # OPTIONS\n\n---\n\n- **-v**\n  Be verbose.\n\n---\n\n\
+                        # EXAMPLES\n\n- Run `tool`:\n\n  ```\n  * tool -v\n  ```\n\n| a | b |\n|---|---|\n| 1 | 2 |\n";
+        let (nodes, _) = parse_page(markdown, &None).unwrap();
+        assert_eq!(
+            org_page(&nodes),
+            ":PROPERTIES:\n:MAN_NAME: tool\n:MAN_SECTION: 1\n:END:\n#+TITLE: tool(1)\n#+DATE: 2025-01-01\n\n\
+             * NAME\n\n*tool* - do /things/, see [[https://example.com][docs]]\n\n\
+             * OPTIONS\n\n- *-v* :: Be verbose.\n\n\
+             * EXAMPLES\n\n- Run ~tool~:\n  #+BEGIN_EXAMPLE\n  ,* tool -v\n  #+END_EXAMPLE\n\n\
+             | a | b |\n|---+---|\n| 1 | 2 |\n"
+        );
+    }
+
+    #[test]
+    fn test_table_columns_line_up() {
+        let markdown = "| Name | Größe |\n|---|---|\n| 日本 | 1 |\n| x | q\u{301}x |\n";
+        let (nodes, _) = parse_page(markdown, &None).unwrap();
+        assert_eq!(
+            org_page(&nodes),
+            "| Name | Größe |\n\
+             |------+-------|\n\
+             | 日本 | 1     |\n\
+             | x    | q\u{301}x    |\n"
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use std::collections::BTreeSet;
+
+use mdman::man_node::ManNode;
+use mdman::roff::{self, RoffOptions, TargetFormatter};
+
+/// A construct that renders differently (or not at all) on some formatters.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Finding {
+    /// Markdown line of the block the construct is in.
+    pub line: Option<usize>,
+    pub construct: String,
+    pub note: &'static str,
+}
+
+/// List the constructs of a page that mandoc, groff and plain nroff disagree
+/// on, given the options the page is rendered with. `lines` are the Markdown
+/// lines of the top-level nodes, as returned by [`mdman::parse_page`].
+pub fn report(nodes: &[ManNode], lines: &[Option<usize>], options: &RoffOptions) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut seen_chars = BTreeSet::new();
+    for (node, line) in nodes.iter().zip(lines) {
+        let mut add = |construct: String, note| {
+            let duplicate = findings
+                .iter()
+                .any(|f: &Finding| f.line == *line && f.construct == construct);
+            if !duplicate {
+                findings.push(Finding {
+                    line: *line,
+                    construct,
+                    note,
+                });
+            }
+        };
+        node.walk(&mut |n| {
+            if let Some((construct, note)) = construct_note(n, options) {
+                add(construct.to_string(), note);
+            }
+            for c in text_of(n).chars().filter(|c| !c.is_ascii()) {
+                if seen_chars.insert(c) {
+                    add(
+                        format!("character U+{:04X} ({})", c as u32, c),
+                        "needs a UTF-8 capable formatter (mandoc, groff with preconv); \
+                         plain nroff prints garbage",
+                    );
+                }
+            }
+        });
+    }
+    findings
+}
+
+fn construct_note(node: &ManNode, options: &RoffOptions) -> Option<(&'static str, &'static str)> {
+    let portable = options.target == Some(TargetFormatter::Portable);
+    match node {
+        ManNode::TitleLine(title)
+            if title
+                .lang
+                .as_deref()
+                .and_then(roff::groff_language)
+                .is_some()
+                && options.groff_requests() =>
+        {
+            Some((
+                ".hla hyphenation language",
+                "groff only; other formatters hyphenate with English patterns",
+            ))
+        }
+        ManNode::SectionHeading { .. }
+        | ManNode::SubsectionHeading { .. }
+        | ManNode::MinorHeading { .. }
+            if options.pdf_bookmarks && options.groff_requests() =>
+        {
+            Some((
+                ".pdfhref bookmark",
+                "groff with pdfmark only; ignored elsewhere",
+            ))
+        }
+        ManNode::Table { .. } => Some((
+            "table",
+            "needs the tbl preprocessor; mandoc renders simple tables only, \
+             nroff without tbl shows the raw .TS block",
+        )),
+        ManNode::Pic(_) => Some((
+            "pic diagram",
+            "needs the pic preprocessor (groff); mandoc drops it",
+        )),
+        ManNode::Equation(_) => Some((
+            "eqn equation",
+            "needs the eqn preprocessor (groff, mandoc); nroff without eqn shows the raw .EQ block",
+        )),
+        ManNode::Uri { url, .. } if !portable && url.starts_with("mailto:") => Some((
+            ".MT/.ME email link",
+            "groff and mandoc only; old nroff ignores the macros and loses the address",
+        )),
+        ManNode::Uri { .. } if !portable => Some((
+            ".UR/.UE link",
+            "groff and mandoc only; old nroff ignores the macros and loses the URL",
+        )),
+        ManNode::CodeBlock { .. } if !portable => Some((
+            ".EX/.EE example",
+            "groff and mandoc only; old nroff fills the block as prose",
+        )),
+        ManNode::InlineCode(_) => match options.target {
+            None => Some((
+                "\\fC font",
+                "not a standard font name; nroff and some groff devices warn \
+                 and fall back to roman",
+            )),
+            Some(TargetFormatter::Groff) => Some((
+                "\\f(CR font",
+                "groff only; mandoc before 1.14.5 and nroff fall back to roman",
+            )),
+            Some(TargetFormatter::Mandoc) => Some((
+                "\\f(CW font",
+                "not available in every nroff; falls back to roman",
+            )),
+            Some(TargetFormatter::Portable) => None,
+        },
+        ManNode::RawInline(_) | ManNode::RawBlock(_) => Some((
+            "raw roff",
+            "passed through unchanged; portability depends on its content",
+        )),
+        _ => None,
+    }
+}
+
+/// Text that ends up in the output as characters.
+fn text_of(node: &ManNode) -> &str {
+    match node {
+        ManNode::SectionHeading { title, .. }
+        | ManNode::SubsectionHeading { title, .. }
+        | ManNode::MinorHeading { title, .. } => title,
+        ManNode::Text(text)
+        | ManNode::InlineCode(text)
+        | ManNode::CodeBlock { code: text, .. }
+        | ManNode::NoFill(text) => text,
+        ManNode::Uri { url, .. } => url,
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn constructs(findings: &[Finding]) -> Vec<(Option<usize>, &str)> {
+        findings
+            .iter()
+            .map(|f| (f.line, f.construct.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_report() {
+        let nodes = [
+            ManNode::Paragraph {
+                children: vec![
+                    ManNode::Text("caf\u{e9} ".into()),
+                    ManNode::InlineCode("a".into()),
+                    ManNode::InlineCode("b".into()),
+                ],
+            },
+            ManNode::Paragraph {
+                children: vec![ManNode::Text("\u{e9}".into())],
+            },
+            ManNode::code_block("ls"),
+        ];
+        let lines = [Some(3), Some(5), Some(7)];
+
+        let findings = report(&nodes, &lines, &RoffOptions::default());
+        assert_eq!(
+            constructs(&findings),
+            [
+                (Some(3), "character U+00E9 (\u{e9})"),
+                (Some(3), "\\fC font"),
+                (Some(7), ".EX/.EE example"),
+            ]
+        );
+
+        let portable = RoffOptions {
+            target: Some(TargetFormatter::Portable),
+            ..RoffOptions::default()
+        };
+        let findings = report(&nodes, &lines, &portable);
+        assert_eq!(
+            constructs(&findings),
+            [(Some(3), "character U+00E9 (\u{e9})")]
+        );
+    }
+}
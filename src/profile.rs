@@ -0,0 +1,72 @@
+//! Presets for the conventions of the systems pages get packaged for, so one
+//! `--profile` gives output their packaging policies expect.
+
+use clap::ValueEnum;
+
+use crate::manifest::Layout;
+use mdman::roff::{EscapeProfile, TargetFormatter};
+
+/// A system the pages are packaged for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Profile {
+    /// Debian and derivatives: man-db with groff.
+    Debian,
+    /// Fedora and other RPM-based distributions: man-db with groff.
+    Fedora,
+    /// Homebrew on macOS, where `man` is mandoc, and old groff on older releases.
+    Homebrew,
+    /// FreeBSD, OpenBSD and NetBSD: mandoc.
+    Bsd,
+}
+
+/// What a profile sets. Flags given on the command line win.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProfileSettings {
+    pub target: TargetFormatter,
+    pub escape_profile: EscapeProfile,
+    /// Ship fallbacks for `.UR` and `.EX`, which older man macro sets lack.
+    pub compat_macros: bool,
+    /// Section headings in capitals, as packaging checks expect.
+    pub uppercase_sections: bool,
+    /// How `mdman build` arranges the pages if the manifest does not say.
+    pub layout: Layout,
+    /// Stay clear of what lintian warns about (see `--lintian`).
+    pub lintian: bool,
+    /// Compress the man pages that are written to files, as the packages
+    /// ship them.
+    pub gzip: bool,
+}
+
+impl Profile {
+    pub fn settings(self) -> ProfileSettings {
+        match self {
+            Profile::Debian | Profile::Fedora => ProfileSettings {
+                target: TargetFormatter::Groff,
+                escape_profile: EscapeProfile::Groff,
+                compat_macros: false,
+                uppercase_sections: true,
+                layout: Layout::Sections,
+                lintian: self == Profile::Debian,
+                gzip: true,
+            },
+            Profile::Homebrew => ProfileSettings {
+                target: TargetFormatter::Mandoc,
+                escape_profile: EscapeProfile::Mandoc,
+                compat_macros: true,
+                uppercase_sections: true,
+                layout: Layout::Sections,
+                lintian: false,
+                gzip: false,
+            },
+            Profile::Bsd => ProfileSettings {
+                target: TargetFormatter::Mandoc,
+                escape_profile: EscapeProfile::Mandoc,
+                compat_macros: false,
+                uppercase_sections: true,
+                layout: Layout::Sections,
+                lintian: false,
+                gzip: false,
+            },
+        }
+    }
+}
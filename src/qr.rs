@@ -0,0 +1,438 @@
+//! QR codes for `--pdf-qr`, so that readers of a printed manual can reach its
+//! links with a phone. Only what URLs need is supported: byte mode, error
+//! correction level M (15% of the code may be damaged) and versions 1 to 10,
+//! which hold up to 213 bytes.
+
+/// Error correction codewords per block, for versions 1 to 10 at level M.
+const EC_PER_BLOCK: [usize; 10] = [10, 16, 26, 18, 24, 16, 18, 22, 22, 26];
+/// Blocks the codewords are split into, for versions 1 to 10 at level M.
+const BLOCKS: [usize; 10] = [1, 1, 1, 2, 2, 4, 4, 4, 5, 5];
+const MAX_VERSION: usize = 10;
+
+/// A QR code: `size` × `size` modules, `true` for dark ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QrCode {
+    pub size: usize,
+    modules: Vec<bool>,
+}
+
+impl QrCode {
+    /// The smallest code for `data`, or `None` if it does not fit.
+    pub fn encode(data: &[u8]) -> Option<QrCode> {
+        let version = (1..=MAX_VERSION).find(|&v| data.len() <= capacity(v))?;
+        let codewords = add_error_correction(&data_codewords(data, version), version);
+        let mut best: Option<(usize, QrCode)> = None;
+        for mask in 0..8 {
+            let code = QrCode::build(version, &codewords, mask);
+            let penalty = code.penalty();
+            if best.as_ref().is_none_or(|(p, _)| penalty < *p) {
+                best = Some((penalty, code));
+            }
+        }
+        best.map(|(_, code)| code)
+    }
+
+    /// Whether the module in column `x` of row `y` is dark.
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.size + x]
+    }
+
+    fn build(version: usize, codewords: &[u8], mask: u8) -> QrCode {
+        let size = version * 4 + 17;
+        let mut grid = Grid {
+            size,
+            modules: vec![false; size * size],
+            function: vec![false; size * size],
+        };
+        grid.draw_function_patterns(version);
+        grid.draw_format(mask);
+        grid.draw_codewords(codewords);
+        grid.apply_mask(mask);
+        QrCode {
+            size,
+            modules: grid.modules,
+        }
+    }
+
+    /// How hard the code is to scan, as the standard scores it, to choose
+    /// the mask: runs of one colour, 2×2 blocks, patterns that look like the
+    /// finders, and an imbalance of dark and light.
+    fn penalty(&self) -> usize {
+        let size = self.size;
+        let mut penalty = 0;
+        let lines = (0..size).flat_map(|i| {
+            [
+                (0..size).map(|j| self.is_dark(j, i)).collect::<Vec<_>>(),
+                (0..size).map(|j| self.is_dark(i, j)).collect::<Vec<_>>(),
+            ]
+        });
+        for line in lines {
+            let mut run = 1;
+            for j in 1..=size {
+                if j < size && line[j] == line[j - 1] {
+                    run += 1;
+                    continue;
+                }
+                if run >= 5 {
+                    penalty += run - 2;
+                }
+                run = 1;
+            }
+            // Dark-light-dark-dark-dark-light-dark with four light modules
+            // on one side; outside the code is light.
+            let at = |j: isize| j >= 0 && (j as usize) < size && line[j as usize];
+            for j in -4..size as isize {
+                let finder = [true, false, true, true, true, false, true]
+                    .iter()
+                    .enumerate()
+                    .all(|(k, &dark)| at(j + k as isize) == dark);
+                let light = |from: isize| (from..from + 4).all(|k| !at(k));
+                if finder && (light(j - 4) || light(j + 7)) {
+                    penalty += 40;
+                }
+            }
+        }
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let dark = self.is_dark(x, y);
+                if [(1, 0), (0, 1), (1, 1)]
+                    .iter()
+                    .all(|&(dx, dy)| self.is_dark(x + dx, y + dy) == dark)
+                {
+                    penalty += 3;
+                }
+            }
+        }
+        let dark = self.modules.iter().filter(|&&m| m).count();
+        let percent = dark * 100 / self.modules.len();
+        penalty + percent.abs_diff(50) / 5 * 10
+    }
+}
+
+/// Modules while the code is drawn, with those of the fixed patterns marked.
+struct Grid {
+    size: usize,
+    modules: Vec<bool>,
+    function: Vec<bool>,
+}
+
+impl Grid {
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y * self.size + x] = dark;
+        self.function[y * self.size + x] = true;
+    }
+
+    fn draw_function_patterns(&mut self, version: usize) {
+        let size = self.size;
+        for i in 0..size {
+            self.set_function(6, i, i % 2 == 0);
+            self.set_function(i, 6, i % 2 == 0);
+        }
+        // Finders with their light separators.
+        for (cx, cy) in [(3, 3), (size - 4, 3), (3, size - 4)] {
+            for dy in -4isize..=4 {
+                for dx in -4isize..=4 {
+                    let (x, y) = (cx as isize + dx, cy as isize + dy);
+                    if (0..size as isize).contains(&x) && (0..size as isize).contains(&y) {
+                        let distance = dx.abs().max(dy.abs());
+                        self.set_function(x as usize, y as usize, distance != 2 && distance != 4);
+                    }
+                }
+            }
+        }
+        let positions = alignment_positions(version);
+        for &cx in &positions {
+            for &cy in &positions {
+                let corner = |a: usize, b: usize| a == 6 && b == 6;
+                if corner(cx, cy) || corner(cx, size - 1 - cy) || corner(size - 1 - cx, cy) {
+                    continue;
+                }
+                for dy in -2isize..=2 {
+                    for dx in -2isize..=2 {
+                        let (x, y) = ((cx as isize + dx) as usize, (cy as isize + dy) as usize);
+                        self.set_function(x, y, dx.abs().max(dy.abs()) != 1);
+                    }
+                }
+            }
+        }
+        if version >= 7 {
+            let bits = version_bits(version);
+            for i in 0..18 {
+                let dark = bits >> i & 1 == 1;
+                let (a, b) = (size - 11 + i % 3, i / 3);
+                self.set_function(a, b, dark);
+                self.set_function(b, a, dark);
+            }
+        }
+    }
+
+    /// The error correction level and mask, twice, and the dark module.
+    fn draw_format(&mut self, mask: u8) {
+        let size = self.size;
+        let bits = format_bits(mask);
+        let bit = |i: usize| bits >> i & 1 == 1;
+        for i in 0..6 {
+            self.set_function(8, i, bit(i));
+        }
+        self.set_function(8, 7, bit(6));
+        self.set_function(8, 8, bit(7));
+        self.set_function(7, 8, bit(8));
+        for i in 9..15 {
+            self.set_function(14 - i, 8, bit(i));
+        }
+        for i in 0..8 {
+            self.set_function(size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set_function(8, size - 15 + i, bit(i));
+        }
+        self.set_function(8, size - 8, true);
+    }
+
+    /// Fill the other modules in the zigzag order of the standard: two
+    /// columns at a time from the right, up and down in turn.
+    fn draw_codewords(&mut self, codewords: &[u8]) {
+        let size = self.size;
+        let mut i = 0;
+        let mut right = size - 1;
+        loop {
+            if right == 6 {
+                right = 5;
+            }
+            let upward = (right + 1) & 2 == 0;
+            for vertical in 0..size {
+                let y = if upward {
+                    size - 1 - vertical
+                } else {
+                    vertical
+                };
+                for x in [right, right - 1] {
+                    if !self.function[y * size + x] && i < codewords.len() * 8 {
+                        self.modules[y * size + x] = codewords[i / 8] >> (7 - i % 8) & 1 == 1;
+                        i += 1;
+                    }
+                }
+            }
+            if right < 2 {
+                break;
+            }
+            right -= 2;
+        }
+    }
+
+    fn apply_mask(&mut self, mask: u8) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let invert = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                let i = y * self.size + x;
+                if invert && !self.function[i] {
+                    self.modules[i] = !self.modules[i];
+                }
+            }
+        }
+    }
+}
+
+/// The 15 format bits for level M and `mask`, with their BCH code.
+fn format_bits(mask: u8) -> u32 {
+    // Level M is 00.
+    let data = mask as u32;
+    let mut remainder = data;
+    for _ in 0..10 {
+        remainder = (remainder << 1) ^ ((remainder >> 9) * 0x537);
+    }
+    (data << 10 | remainder) ^ 0x5412
+}
+
+/// The 18 version bits of versions 7 and up, with their BCH code.
+fn version_bits(version: usize) -> u32 {
+    let mut remainder = version as u32;
+    for _ in 0..12 {
+        remainder = (remainder << 1) ^ ((remainder >> 11) * 0x1f25);
+    }
+    (version as u32) << 12 | remainder
+}
+
+/// Centres of the alignment patterns along each axis; none for version 1.
+fn alignment_positions(version: usize) -> Vec<usize> {
+    if version == 1 {
+        return vec![];
+    }
+    let count = version / 7 + 2;
+    let step = (version * 4 + count * 2 + 1) / (count * 2 - 2) * 2;
+    let last = version * 4 + 10;
+    let mut positions = vec![6];
+    positions.extend((0..count - 1).rev().map(|i| last - i * step));
+    positions
+}
+
+/// Modules left for data and error correction once the patterns are drawn.
+fn raw_data_modules(version: usize) -> usize {
+    let mut modules = (16 * version + 128) * version + 64;
+    if version >= 2 {
+        let count = version / 7 + 2;
+        modules -= (25 * count - 10) * count - 55;
+        if version >= 7 {
+            modules -= 36;
+        }
+    }
+    modules
+}
+
+fn data_codeword_count(version: usize) -> usize {
+    raw_data_modules(version) / 8 - EC_PER_BLOCK[version - 1] * BLOCKS[version - 1]
+}
+
+/// How many bytes a code of `version` holds: after the mode and the
+/// character count.
+fn capacity(version: usize) -> usize {
+    let count_bits = if version < 10 { 8 } else { 16 };
+    (data_codeword_count(version) * 8 - 4 - count_bits) / 8
+}
+
+/// `data` in byte mode, padded to the data capacity of `version`.
+fn data_codewords(data: &[u8], version: usize) -> Vec<u8> {
+    let mut bits = Vec::new();
+    let mut push = |value: u32, count: usize| {
+        bits.extend((0..count).rev().map(|i| value >> i & 1 == 1));
+    };
+    push(0b0100, 4);
+    push(data.len() as u32, if version < 10 { 8 } else { 16 });
+    for &byte in data {
+        push(byte as u32, 8);
+    }
+    let capacity = data_codeword_count(version) * 8;
+    let terminator = (capacity - bits.len()).min(4);
+    bits.extend(std::iter::repeat_n(false, terminator));
+    bits.resize(bits.len().div_ceil(8) * 8, false);
+
+    let mut codewords = bits
+        .chunks(8)
+        .map(|byte| byte.iter().fold(0, |acc, &bit| acc << 1 | bit as u8))
+        .collect::<Vec<u8>>();
+    for pad in [0xec, 0x11].into_iter().cycle() {
+        if codewords.len() == capacity / 8 {
+            break;
+        }
+        codewords.push(pad);
+    }
+    codewords
+}
+
+/// Split the data into blocks, append each block's Reed-Solomon codewords,
+/// and interleave them as the standard says: the data of all blocks column
+/// by column, then their error correction the same way.
+fn add_error_correction(data: &[u8], version: usize) -> Vec<u8> {
+    let blocks = BLOCKS[version - 1];
+    let ec_len = EC_PER_BLOCK[version - 1];
+    let total = raw_data_modules(version) / 8;
+    let long_blocks = total % blocks;
+    let short_len = total / blocks - ec_len;
+    let divisor = rs_divisor(ec_len);
+
+    let mut split = Vec::new();
+    let mut start = 0;
+    for i in 0..blocks {
+        let len = short_len + usize::from(i >= blocks - long_blocks);
+        let block = &data[start..start + len];
+        split.push((block, rs_remainder(block, &divisor)));
+        start += len;
+    }
+    let mut out = Vec::with_capacity(total);
+    for i in 0..=short_len {
+        out.extend(split.iter().filter_map(|(block, _)| block.get(i)));
+    }
+    for i in 0..ec_len {
+        out.extend(split.iter().map(|(_, ec)| ec[i]));
+    }
+    out
+}
+
+/// Multiplication in GF(2^8) modulo x^8 + x^4 + x^3 + x^2 + 1.
+fn gf_multiply(x: u8, y: u8) -> u8 {
+    let mut z: u32 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x11d);
+        z ^= (y as u32 >> i & 1) * x as u32;
+    }
+    z as u8
+}
+
+/// The generator polynomial of `degree`, without its leading 1.
+fn rs_divisor(degree: usize) -> Vec<u8> {
+    let mut result = vec![0; degree];
+    result[degree - 1] = 1;
+    let mut root = 1;
+    for _ in 0..degree {
+        for j in 0..degree {
+            result[j] = gf_multiply(result[j], root);
+            if j + 1 < degree {
+                result[j] ^= result[j + 1];
+            }
+        }
+        root = gf_multiply(root, 2);
+    }
+    result
+}
+
+fn rs_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut result = vec![0; divisor.len()];
+    for &byte in data {
+        let factor = byte ^ result.remove(0);
+        result.push(0);
+        for (r, &d) in result.iter_mut().zip(divisor) {
+            *r ^= gf_multiply(d, factor);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tables() {
+        let capacities = (1..=MAX_VERSION).map(capacity).collect::<Vec<_>>();
+        assert_eq!(capacities, [14, 26, 42, 62, 84, 106, 122, 152, 180, 213]);
+        assert_eq!(alignment_positions(7), [6, 22, 38]);
+        assert_eq!(alignment_positions(10), [6, 28, 50]);
+        assert_eq!(format_bits(0), 0b101010000010010);
+        assert_eq!(format_bits(7), 0b100101010100000);
+        assert_eq!(version_bits(7), 0x07c94);
+    }
+
+    #[test]
+    fn test_error_correction() {
+        // "HELLO WORLD" as 1-M, from the usual worked example.
+        let data = [
+            32, 91, 11, 120, 209, 114, 220, 77, 67, 64, 236, 17, 236, 17, 236, 17,
+        ];
+        assert_eq!(
+            rs_remainder(&data, &rs_divisor(10)),
+            [196, 35, 39, 119, 235, 215, 231, 226, 93, 23]
+        );
+    }
+
+    #[test]
+    fn test_encode() {
+        let code = QrCode::encode(b"https://example.com").unwrap();
+        assert_eq!(code.size, 25);
+        // The finder in the top left corner, its separator and the dark
+        // module next to the bottom left one.
+        let row = (0..8).map(|x| code.is_dark(x, 0)).collect::<Vec<_>>();
+        assert_eq!(row, [true, true, true, true, true, true, true, false]);
+        assert!(code.is_dark(8, 25 - 8));
+        assert_eq!(QrCode::encode(&[b'a'; 213]).unwrap().size, 57);
+        assert!(QrCode::encode(&[b'a'; 214]).is_none());
+    }
+}
@@ -0,0 +1,124 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use crate::ansi::AnsiRenderer;
+use crate::asciidoc::AsciidocRenderer;
+use crate::html::{HtmlOptions, HtmlRenderer};
+use crate::man_node::ManNode;
+use crate::org::OrgRenderer;
+use crate::roff::{ManRenderer, RoffOptions};
+use crate::texinfo::TexinfoRenderer;
+use crate::tldr::TldrRenderer;
+
+/// An output format: turns a whole page into a document.
+///
+/// Renderers walk the tree recursively. Pages from [`crate::parse_page`] are
+/// never nested deeper than [`MAX_DEPTH`](crate::man_node::MAX_DEPTH); check
+/// trees assembled by hand with [`check_depth`](crate::man_node::check_depth).
+pub trait Renderer: Send + Sync {
+    fn render(&self, nodes: &[ManNode]) -> String;
+
+    /// Extension of the file the output is written to, e.g. `1` or `texi`.
+    fn extension(&self, nodes: &[ManNode]) -> String;
+
+    /// Whether the output is meant to be looked at rather than saved, so it
+    /// goes to stdout unless a file is asked for.
+    fn for_terminal(&self) -> bool {
+        false
+    }
+}
+
+/// Output formats mdman can produce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// A roff man page.
+    Man,
+    /// A condensed page in the tldr-pages Markdown convention.
+    Tldr,
+    /// A Texinfo manual, for building info pages with makeinfo.
+    Texinfo,
+    /// AsciiDoc for asciidoctor's manpage doctype.
+    Asciidoc,
+    /// The formatted page with ANSI styles, as `man` would show it.
+    Ansi,
+    /// An Org-mode document.
+    Org,
+    /// A standalone HTML page with landmarks for screen readers.
+    Html,
+}
+
+impl Format {
+    /// The renderer for this format. `options` only matter for man pages,
+    /// `html` for HTML.
+    pub fn renderer<'a>(
+        self,
+        options: &'a RoffOptions,
+        html: &'a HtmlOptions,
+    ) -> Box<dyn Renderer + 'a> {
+        match self {
+            Format::Man => Box::new(ManRenderer { options }),
+            Format::Tldr => Box::new(TldrRenderer),
+            Format::Texinfo => Box::new(TexinfoRenderer),
+            Format::Asciidoc => Box::new(AsciidocRenderer),
+            Format::Ansi => Box::new(AnsiRenderer::for_terminal()),
+            Format::Org => Box::new(OrgRenderer),
+            Format::Html => Box::new(HtmlRenderer { options: html }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html::HtmlOptions;
+    use crate::man_node::check_depth;
+    use crate::parse_page;
+
+    #[test]
+    fn test_every_format_renders() {
+        let markdown = "---\nname: tool\nsection: 8\n---\n\n# NAME\n\n**tool** - do things\n";
+        let (nodes, _) = parse_page(markdown, &None).unwrap();
+        let options = RoffOptions::default();
+        let html = HtmlOptions::default();
+        for format in Format::value_variants() {
+            let renderer = format.renderer(&options, &html);
+            assert!(renderer.render(&nodes).contains("tool"), "{:?}", format);
+        }
+        let extensions = [Format::Man, Format::Texinfo, Format::Html]
+            .map(|format| format.renderer(&options, &html).extension(&nodes));
+        assert_eq!(extensions, ["8", "texi", "8.html"]);
+    }
+
+    /// The renderers recurse, so whatever [`parse_page`] accepts has to fit
+    /// on a test thread's stack in every format.
+    #[test]
+    fn test_deepest_pages_render() {
+        let options = RoffOptions::default();
+        let pages = [
+            |n: usize| format!("{}x\n", "> ".repeat(n)),
+            |n: usize| format!("{}x{}\n", "*".repeat(n), "*".repeat(n)),
+            |n: usize| {
+                (0..n)
+                    .map(|i| format!("{}- item\n", "  ".repeat(i)))
+                    .collect()
+            },
+            |n: usize| format!("{}x\n", "> - ".repeat(n)),
+        ];
+        for page in pages {
+            let deepest = (1..)
+                .map(|n| parse_page(&page(n), &None))
+                .take_while(Result::is_ok)
+                .last()
+                .unwrap()
+                .unwrap()
+                .0;
+            assert!(check_depth(&deepest).is_ok());
+            for format in Format::value_variants() {
+                format
+                    .renderer(&options, &HtmlOptions::default())
+                    .render(&deepest);
+            }
+        }
+    }
+}
@@ -1,15 +1,193 @@
 use std::fmt::Write;
 
+use clap::ValueEnum;
 use jiff::Zoned;
 
-use crate::man_node::{ManNode, TableAlign, TitleLine};
+use crate::man_node::{
+    InlineRun, ManNode, Spacing, TableAlign, TableLayout, TableStyle, TitleLine, inline_runs,
+    title_line,
+};
+use crate::qr::QrCode;
+use crate::render::Renderer;
+use crate::unicode;
+
+mod builder;
+
+use builder::{RoffBuilder, combined_font};
+
+/// Which characters the prose escaper replaces.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum EscapeProfile {
+    /// Escape everything that could be special anywhere (`.`, `'`, `"`, `-`, `~`, `|`, `%`).
+    #[default]
+    Aggressive,
+    /// Only backslashes and control characters at the start of a line.
+    Minimal,
+    /// Minimal plus `-`, `~` and `^`, which groff renders as typographic glyphs otherwise.
+    Groff,
+    /// Minimal plus `-`, which mandoc needs to tell minus signs from hyphens.
+    Mandoc,
+}
+
+/// What to do with code and literal blocks wider than the page.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum WideBlocks {
+    /// Leave them alone.
+    #[default]
+    Ignore,
+    /// Report them on stderr.
+    Warn,
+    /// Reduce the point size by one in print output (`.if t .ps -1`).
+    Shrink,
+}
+
+/// How line breaks in the Markdown source end up in the output.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SoftBreaks {
+    /// Keep the author's line breaks in the roff source; the text is still filled.
+    #[default]
+    Keep,
+    /// Join the lines of a paragraph into one.
+    Join,
+    /// Force a line break with `.br`.
+    Br,
+}
+
+/// Which roff implementation the output is tuned for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TargetFormatter {
+    /// GNU roff: `\f(CR` for code, plus groff-only requests like `.hla`.
+    Groff,
+    /// mandoc: `\f(CW` for code, no groff-only requests.
+    Mandoc,
+    /// Only what every man implementation understands: bold code, links as
+    /// text with the URL in angle brackets, `.nf` instead of `.EX`.
+    Portable,
+}
+
+/// Default column budget for code blocks on an 80-column terminal, leaving
+/// room for the body indentation.
+pub const DEFAULT_BLOCK_WIDTH: usize = 72;
+
+#[derive(Debug, Clone)]
+pub struct RoffOptions {
+    pub escape_profile: EscapeProfile,
+    pub soft_breaks: SoftBreaks,
+    pub wide_blocks: WideBlocks,
+    pub block_width: usize,
+    /// Add a PDF outline entry and named destination for every heading
+    /// (`.pdfhref O`, `.pdfhref M`).
+    pub pdf_bookmarks: bool,
+    /// Draw a QR code of every long web link in print output.
+    pub pdf_qr: bool,
+    /// Define fallbacks for the an-ext macros (`.UR`, `.EX`, ...) the page uses.
+    pub compat_macros: bool,
+    /// Formatter to tune the output for; `None` keeps the historical mix.
+    pub target: Option<TargetFormatter>,
+    /// Set block quotes in italics.
+    pub italic_quotes: bool,
+    /// Ask for tbl in the preprocessor line when the page has tables.
+    pub tbl_line: bool,
+    /// Centre of the page footer in print output, instead of the date.
+    pub footer: Option<String>,
+    /// Number the pages of print output in the footer.
+    pub page_numbers: bool,
+    /// Frame of all tables, over the frontmatter's `table-style`.
+    pub table_style: Option<TableStyle>,
+    /// Avoid what Debian's lintian complains about: fill in every `.TH`
+    /// field and always escape `-`.
+    pub lintian: bool,
+    /// Write the output in the [stable style](RoffOptions::stable).
+    pub stable: bool,
+}
+
+/// Version of the style `--stable-output` writes. It only changes when the
+/// style has to, e.g. for a formatter bug, and the release notes say so.
+pub const STABLE_STYLE: u32 = 1;
+
+impl RoffOptions {
+    /// These options with the choices of the stable style pinned: aggressive
+    /// escaping, the author's line breaks, the historical macro mix and a tbl
+    /// preprocessor line for tables. See [`normalize_stable`] for the rest.
+    pub fn stable(self) -> Self {
+        Self {
+            escape_profile: EscapeProfile::Aggressive,
+            soft_breaks: SoftBreaks::Keep,
+            target: None,
+            tbl_line: true,
+            stable: true,
+            ..self
+        }
+    }
+
+    /// Whether groff-only requests (`.hla`, `.pdfhref`) may be emitted.
+    pub fn groff_requests(&self) -> bool {
+        !matches!(
+            self.target,
+            Some(TargetFormatter::Mandoc | TargetFormatter::Portable)
+        )
+    }
+}
+
+impl Default for RoffOptions {
+    fn default() -> Self {
+        Self {
+            escape_profile: EscapeProfile::default(),
+            soft_breaks: SoftBreaks::default(),
+            wide_blocks: WideBlocks::default(),
+            block_width: DEFAULT_BLOCK_WIDTH,
+            pdf_bookmarks: false,
+            pdf_qr: false,
+            compat_macros: false,
+            target: None,
+            italic_quotes: false,
+            tbl_line: true,
+            footer: None,
+            page_numbers: true,
+            table_style: None,
+            lintian: false,
+            stable: false,
+        }
+    }
+}
 
 pub trait ToRoff {
-    fn to_roff(&self) -> String;
+    /// Render with the default options.
+    fn to_roff(&self) -> String {
+        self.to_roff_with(&RoffOptions::default())
+    }
+
+    fn to_roff_with(&self, options: &RoffOptions) -> String;
 }
 
 impl ToRoff for ManNode {
-    fn to_roff(&self) -> String {
+    fn to_roff_with(&self, options: &RoffOptions) -> String {
+        let mut b = RoffBuilder::new(options);
+        self.write_roff(&mut b);
+        b.finish()
+    }
+}
+
+/// Whole man pages, preamble included, as a [`Renderer`].
+pub struct ManRenderer<'a> {
+    pub options: &'a RoffOptions,
+}
+
+impl Renderer for ManRenderer<'_> {
+    fn render(&self, nodes: &[ManNode]) -> String {
+        let (preamble, chunks) = crate::render_page(nodes, self.options);
+        preamble + &chunks.concat()
+    }
+
+    /// The section number from the frontmatter, 1 without one.
+    fn extension(&self, nodes: &[ManNode]) -> String {
+        title_line(nodes).map_or(1, |t| t.section).to_string()
+    }
+}
+
+impl ManNode {
+    fn write_roff(&self, b: &mut RoffBuilder) {
+        let options = b.options();
         match self {
             ManNode::TitleLine(TitleLine {
                 name,
@@ -17,92 +195,272 @@ impl ToRoff for ManNode {
                 date,
                 left_footer,
                 center_footer,
+                lang,
+                version,
+                ..
             }) => {
-                let mut th = format!(".TH \"{}\" \"{}\"", name.to_uppercase(), section);
-                th.push_str(" \"");
-                if let Some(d) = date {
-                    th.push_str(d);
+                let date = date
+                    .clone()
+                    .unwrap_or_else(|| Zoned::now().strftime("%Y-%m-%d").to_string());
+                let mut args = vec![
+                    RoffBuilder::quote(&name.to_uppercase()),
+                    RoffBuilder::quote(&section.to_string()),
+                    RoffBuilder::quote(&date),
+                ];
+                let (source, manual) = if options.lintian {
+                    let source = left_footer.clone().unwrap_or_else(|| match version {
+                        Some(version) => format!("{} {}", name, version),
+                        None => name.clone(),
+                    });
+                    let manual = center_footer
+                        .clone()
+                        .unwrap_or_else(|| section_manual(*section).to_string());
+                    (Some(source), Some(manual))
                 } else {
-                    let d = Zoned::now().strftime("%Y-%m-%d").to_string();
-                    th.push_str(&d);
+                    (left_footer.clone(), center_footer.clone())
+                };
+                // The manual is the fifth argument, even without a source.
+                if source.is_some() || manual.is_some() {
+                    args.push(RoffBuilder::quote(source.as_deref().unwrap_or_default()));
                 }
-                th.push('"');
-
-                if let Some(lf) = left_footer {
-                    th.push_str(" \"");
-                    th.push_str(lf);
-                    th.push('"');
-                }
-                if let Some(cf) = center_footer {
-                    th.push_str(" \"");
-                    th.push_str(cf);
-                    th.push('"');
+                args.extend(manual.iter().map(|m| RoffBuilder::quote(m)));
+                b.request("TH", &args.iter().map(String::as_str).collect::<Vec<_>>());
+                if let Some((hla, patterns)) = lang.as_deref().and_then(hyphenation_patterns)
+                    && options.groff_requests()
+                {
+                    // Only groff knows `.hla`; mandoc would warn about it.
+                    b.request("if", &["\\n(.g", ".hla", hla]);
+                    b.request("if", &["\\n(.g", ".hpf", patterns]);
                 }
-                th.push('\n');
-                th
+                print_footer(b, left_footer.as_deref(), &date);
             }
             ManNode::SectionHeading { title, children } => {
-                let body = children.iter().map(|n| n.to_roff()).collect::<String>();
-                format!(".SH {}\n{}", title, body)
+                let escaped = escape(title, options.escape_profile);
+                b.request("SH", &[&escaped]);
+                pdf_bookmark(b, 1, title);
+                for child in children {
+                    child.write_roff(b);
+                }
             }
             ManNode::SubsectionHeading { title, children } => {
-                let body = children.iter().map(|n| n.to_roff()).collect::<String>();
-                format!(".SS {}\n{}", title, body)
+                let escaped = escape(title, options.escape_profile);
+                b.request("SS", &[&escaped]);
+                pdf_bookmark(b, 2, title);
+                for child in children {
+                    child.write_roff(b);
+                }
+            }
+            ManNode::MinorHeading {
+                level,
+                title,
+                children,
+            } => {
+                // Below `.SS` a heading is a paragraph of its own, bold at
+                // level 3 and bold italic deeper down.
+                let font = if *level == 3 { "B" } else { "BI" };
+                b.request("PD", &[]).request("PP", &[]).font(font, |b| {
+                    b.text(title);
+                });
+                b.end_line();
+                pdf_bookmark(b, *level, title);
+                for child in children {
+                    child.write_roff(b);
+                }
+            }
+            ManNode::Paragraph { children } if is_plain(children) => {
+                // Plain prose, the bulk of most pages, has no links or fonts
+                // to join up, so it is written straight out.
+                b.request("PD", &[]).request("PP", &[]);
+                write_plain(children, b);
+                b.end_line();
             }
             ManNode::Paragraph { children } => {
-                let content = children.iter().map(|n| n.to_roff()).collect::<String>();
-                format!(".PD\n.PP\n{}\n", content)
+                let text = render_inlines(children, b);
+                b.request("PD", &[])
+                    .request("PP", &[])
+                    .raw(&text)
+                    .end_line();
+                write_qr_codes(children, b);
+                b.end_line();
+            }
+            ManNode::SoftBreak => {
+                b.raw(match options.soft_breaks {
+                    SoftBreaks::Keep => "\n",
+                    SoftBreaks::Join => " ",
+                    SoftBreaks::Br => "\n.br\n",
+                });
+            }
+            ManNode::LineBreak => {
+                b.newline().request("br", &[]);
+            }
+            ManNode::Bold(children) | ManNode::Italic(children) => {
+                let font = if matches!(self, ManNode::Bold(_)) {
+                    "B"
+                } else {
+                    "I"
+                };
+                match children.as_slice() {
+                    // `**`code`**` needs one font switch, not two.
+                    [ManNode::InlineCode(code)] => {
+                        b.font(&combined_font(font, code_font(options)), |b| {
+                            b.code(code);
+                        });
+                    }
+                    _ => {
+                        b.font(font, |b| {
+                            let text = render_inlines(children, b);
+                            b.raw(&text);
+                        });
+                    }
+                }
+            }
+            ManNode::Strikethrough(children) => {
+                // Roff has no strike-through, so say what the line meant.
+                b.font("I", |b| {
+                    let text = render_inlines(children, b);
+                    b.raw(&text);
+                });
+                b.text(" (deprecated)");
+            }
+            ManNode::InlineCode(text) => {
+                b.font(code_font(options), |b| {
+                    b.code(text);
+                });
+            }
+            ManNode::CodeBlock { code: text, .. } => {
+                let (start, end) = if options.target == Some(TargetFormatter::Portable) {
+                    ("nf", "fi")
+                } else {
+                    ("EX", "EE")
+                };
+                shrink_if_wide(b, text, |b| {
+                    b.request(start, &[])
+                        .code(text)
+                        .end_line()
+                        .request(end, &[]);
+                });
+            }
+            ManNode::NoFill(text) => {
+                shrink_if_wide(b, text, |b| {
+                    b.request("nf", &[])
+                        .text(text)
+                        .end_line()
+                        .request("fi", &[]);
+                });
+            }
+            ManNode::Pic(diagram) => {
+                b.request("PS", &[])
+                    .raw(diagram)
+                    .end_line()
+                    .request("PE", &[]);
+            }
+            ManNode::Equation(equation) => {
+                b.request("EQ", &[])
+                    .raw(equation)
+                    .end_line()
+                    .request("EN", &[]);
+            }
+            ManNode::RawInline(roff) => {
+                b.raw(roff);
+            }
+            ManNode::RawBlock(roff) => {
+                b.end_line().raw(roff).end_line();
             }
-            ManNode::Bold(text) => format!("\\fB{}\\fP", text),
-            ManNode::Italic(text) => format!("\\fI{}\\fP", text),
-            ManNode::InlineCode(text) => format!("\\fC{}\\fP", text),
-            ManNode::CodeBlock(text) => format!(".EX\n{}\n.EE\n", text),
             ManNode::Text(text) => {
-                let text = escape(text);
-                text
-                // if text.starts_with("\n") {
-                //     format!("\n.RS 8{}\n.RE", text)
-                // } else {
-                //     text.to_string()
-                // }
+                b.text(text);
             }
             ManNode::BulletList { children } => {
-                let mut content = String::new();
+                // The leading line break marks the list as block content for
+                // `render_inlines`.
+                b.newline().request("RS", &["2"]).request("PD", &["0"]);
+                // Task boxes in ASCII, which every formatter and font has.
+                let width = if children.iter().any(|c| c.checked().is_some()) {
+                    "4"
+                } else {
+                    "2"
+                };
                 for child in children {
-                    content.push_str(".IP \\(bu 2\n");
-                    content.push_str(&child.to_roff());
-                    content.push('\n')
+                    let marker = match child.checked() {
+                        Some(true) => "[x]",
+                        Some(false) => "[\\ ]",
+                        None => "\\(bu",
+                    };
+                    b.request("IP", &[marker, width]);
+                    child.write_roff(b);
+                    b.newline();
                 }
-                format!("\n.RS 2\n.PD 0\n{}\n.RE\n", content)
+                b.newline().request("RE", &[]);
             }
             ManNode::NumberedList { children } => {
-                let mut content = String::new();
+                b.newline().request("RS", &["2"]).request("PD", &["0"]);
                 for (i, child) in children.iter().enumerate() {
-                    _ = write!(content, ".IP {}. 4\n{}\n", i + 1, child.to_roff());
+                    b.request("IP", &[&format!("{}.", i + 1), "4"]);
+                    child.write_roff(b);
+                    b.newline();
                 }
-                format!("\n.RS 2\n.PD 0\n{}\n.RE\n", content)
+                b.newline().request("RE", &[]);
             }
-            ManNode::ListItem { children } => {
-                children.iter().map(|n| n.to_roff()).collect::<String>()
+            ManNode::ListItem { children, .. } => {
+                b.raw(&render_inlines(children, b));
+                write_qr_codes(children, b);
             }
             ManNode::Uri {
                 url,
                 title: _title,
                 children,
             } => {
-                // dbg!(&url);
-                // dbg!(&_title);
-                // dbg!(&children);
-                let text = children.iter().map(|n| n.to_roff()).collect::<String>();
-                // let url = format!("\\fI{}\\fP", url);
-                format!("\n.UR {}\n{}\n.UE\n", url, text)
-            }
-            ManNode::Table { align, children } => {
-                let mut table = ".TS\n".to_string();
-                table.push_str("allbox;\n");
-                // table.push_str("box;\n");
-                // table.push_str("doublebox;\n");
-                let align_chars = align
+                let text = render_inlines(children, b);
+                let (open, close, target) = match url.strip_prefix("mailto:") {
+                    Some(address) => ("MT", "ME", address),
+                    None => ("UR", "UE", url.as_str()),
+                };
+                let shown = escape(target, options.escape_profile);
+                // Autolinks, `www.` ones included, have their target as the
+                // text, and the macros show the target anyway.
+                let autolink = text == shown
+                    || url
+                        .strip_prefix("http://")
+                        .is_some_and(|rest| escape(rest, options.escape_profile) == text);
+                if options.target == Some(TargetFormatter::Portable) {
+                    if autolink {
+                        b.raw(&format!("<{}>", shown));
+                    } else {
+                        b.raw(&format!("{} <{}>", text, shown));
+                    }
+                    return;
+                }
+                b.newline().request(open, &[target]);
+                if !autolink {
+                    b.raw(&text).newline();
+                }
+                b.request(close, &[]);
+            }
+            ManNode::ManRef { name, section } if b.in_font() => {
+                // A `.BR` line would break out of the bold or italic text
+                // around the reference, so it stays inline.
+                b.font("B", |b| {
+                    b.text(name);
+                });
+                b.text(&format!("({})", section));
+            }
+            ManNode::ManRef { name, section } => {
+                let name = escape(name, options.escape_profile);
+                b.newline()
+                    .request("BR", &[&name, &format!("({})", section)]);
+            }
+            ManNode::Table {
+                align,
+                style,
+                layout,
+                children,
+            } => {
+                // A body row may have more cells than the header, and tbl
+                // refuses rows wider than the format line.
+                let columns = children
+                    .iter()
+                    .map(|row| row.children().len())
+                    .fold(align.len(), usize::max);
+                let columns = align
                     .iter()
                     .map(|a| match a {
                         TableAlign::Left => "l",
@@ -110,48 +468,751 @@ impl ToRoff for ManNode {
                         TableAlign::Center => "c",
                         TableAlign::None => "l",
                     })
-                    .collect::<Vec<_>>()
-                    .join(" ");
-
-                table.push_str(&align_chars);
-                table.push('.');
-                table.push('\n');
-                let text = children.iter().map(|n| n.to_roff()).collect::<String>();
-                table.push_str(&text);
-                table.push_str(".TE");
-                table.push('\n');
-                table
+                    .chain(std::iter::repeat("l"))
+                    .take(columns)
+                    .zip(column_widths(
+                        children,
+                        layout,
+                        columns,
+                        options.block_width,
+                    ))
+                    .collect::<Vec<_>>();
+                // One format line per row up to the last one that differs
+                // from a plain body row; tbl uses the last for all others.
+                let body = format_line(&columns, None, "");
+                let mut format_lines = children
+                    .iter()
+                    .enumerate()
+                    .map(|(i, row)| format_line(&columns, Some(row), if i == 0 { "B" } else { "" }))
+                    .collect::<Vec<_>>();
+                let specific = format_lines.iter().rposition(|line| *line != body);
+                format_lines.truncate(specific.map_or(0, |i| i + 1));
+                format_lines.push(body + ".");
+                // Generated reference tables can have thousands of rows, so
+                // grow the output once instead of row by row.
+                b.reserve(table_size(children)).request("TS", &[]);
+                let mut table_options = match options.table_style.or(*style).unwrap_or_default() {
+                    TableStyle::Allbox => vec!["allbox"],
+                    TableStyle::Box => vec!["box"],
+                    TableStyle::Doublebox => vec!["doublebox"],
+                    TableStyle::None => vec![],
+                };
+                if layout.expand {
+                    table_options.push("expand");
+                }
+                if !table_options.is_empty() {
+                    b.line(&format!("{};", table_options.join(" ")));
+                }
+                for line in &format_lines {
+                    b.line(line);
+                }
+                for row in children {
+                    // A spanned cell has no entry of its own.
+                    for (i, cell) in row.children().iter().enumerate() {
+                        if !is_span(i, cell) {
+                            cell.write_roff(b);
+                        }
+                    }
+                    b.newline();
+                }
+                b.request("TE", &[]);
             }
             ManNode::TableRow(children) => {
-                let text = children.iter().map(|n| n.to_roff()).collect::<String>();
-                format!("{}\n", text)
+                for child in children {
+                    child.write_roff(b);
+                }
+                b.newline();
             }
             ManNode::TableCell(children) => {
-                let text = children.iter().map(|n| n.to_roff()).collect::<String>();
-                format! {"T{{\n{}\nT}}\t", text}
+                // Cells follow each other on one line, separated by tabs.
+                b.raw("T{").newline();
+                if is_plain(children) {
+                    write_plain(children, b);
+                } else {
+                    let text = render_inlines(children, b);
+                    b.raw(&text);
+                }
+                b.newline().raw("T}\t");
+            }
+            ManNode::Blockquote { children } => {
+                // Everything inside, code blocks included, keeps the indentation.
+                b.request("RS", &["4"]);
+                let write_children = |b: &mut RoffBuilder| {
+                    for child in children {
+                        child.write_roff(b);
+                    }
+                };
+                if b.options().italic_quotes {
+                    b.block_font("I", write_children);
+                } else {
+                    write_children(b);
+                }
+                b.request("RE", &[]);
             }
             ManNode::DefinitionList { children } => {
-                let mut s = String::new();
-
                 for child in children {
-                    // s.push_str(&format!(".TP\n\\fB{}\\fP\n\n", &child.to_roff()));
-                    s.push_str(&format!(".TP\n{}\n\n", &child.to_roff()));
+                    b.request("TP", &[]);
+                    child.write_roff(b);
+                    b.newline().newline();
                 }
-                s
             }
         }
     }
 }
 
-fn escape(text: &str) -> String {
-    text.replace('\\', "\\\\")
-        .replace('.', "\\&.")
-        .replace('\'', "\\&'")
-        .replace('"', "\\&\"")
-        .replace('-', "\\-")
-        .replace('~', "\\(ti")
-        .replace('|', "\\(ba")
-        .replace('%', "\\%")
+/// Join rendered inline nodes exactly as authored. Some inlines (links) are
+/// macros that need a line of their own: they must not leave blank lines or
+/// trailing blanks behind, text after them must not start with whitespace
+/// (roff breaks the line there), and punctuation directly following a link is
+/// passed to `.UE` so no space is inserted before it. Blocks nested in list
+/// items (tables, code) are treated the same way.
+fn render_inlines(children: &[ManNode], b: &RoffBuilder) -> String {
+    let mut out = String::new();
+    let mut previous: Option<&ManNode> = None;
+    for InlineRun {
+        node: child,
+        spacing,
+    } in inline_runs(children)
+    {
+        // Punctuation right after a link goes to `.UE`, after a page
+        // reference onto its section, as in `.BR grep (1),`.
+        let joiner = match previous {
+            Some(ManNode::Uri { .. }) => Some(" "),
+            Some(ManNode::ManRef { .. }) => Some(""),
+            _ => None,
+        };
+        previous = Some(child);
+        // Text that goes on a line of its own loses the blanks in front, and
+        // text after `.UE` or `.BR` its first word to that line. Both are
+        // split off before escaping, so that the rest is escaped for the
+        // line it starts.
+        if let ManNode::Text(text) = child
+            && out.ends_with('\n')
+        {
+            let mut text = text.trim_start_matches(char::is_whitespace);
+            if let Some(joiner) = joiner
+                && spacing == Spacing::None
+            {
+                let end = text.find(char::is_whitespace).unwrap_or(text.len());
+                let mut word = b.nested_after(joiner);
+                word.text(&text[..end]);
+                out.pop();
+                out.push_str(joiner);
+                out.push_str(&word.finish());
+                out.push('\n');
+                text = text[end..].trim_start_matches(char::is_whitespace);
+            }
+            let mut nested = b.nested_after(&out);
+            nested.text(text);
+            out.push_str(&nested.finish());
+            continue;
+        }
+        let mut nested = b.nested_after(&out);
+        child.write_roff(&mut nested);
+        let mut rendered = nested.finish();
+        // Text right before a page reference, as the `(` in `(grep(1))`,
+        // becomes the first of `.RB`'s alternating arguments.
+        if let ManNode::ManRef { .. } = child
+            && rendered.starts_with("\n.BR ")
+            && spacing == Spacing::None
+            && !out.ends_with(char::is_whitespace)
+        {
+            let word = out.split_off(out.rfind(char::is_whitespace).map_or(0, |i| i + 1));
+            if !word.is_empty() {
+                rendered = rendered.replacen("\n.BR ", &format!("\n.RB {} ", word), 1);
+            }
+        }
+        let macro_lines = match rendered.strip_prefix('\n') {
+            Some(lines) => Some(lines),
+            None => is_block(child).then_some(rendered.as_str()),
+        };
+        if let Some(macro_lines) = macro_lines {
+            out.truncate(out.trim_end_matches([' ', '\t']).len());
+            if !out.is_empty() && !out.ends_with('\n') {
+                out.push('\n');
+            }
+            out.push_str(macro_lines);
+            continue;
+        }
+        match out.ends_with('\n') {
+            true => out.push_str(rendered.trim_start_matches(char::is_whitespace)),
+            false => out.push_str(&rendered),
+        }
+    }
+    out
+}
+
+fn code_font(options: &RoffOptions) -> &'static str {
+    match options.target {
+        None => "C",
+        Some(TargetFormatter::Groff) => "CR",
+        Some(TargetFormatter::Mandoc) => "CW",
+        Some(TargetFormatter::Portable) => "B",
+    }
+}
+
+/// The tbl format of a row: a key letter per column (`s` where an empty cell
+/// continues the one before it), `font` and the column width.
+fn format_line(columns: &[(&str, Option<usize>)], row: Option<&ManNode>, font: &str) -> String {
+    let cells = row.map_or(&[][..], ManNode::children);
+    columns
+        .iter()
+        .enumerate()
+        .map(|(i, (key, width))| match cells.get(i) {
+            Some(cell) if is_span(i, cell) => "s".to_string(),
+            _ => {
+                let width = width.map(|w| format!("w({}n)", w)).unwrap_or_default();
+                format!("{}{}{}", key, font, width)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Whether a cell is empty and so merges into the cell to its left.
+fn is_span(column: usize, cell: &ManNode) -> bool {
+    column > 0 && cell.plain_text().trim().is_empty()
+}
+
+/// Narrowest column [`column_widths`] squeezes a table to, in ens.
+const MIN_COLUMN_WIDTH: usize = 8;
+
+/// Widths in ens for the columns of a table: those of its table comment,
+/// plus, when the cells would not fit into `budget` columns otherwise, equal
+/// shares of the remaining room for the widest columns. tbl wraps the text of
+/// a cell to the width of its column.
+fn column_widths(
+    rows: &[ManNode],
+    layout: &TableLayout,
+    columns: usize,
+    budget: usize,
+) -> Vec<Option<usize>> {
+    let mut natural = vec![0; columns];
+    for row in rows {
+        for (i, cell) in row.children().iter().enumerate() {
+            natural[i] = natural[i].max(widest_line(&cell.plain_text()));
+        }
+    }
+    let mut widths = (0..columns)
+        .map(|i| layout.widths.get(i).copied().flatten())
+        .collect::<Vec<_>>();
+    // tbl leaves three ens between columns.
+    let fixed = widths.iter().flatten().sum::<usize>();
+    let mut room = budget.saturating_sub(3 * columns.saturating_sub(1) + fixed);
+    let mut open = (0..columns)
+        .filter(|&i| widths[i].is_none())
+        .collect::<Vec<_>>();
+    if open.iter().map(|&i| natural[i]).sum::<usize>() <= room {
+        return widths;
+    }
+    // Columns narrower than an equal share keep their width, which leaves
+    // more for the others.
+    while !open.is_empty() {
+        let share = room / open.len();
+        let (narrow, wide): (Vec<usize>, Vec<usize>) =
+            open.iter().partition(|&&i| natural[i] <= share);
+        if narrow.is_empty() {
+            for i in wide {
+                widths[i] = Some(share.max(MIN_COLUMN_WIDTH));
+            }
+            break;
+        }
+        room -= narrow.iter().map(|&i| natural[i]).sum::<usize>();
+        open = wide;
+    }
+    widths
+}
+
+/// About how many bytes of roff the rows of a table take: their text, an
+/// eighth more for escapes, and the `T{`/`T}` around every cell.
+fn table_size(rows: &[ManNode]) -> usize {
+    fn text_len(node: &ManNode) -> usize {
+        match node {
+            ManNode::Text(text) | ManNode::InlineCode(text) => text.len(),
+            other => other.children().iter().map(text_len).sum(),
+        }
+    }
+    rows.iter()
+        .map(|row| {
+            let text = text_len(row);
+            text + text / 8 + row.children().len() * 8 + 1
+        })
+        .sum()
+}
+
+/// Write text and line breaks as [`render_inlines`] would join them, but
+/// straight into `b`.
+fn write_plain(children: &[ManNode], b: &mut RoffBuilder) {
+    for (i, child) in children.iter().enumerate() {
+        match child {
+            ManNode::Text(text) if i > 0 && b.at_line_start() => {
+                b.text(text.trim_start());
+            }
+            ManNode::Text(text) => {
+                b.text(text);
+            }
+            _ => child.write_roff(b),
+        }
+    }
+}
+
+/// Whether inline nodes are nothing but text and line breaks.
+fn is_plain(children: &[ManNode]) -> bool {
+    children
+        .iter()
+        .all(|c| matches!(c, ManNode::Text(_) | ManNode::SoftBreak))
+}
+
+/// Nodes that render to whole lines of requests, even inside a list item.
+fn is_block(node: &ManNode) -> bool {
+    matches!(
+        node,
+        ManNode::Table { .. }
+            | ManNode::CodeBlock { .. }
+            | ManNode::NoFill(_)
+            | ManNode::Pic(_)
+            | ManNode::Equation(_)
+            | ManNode::RawBlock(_)
+            | ManNode::Blockquote { .. }
+    )
+}
+
+/// The `'\" ...` comment on the first line that tells `man` which preprocessors
+/// the page needs, e.g. `'\" p` for pic or `'\" t` for tbl.
+pub fn preprocessor_line(nodes: &[ManNode], options: &RoffOptions) -> Option<String> {
+    let (mut needs_eqn, mut needs_pic, mut needs_tbl) = (false, false, false);
+    for node in nodes {
+        node.walk(&mut |n| {
+            needs_eqn |= matches!(n, ManNode::Equation(_));
+            needs_pic |= matches!(n, ManNode::Pic(_));
+            needs_tbl |= matches!(n, ManNode::Table { .. });
+        });
+    }
+    // In the order man runs them: pic, tbl, then eqn.
+    let mut letters = String::new();
+    if needs_pic {
+        letters.push('p');
+    }
+    if needs_tbl && options.tbl_line {
+        letters.push('t');
+    }
+    if needs_eqn {
+        letters.push('e');
+    }
+    (!letters.is_empty()).then(|| format!("'\\\" {}\n", letters))
+}
+
+/// The comment after the preprocessor line of pages in the stable style.
+pub fn stable_marker() -> String {
+    format!(".\\\" mdman stable output style {}\n", STABLE_STYLE)
+}
+
+/// Bring rendered chunks into the stable style: blank lines outside literal
+/// blocks, tables, equations and pictures are written as `.sp`, no line ends
+/// in blanks, and every chunk ends with a line break. The number of lines
+/// stays the same, so source maps still hold.
+pub fn normalize_stable(chunks: &mut [String]) {
+    const OPEN: [&str; 5] = [".nf", ".EX", ".TS", ".EQ", ".PS"];
+    const CLOSE: [&str; 5] = [".fi", ".EE", ".TE", ".EN", ".PE"];
+    let mut literal = false;
+    for chunk in chunks.iter_mut().filter(|c| !c.is_empty()) {
+        let mut out = String::with_capacity(chunk.len());
+        for line in chunk.lines() {
+            let request = line.split_whitespace().next().unwrap_or_default();
+            if OPEN.contains(&request) {
+                literal = true;
+            } else if CLOSE.contains(&request) {
+                literal = false;
+            }
+            let mut trimmed = line.trim_end_matches([' ', '\t']);
+            // `\ ` at the end is an escaped space, not a blank.
+            if trimmed.len() < line.len() && trimmed.ends_with('\\') {
+                trimmed = &line[..trimmed.len() + 1];
+            }
+            match trimmed {
+                "" if !literal => out.push_str(".sp"),
+                line => out.push_str(line),
+            }
+            out.push('\n');
+        }
+        *chunk = out;
+    }
+}
+
+/// Fallback `.UR`/`.UE` as in groff's an-ext.tmac: the URL is printed in
+/// angle brackets after the link text.
+const COMPAT_URL_MACROS: &str = r#".if !d UR \{\
+.de UR
+.ds m1 \\$1\"
+.nh
+..
+.de UE
+<\\*(m1>\\$1
+.hy
+..
+.\}
+"#;
+
+/// Fallback `.MT`/`.ME`, the same as the `.UR`/`.UE` ones.
+const COMPAT_MAIL_MACROS: &str = r#".if !d MT \{\
+.de MT
+.ds m2 \\$1\"
+.nh
+..
+.de ME
+<\\*(m2>\\$1
+.hy
+..
+.\}
+"#;
+
+/// Fallback `.EX`/`.EE`: no filling, constant width where there is one.
+const COMPAT_EXAMPLE_MACROS: &str = r#".if !d EX \{\
+.de EX
+.nf
+.if t .ft CW
+..
+.de EE
+.if t .ft R
+.fi
+..
+.\}
+"#;
+
+/// Definitions of the an-ext macros the page uses, for man implementations
+/// that predate them. Formatters that have them (groff, mandoc) keep theirs.
+pub fn compat_macros(nodes: &[ManNode], options: &RoffOptions) -> String {
+    if !options.compat_macros || options.target == Some(TargetFormatter::Portable) {
+        return String::new();
+    }
+    let (mut uses_url, mut uses_mail, mut uses_example) = (false, false, false);
+    for node in nodes {
+        node.walk(&mut |n| {
+            if let ManNode::Uri { url, .. } = n {
+                if url.starts_with("mailto:") {
+                    uses_mail = true;
+                } else {
+                    uses_url = true;
+                }
+            }
+            uses_example |= matches!(n, ManNode::CodeBlock { .. });
+        });
+    }
+    let mut macros = String::new();
+    if uses_url {
+        macros.push_str(COMPAT_URL_MACROS);
+    }
+    if uses_mail {
+        macros.push_str(COMPAT_MAIL_MACROS);
+    }
+    if uses_example {
+        macros.push_str(COMPAT_EXAMPLE_MACROS);
+    }
+    macros
+}
+
+/// Named destination and outline entry for a heading, only emitted when the
+/// pdfmark macros are loaded (`groff -Tpdf -mpdfmark`, or gropdf with its
+/// built-in definitions). Other documents can link to the destination, e.g.
+/// `tool.pdf#nameddest=options`.
+fn pdf_bookmark(b: &mut RoffBuilder, level: u8, title: &str) {
+    let options = b.options();
+    let destination = b.heading(level, title);
+    if options.pdf_bookmarks && options.groff_requests() {
+        let level = level.to_string();
+        let title = RoffBuilder::quote(&escape(title, options.escape_profile));
+        b.request("if", &["d", "pdfhref", ".pdfhref", "M", "-N", &destination]);
+        b.request("if", &["d", "pdfhref", ".pdfhref", "O", &level, &title]);
+    }
+}
+
+/// Web links shorter than this are easy enough to type and get no QR code.
+const QR_MIN_LENGTH: usize = 30;
+
+/// Size of a QR code module in tenths of a point: a version 3 code, enough
+/// for most URLs, is about 2 cm wide with its margin.
+const QR_MODULE: usize = 15;
+
+/// Draw a QR code for each long web link among `inlines` below them, in print
+/// output only (`.if t`): groff -Tpdf and -Tps fill the modules as polygons,
+/// while terminals and mandoc skip the block. The block ends without a line
+/// break, like the text before it.
+fn write_qr_codes(inlines: &[ManNode], b: &mut RoffBuilder) {
+    let options = b.options();
+    if !options.pdf_qr || !options.groff_requests() {
+        return;
+    }
+    let mut urls: Vec<&str> = Vec::new();
+    for node in inlines {
+        node.walk(&mut |n| {
+            if let ManNode::Uri { url, .. } = n
+                && (url.starts_with("https://") || url.starts_with("http://"))
+                && url.len() >= QR_MIN_LENGTH
+                && !urls.contains(&url.as_str())
+            {
+                urls.push(url);
+            }
+        });
+    }
+    for code in urls.iter().filter_map(|url| QrCode::encode(url.as_bytes())) {
+        let points = |tenths: isize| match tenths {
+            0 => "0".to_string(),
+            _ => {
+                let sign = if tenths < 0 { "-" } else { "" };
+                let tenths = tenths.unsigned_abs();
+                format!("{}{}.{}p", sign, tenths / 10, tenths % 10)
+            }
+        };
+        let module = QR_MODULE as isize;
+        // With the light margin of four modules the standard asks for.
+        let height = (code.size as isize + 8) * module;
+        let mut drawing = String::new();
+        let (mut x, mut y) = (-4, -4);
+        for row in 0..code.size {
+            let mut column = 0;
+            while column < code.size {
+                if !code.is_dark(column, row) {
+                    column += 1;
+                    continue;
+                }
+                let run = (column..code.size)
+                    .take_while(|&c| code.is_dark(c, row))
+                    .count();
+                let (to_x, to_y) = (column as isize, row as isize);
+                if to_x != x {
+                    drawing.push_str(&format!("\\h'{}'", points((to_x - x) * module)));
+                }
+                if to_y != y {
+                    drawing.push_str(&format!("\\v'{}'", points((to_y - y) * module)));
+                }
+                // A closed path that ends where it started, so that the
+                // current position stays put.
+                let width = run as isize * module;
+                drawing.push_str(&format!(
+                    "\\D'P {} 0 0 {} {} 0 0 {}'",
+                    points(width),
+                    points(module),
+                    points(-width),
+                    points(-module)
+                ));
+                (x, y) = (to_x, to_y);
+                column += run;
+            }
+        }
+        // Back to the start, so that the line has no width and the next one
+        // is where it would be.
+        drawing.push_str(&format!(
+            "\\h'{}'\\v'{}'",
+            points((-4 - x) * module),
+            points((-4 - y) * module)
+        ));
+        b.end_line()
+            .line(".if t \\{\\")
+            .request("br", &[])
+            .request("ne", &[&points(height)])
+            .line(&drawing)
+            .request("br", &[])
+            .request("sp", &[&points(height)])
+            .raw(".\\}");
+    }
+}
+
+/// The usual title of a section's manual, as man-db and mandoc print it.
+fn section_manual(section: u8) -> &'static str {
+    match section {
+        1 => "User Commands",
+        2 => "System Calls Manual",
+        3 => "Library Functions Manual",
+        4 => "Kernel Interfaces Manual",
+        5 => "File Formats Manual",
+        6 => "Games Manual",
+        7 => "Miscellaneous Information Manual",
+        8 => "System Manager's Manual",
+        9 => "Kernel Developer's Manual",
+        _ => "Manual",
+    }
+}
+
+/// Replace the footer the man macros put at the bottom of every printed page
+/// (`BT`: source, date, page number) when `--footer` or `--no-page-numbers`
+/// changed it. Terminal output keeps its footer: `.rn` only runs in troff mode.
+fn print_footer(b: &mut RoffBuilder, source: Option<&str>, date: &str) {
+    let options = b.options();
+    if options.footer.is_none() && options.page_numbers {
+        return;
+    }
+    // `'` delimits the parts of a `.tl` title; `\%` would vanish from it.
+    let part = |text: &str| {
+        escape(text, options.escape_profile)
+            .replace('\'', "\\(aq")
+            .replace("\\%", "%")
+    };
+    let left = source.map(part).unwrap_or_default();
+    let center = part(options.footer.as_deref().unwrap_or(date));
+    // `.pc` turns off `%` as the page number character, so a `%` in the text
+    // stays one; the number comes from the `%` register instead.
+    let right = if options.page_numbers { "\\\\n%" } else { "" };
+    b.end_line()
+        .raw(".de mdman-footer\n.pc\n")
+        .raw(&format!(".tl '{}'{}'{}'\n", left, center, right))
+        .raw(".pc %\n..\n");
+    b.request("if", &["t", ".rn", "mdman-footer", "BT"]);
+}
+
+/// Languages groff (since 1.22) ships hyphenation patterns and a `-m`
+/// macro package for, with the file of their patterns. English is left out,
+/// as its patterns are loaded anyway.
+const GROFF_LANGUAGES: &[(&str, &str)] = &[
+    ("cs", "hyphen.cs"),
+    ("de", "hyphen.den"),
+    ("fr", "hyphen.fr"),
+    ("sv", "hyphen.sv"),
+];
+
+/// groff names hyphenation languages by their primary language subtag, so
+/// `de-DE` and `de_DE.UTF-8` both become `de`. `None` for languages groff has
+/// no patterns for, for which `.hla` would turn hyphenation off.
+pub fn groff_language(lang: &str) -> Option<&'static str> {
+    hyphenation_patterns(lang).map(|(language, _)| language)
+}
+
+/// The groff language of `lang` and the file with its hyphenation patterns.
+fn hyphenation_patterns(lang: &str) -> Option<(&'static str, &'static str)> {
+    let primary = lang.split(['-', '_', '.']).next()?.to_ascii_lowercase();
+    GROFF_LANGUAGES
+        .iter()
+        .find(|(language, _)| *language == primary)
+        .copied()
+}
+
+/// Width in columns of the longest line of a block.
+pub fn widest_line(text: &str) -> usize {
+    text.lines().map(unicode::display_width).max().unwrap_or(0)
+}
+
+/// Widths of all code and literal blocks that exceed `limit` columns.
+pub fn wide_blocks(nodes: &[ManNode], limit: usize) -> Vec<usize> {
+    let mut widths = Vec::new();
+    for node in nodes {
+        node.walk(&mut |n| {
+            if let ManNode::CodeBlock { code: text, .. } | ManNode::NoFill(text) = n {
+                let width = widest_line(text);
+                if width > limit {
+                    widths.push(width);
+                }
+            }
+        });
+    }
+    widths
+}
+
+fn shrink_if_wide(b: &mut RoffBuilder, text: &str, block: impl FnOnce(&mut RoffBuilder)) {
+    let options = b.options();
+    if options.wide_blocks == WideBlocks::Shrink && widest_line(text) > options.block_width {
+        b.request("if", &["t", ".ps", "-1"]);
+        block(b);
+        b.request("if", &["t", ".ps", "+1"]);
+    } else {
+        block(b);
+    }
+}
+
+fn escape(text: &str, profile: EscapeProfile) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    escape_into(&mut escaped, text, profile, true);
+    escaped
+}
+
+/// Append `text` escaped with `profile` to `out`, where `line_start` says
+/// whether it starts an output line. This is on the path of every word of
+/// prose, so it takes one pass over the text unless there are combining marks
+/// to turn into composite glyphs.
+fn escape_into(out: &mut String, text: &str, profile: EscapeProfile, line_start: bool) {
+    let escape_chars = |out: &mut String| match profile {
+        EscapeProfile::Aggressive => escape_aggressively(out, text),
+        _ => escape_selectively(out, text, profile, line_start),
+    };
+    let composite = matches!(profile, EscapeProfile::Aggressive | EscapeProfile::Groff)
+        && text.chars().any(unicode::is_combining);
+    if composite {
+        let mut escaped = String::with_capacity(text.len());
+        escape_chars(&mut escaped);
+        out.push_str(&composite_glyphs(&escaped));
+    } else {
+        escape_chars(out);
+    }
+}
+
+fn escape_aggressively(out: &mut String, text: &str) {
+    out.reserve(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '.' => out.push_str("\\&."),
+            '\'' => out.push_str("\\&'"),
+            '"' => out.push_str("\\&\""),
+            '-' => out.push_str("\\-"),
+            '~' => out.push_str("\\(ti"),
+            '|' => out.push_str("\\(ba"),
+            '%' => out.push_str("\\%"),
+            _ => out.push(c),
+        }
+    }
+}
+
+/// Combining marks left over after NFC normalization (no precomposed form
+/// exists) are drawn as separate glyphs by groff in terminals. Its composite
+/// glyph syntax, e.g. `\[u0071_0301]`, renders them on their base letter.
+fn composite_glyphs(text: &str) -> String {
+    if !text.chars().any(unicode::is_combining) {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len() + 16);
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let has_marks = chars
+            .peek()
+            .is_some_and(|&next| unicode::is_combining(next));
+        if c.is_alphanumeric() && !unicode::is_combining(c) && has_marks {
+            _ = write!(out, "\\[u{:04X}", c as u32);
+            while let Some(mark) = chars.next_if(|&next| unicode::is_combining(next)) {
+                _ = write!(out, "_{:04X}", mark as u32);
+            }
+            out.push(']');
+        } else if unicode::is_combining(c) {
+            _ = write!(out, "\\[u{:04X}]", c as u32);
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Code is copied from man pages, so it must come out as typed regardless of
+/// the prose profile: ASCII minus, tilde and caret instead of typographic glyphs.
+fn escape_code(out: &mut String, text: &str, line_start: bool) {
+    escape_selectively(out, text, EscapeProfile::Groff, line_start);
+}
+
+/// Escape what `profile` asks for, and `.` and `'` where they would start a
+/// line and make it a control line.
+fn escape_selectively(out: &mut String, text: &str, profile: EscapeProfile, line_start: bool) {
+    out.reserve(text.len());
+    let mut line_start = line_start;
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '.' | '\'' if line_start => {
+                out.push_str("\\&");
+                out.push(c);
+            }
+            '-' if profile != EscapeProfile::Minimal => out.push_str("\\-"),
+            '~' if profile == EscapeProfile::Groff => out.push_str("\\(ti"),
+            '^' if profile == EscapeProfile::Groff => out.push_str("\\(ha"),
+            _ => out.push(c),
+        }
+        line_start = c == '\n';
+    }
 }
 
 #[cfg(test)]
@@ -167,6 +1228,10 @@ mod tests {
             date: Some("2025-01-01".into()),
             left_footer: Some("TestCmd".into()),
             center_footer: Some("v1.0".into()),
+            options_file: None,
+            lang: None,
+            table_style: None,
+            version: None,
         });
 
         let roff = title.to_roff();
@@ -176,6 +1241,418 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_title_line_lang() {
+        let title = ManNode::TitleLine(TitleLine {
+            name: "test-cmd".into(),
+            section: 1,
+            date: Some("2025-01-01".into()),
+            left_footer: None,
+            center_footer: None,
+            options_file: None,
+            lang: Some("de_DE.UTF-8".into()),
+            table_style: None,
+            version: None,
+        });
+        assert_eq!(
+            title.to_roff(),
+            ".TH \"TEST-CMD\" \"1\" \"2025-01-01\"\n.if \\n(.g .hla de\n.if \\n(.g .hpf hyphen.den\n"
+        );
+        assert_eq!(groff_language("fr-CA"), Some("fr"));
+        assert_eq!(groff_language("en"), None);
+        assert_eq!(groff_language("x; .so /etc/passwd"), None);
+    }
+
+    #[test]
+    fn test_lintian_title_line() {
+        let title = |left_footer: Option<&str>, center_footer: Option<&str>| {
+            ManNode::TitleLine(TitleLine {
+                name: "tool".into(),
+                section: 8,
+                date: Some("2025-01-01".into()),
+                left_footer: left_footer.map(String::from),
+                center_footer: center_footer.map(String::from),
+                options_file: None,
+                lang: None,
+                table_style: None,
+                version: Some("2.0".into()),
+            })
+        };
+        assert_eq!(
+            title(None, Some("Tools")).to_roff(),
+            ".TH \"TOOL\" \"8\" \"2025-01-01\" \"\" \"Tools\"\n"
+        );
+        let options = RoffOptions {
+            lintian: true,
+            ..RoffOptions::default()
+        };
+        assert_eq!(
+            title(None, None).to_roff_with(&options),
+            ".TH \"TOOL\" \"8\" \"2025-01-01\" \"tool 2.0\" \"System Manager's Manual\"\n"
+        );
+        assert_eq!(
+            title(Some("Tool"), Some("Tools")).to_roff_with(&options),
+            ".TH \"TOOL\" \"8\" \"2025-01-01\" \"Tool\" \"Tools\"\n"
+        );
+    }
+
+    #[test]
+    fn test_print_footer() {
+        let title = ManNode::TitleLine(TitleLine {
+            name: "tool".into(),
+            section: 1,
+            date: Some("2025-01-01".into()),
+            left_footer: Some("Tool 1.0".into()),
+            center_footer: None,
+            options_file: None,
+            lang: None,
+            table_style: None,
+            version: None,
+        });
+        assert!(!title.to_roff().contains("BT"));
+        let options = RoffOptions {
+            footer: Some("Bob's 100% manual".into()),
+            ..RoffOptions::default()
+        };
+        assert_eq!(
+            title.to_roff_with(&options),
+            ".TH \"TOOL\" \"1\" \"2025-01-01\" \"Tool 1.0\"\n\
+             .de mdman-footer\n.pc\n.tl 'Tool 1\\&.0'Bob\\&\\(aqs 100% manual'\\\\n%'\n.pc %\n..\n\
+             .if t .rn mdman-footer BT\n"
+        );
+        let options = RoffOptions {
+            page_numbers: false,
+            ..RoffOptions::default()
+        };
+        assert!(
+            title
+                .to_roff_with(&options)
+                .contains(".tl 'Tool 1\\&.0'2025\\-01\\-01''\n")
+        );
+    }
+
+    #[test]
+    fn test_pdf_bookmarks() {
+        let options = RoffOptions {
+            pdf_bookmarks: true,
+            ..RoffOptions::default()
+        };
+        let section = ManNode::SectionHeading {
+            title: "SEE ALSO".into(),
+            children: vec![],
+        };
+        let subsection = ManNode::SubsectionHeading {
+            title: "Say \"hi\"".into(),
+            children: vec![],
+        };
+        assert_eq!(
+            section.to_roff_with(&options),
+            ".SH SEE ALSO\n.if d pdfhref .pdfhref M -N see-also\n\
+             .if d pdfhref .pdfhref O 1 \"SEE ALSO\"\n"
+        );
+        assert!(
+            subsection
+                .to_roff_with(&options)
+                .ends_with(".pdfhref O 2 \"Say \\&\\(dqhi\\&\\(dq\"\n")
+        );
+        let nested = ManNode::SectionHeading {
+            title: "OPTIONS".into(),
+            children: vec![subsection],
+        };
+        assert!(
+            nested
+                .to_roff_with(&options)
+                .contains("\n.if d pdfhref .pdfhref M -N options.say-hi\n")
+        );
+        assert_eq!(section.to_roff(), ".SH SEE ALSO\n");
+    }
+
+    #[test]
+    fn test_pdf_qr() {
+        let options = RoffOptions {
+            pdf_qr: true,
+            ..RoffOptions::default()
+        };
+        let link = |url: &str| ManNode::Uri {
+            url: url.into(),
+            title: None,
+            children: vec![ManNode::Text("the guide".into())],
+        };
+        let para = ManNode::Paragraph {
+            children: vec![
+                link("https://example.com/docs/getting-started.html"),
+                link("https://example.com/docs/getting-started.html"),
+                link("https://example.com/short"),
+            ],
+        };
+        let roff = para.to_roff_with(&options);
+        assert_eq!(roff.matches(".if t \\{\\\n").count(), 1);
+        let (_, block) = roff.split_once(".if t \\{\\\n").unwrap();
+        let lines = block.lines().collect::<Vec<_>>();
+        // A version 4 code of 33 modules and the margins, 1.5p each.
+        assert_eq!(lines[..2], [".br", ".ne 61.5p"]);
+        assert_eq!(lines[3..], [".br", ".sp 61.5p", ".\\}"]);
+        assert!(lines[2].starts_with("\\h'6.0p'\\v'6.0p'\\D'P 10.5p 0 0 1.5p -10.5p 0 0 -1.5p'"));
+        // The drawing ends where it started.
+        let moves = |escape: &str| {
+            lines[2]
+                .split(escape)
+                .skip(1)
+                .map(|rest| rest.split('\'').next().unwrap().trim_end_matches('p'))
+                .map(|n| n.parse::<f64>().unwrap())
+                .sum::<f64>()
+        };
+        assert_eq!((moves("\\h'"), moves("\\v'")), (0.0, 0.0));
+
+        assert!(!para.to_roff().contains(".if t"));
+        let mandoc = RoffOptions {
+            target: Some(TargetFormatter::Mandoc),
+            ..options
+        };
+        assert!(!para.to_roff_with(&mandoc).contains(".if t"));
+    }
+
+    #[test]
+    fn test_minor_headings() {
+        let heading = |level| ManNode::MinorHeading {
+            level,
+            title: ".dotfiles".into(),
+            children: vec![],
+        };
+        assert_eq!(heading(3).to_roff(), ".PD\n.PP\n\\fB\\&.dotfiles\\fP\n");
+        assert_eq!(heading(5).to_roff(), ".PD\n.PP\n\\f(BI\\&.dotfiles\\fP\n");
+    }
+
+    #[test]
+    fn test_target_formatter() {
+        let code = ManNode::InlineCode("ls".into());
+        let link = ManNode::Paragraph {
+            children: vec![
+                ManNode::Text("see ".into()),
+                ManNode::Uri {
+                    url: "https://example.org".into(),
+                    title: None,
+                    children: vec![ManNode::Text("docs".into())],
+                },
+                ManNode::Text(".".into()),
+            ],
+        };
+        let target = |target| RoffOptions {
+            target: Some(target),
+            ..RoffOptions::default()
+        };
+        let groff = target(TargetFormatter::Groff);
+        let mandoc = target(TargetFormatter::Mandoc);
+        let portable = target(TargetFormatter::Portable);
+
+        assert_eq!(code.to_roff(), "\\fCls\\fP");
+        assert_eq!(code.to_roff_with(&groff), "\\f(CRls\\fP");
+        assert_eq!(code.to_roff_with(&mandoc), "\\f(CWls\\fP");
+        assert_eq!(code.to_roff_with(&portable), "\\fBls\\fP");
+
+        assert!(link.to_roff_with(&mandoc).contains(".UR https"));
+        assert_eq!(
+            link.to_roff_with(&portable),
+            ".PD\n.PP\nsee docs <https://example\\&.org>\\&.\n"
+        );
+        assert_eq!(
+            ManNode::code_block("ls").to_roff_with(&portable),
+            ".nf\nls\n.fi\n"
+        );
+
+        let title = ManNode::TitleLine(TitleLine {
+            name: "t".into(),
+            section: 1,
+            date: Some("2025-01-01".into()),
+            left_footer: None,
+            center_footer: None,
+            options_file: None,
+            lang: Some("de".into()),
+            table_style: None,
+            version: None,
+        });
+        assert!(title.to_roff_with(&groff).contains(".hla de"));
+        assert!(!title.to_roff_with(&mandoc).contains(".hla"));
+    }
+
+    #[test]
+    fn test_compat_macros() {
+        let nodes = [ManNode::code_block("ls")];
+        let options = RoffOptions {
+            compat_macros: true,
+            ..RoffOptions::default()
+        };
+        assert_eq!(compat_macros(&nodes, &RoffOptions::default()), "");
+        let macros = compat_macros(&nodes, &options);
+        assert!(macros.starts_with(".if !d EX \\{\\\n.de EX\n"));
+        assert!(!macros.contains(".de UR"));
+    }
+
+    #[test]
+    fn test_task_list_roff() {
+        let task = |checked, text: &str| ManNode::ListItem {
+            checked,
+            children: vec![ManNode::Text(text.into())],
+        };
+        let list = ManNode::BulletList {
+            children: vec![
+                task(Some(true), "done"),
+                task(Some(false), "todo"),
+                task(None, "note"),
+            ],
+        };
+        assert_eq!(
+            list.to_roff(),
+            "\n.RS 2\n.PD 0\n.IP [x] 4\ndone\n.IP [\\ ] 4\ntodo\n.IP \\(bu 4\nnote\n\n.RE\n"
+        );
+    }
+
+    #[test]
+    fn test_huge_table_roff() {
+        let cell = |text: String| ManNode::TableCell(vec![ManNode::Text(text)]);
+        let rows = (0..10_000)
+            .map(|i| ManNode::TableRow(vec![cell(format!("row-{}", i)), cell("x".into())]))
+            .collect::<Vec<_>>();
+        let estimate = table_size(&rows);
+        let table = ManNode::Table {
+            align: vec![TableAlign::Left, TableAlign::Right],
+            style: None,
+            layout: TableLayout::default(),
+            children: rows,
+        };
+        let roff = table.to_roff();
+        assert!(roff.starts_with(".TS\nallbox;\nlB rB\nl r.\nT{\nrow\\-0\nT}\tT{\nx\nT}\t\n"));
+        assert!(roff.ends_with("T{\nrow\\-9999\nT}\tT{\nx\nT}\t\n.TE\n"));
+        assert_eq!(roff.lines().count(), 10_000 * 5 + 5);
+        // Reserving the estimate up front leaves no growing to do.
+        assert!(
+            estimate >= roff.len() && estimate < roff.len() * 2,
+            "{} for {}",
+            estimate,
+            roff.len()
+        );
+    }
+
+    #[test]
+    fn test_table_row_wider_than_header() {
+        let cell = |text: &str| ManNode::TableCell(vec![ManNode::Text(text.into())]);
+        let table = ManNode::Table {
+            align: vec![TableAlign::Right],
+            style: None,
+            layout: TableLayout::default(),
+            children: vec![
+                ManNode::TableRow(vec![cell("a")]),
+                ManNode::TableRow(vec![cell("1"), cell("2")]),
+            ],
+        };
+        assert!(table.to_roff().contains("\nallbox;\nrB lB\nr l.\n"));
+    }
+
+    #[test]
+    fn test_table_column_widths() {
+        let markdown = format!(
+            "| Name | Description |\n|---|:-:|\n| a | {} |\n\n\
+             <!-- table: 10 - expand -->\n\n| x | y |\n|---|---|\n| 1 | 2 |\n",
+            "word ".repeat(30)
+        );
+        let (nodes, _) = crate::parse_page(&markdown, &None).unwrap();
+        assert!(
+            nodes[0]
+                .to_roff()
+                .starts_with(".TS\nallbox;\nlB cBw(65n)\nl cw(65n).\n")
+        );
+        assert!(
+            nodes[1]
+                .to_roff()
+                .starts_with(".TS\nallbox expand;\nlBw(10n) lB\nlw(10n) l.\n")
+        );
+
+        // Narrow columns keep their width; the wide ones share what is left.
+        let cell = |width: usize| ManNode::TableCell(vec![ManNode::Text("x".repeat(width))]);
+        let rows = [ManNode::TableRow(vec![cell(6), cell(50), cell(90)])];
+        let widths = column_widths(&rows, &TableLayout::default(), 3, 72);
+        assert_eq!(widths, [None, Some(30), Some(30)]);
+        assert_eq!(
+            column_widths(&rows, &TableLayout::default(), 3, 200),
+            [None, None, None]
+        );
+    }
+
+    #[test]
+    fn test_table_spans() {
+        let markdown =
+            "| Name | Value | |\n|---|---|---|\n| a | 1 | 2 |\n| wide | |  |\n| b | 3 | 4 |\n";
+        let (nodes, _) = crate::parse_page(markdown, &None).unwrap();
+        let roff = nodes[0].to_roff();
+        assert!(roff.starts_with(".TS\nallbox;\nlB lB s\nl l l\nl s s\nl l l.\n"));
+        assert!(roff.contains("T}\t\nT{\nwide\nT}\t\nT{\nb\n"));
+    }
+
+    #[test]
+    fn test_table_style() {
+        let markdown =
+            "---\nname: tool\nsection: 1\ntable-style: none\n---\n\n| a |\n|---|\n| 1 |\n";
+        let (nodes, _) = crate::parse_page(markdown, &None).unwrap();
+        let table = &nodes[1];
+        assert!(table.to_roff().starts_with(".TS\nlB\nl.\n"));
+        let options = RoffOptions {
+            table_style: Some(TableStyle::Doublebox),
+            ..RoffOptions::default()
+        };
+        assert!(
+            table
+                .to_roff_with(&options)
+                .starts_with(".TS\ndoublebox;\nlB\nl.\n")
+        );
+    }
+
+    #[test]
+    fn test_blocks_in_list_items() {
+        let list = ManNode::BulletList {
+            children: vec![ManNode::list_item(vec![
+                ManNode::Text("item".into()),
+                ManNode::Table {
+                    align: vec![TableAlign::None],
+                    style: None,
+                    layout: TableLayout::default(),
+                    children: vec![ManNode::TableRow(vec![ManNode::TableCell(vec![
+                        ManNode::Text("a".into()),
+                    ])])],
+                },
+                ManNode::Text("\n".into()),
+                ManNode::Text("after".into()),
+            ])],
+        };
+        assert_eq!(
+            list.to_roff(),
+            "\n.RS 2\n.PD 0\n.IP \\(bu 2\nitem\n.TS\nallbox;\nlB\nl.\nT{\na\nT}\t\n.TE\nafter\n\n.RE\n"
+        );
+    }
+
+    #[test]
+    fn test_blockquote_roff() {
+        let quote = ManNode::Blockquote {
+            children: vec![
+                ManNode::Paragraph {
+                    children: vec![ManNode::Text("note".into())],
+                },
+                ManNode::code_block("key = 1"),
+            ],
+        };
+        assert_eq!(
+            quote.to_roff(),
+            ".RS 4\n.PD\n.PP\nnote\n.EX\nkey = 1\n.EE\n.RE\n"
+        );
+        let options = RoffOptions {
+            italic_quotes: true,
+            ..RoffOptions::default()
+        };
+        assert_eq!(
+            quote.to_roff_with(&options),
+            ".RS 4\n.ft I\n.PD\n.PP\n.ft I\nnote\n.EX\nkey = 1\n.EE\n.ft R\n.RE\n"
+        );
+    }
+
     #[test]
     fn test_paragraph_roff() {
         let para = ManNode::Paragraph {
@@ -186,19 +1663,274 @@ mod tests {
         assert!(roff.contains(".PP\nHello\n"));
     }
 
+    #[test]
+    fn test_strikethrough_roff() {
+        let node = ManNode::Strikethrough(vec![ManNode::Text("--old".into())]);
+        assert_eq!(node.to_roff(), "\\fI\\-\\-old\\fP (deprecated)");
+    }
+
+    #[test]
+    fn test_line_break_roff() {
+        let para = ManNode::Paragraph {
+            children: vec![
+                ManNode::bold("tool"),
+                ManNode::Text(" FILE  ".into()),
+                ManNode::LineBreak,
+                ManNode::Text(".hidden".into()),
+            ],
+        };
+        assert_eq!(
+            para.to_roff(),
+            ".PD\n.PP\n\\fBtool\\fP FILE\n.br\n\\&.hidden\n"
+        );
+    }
+
+    #[test]
+    fn test_plain_paragraph_roff() {
+        let para = ManNode::Paragraph {
+            children: vec![
+                ManNode::Text(" one-".into()),
+                ManNode::SoftBreak,
+                ManNode::Text("  two".into()),
+            ],
+        };
+        let expected = ".PD\n.PP\n one\\-\ntwo\n";
+        assert_eq!(para.to_roff(), expected);
+        // Same as the general path, which a link in the paragraph takes.
+        let options = RoffOptions::default();
+        let mut b = RoffBuilder::new(&options);
+        let text = render_inlines(para.children(), &b);
+        b.request("PD", &[])
+            .request("PP", &[])
+            .raw(&text)
+            .end_line();
+        assert_eq!(b.finish(), expected);
+    }
+
     #[test]
     fn test_bold_text_roff() {
-        let node = ManNode::Bold("bold text".into());
+        let node = ManNode::bold("bold text");
         assert_eq!(node.to_roff(), "\\fBbold text\\fP");
     }
 
     #[test]
     fn test_code_block_roff() {
-        let node = ManNode::CodeBlock("echo hello".into());
+        let node = ManNode::code_block("echo hello");
         let roff = node.to_roff();
         assert_eq!(roff, ".EX\necho hello\n.EE\n");
     }
 
+    #[test]
+    fn test_escape_profiles() {
+        let text = "50% of a|b ~ x-y. 2^3\n.start";
+        assert_eq!(
+            escape(text, EscapeProfile::Aggressive),
+            "50\\% of a\\(bab \\(ti x\\-y\\&. 2^3\n\\&.start"
+        );
+        assert_eq!(
+            escape(text, EscapeProfile::Minimal),
+            "50% of a|b ~ x-y. 2^3\n\\&.start"
+        );
+        assert_eq!(
+            escape(text, EscapeProfile::Groff),
+            "50% of a|b \\(ti x\\-y. 2\\(ha3\n\\&.start"
+        );
+        assert_eq!(
+            escape(text, EscapeProfile::Mandoc),
+            "50% of a|b ~ x\\-y. 2^3\n\\&.start"
+        );
+    }
+
+    #[test]
+    fn test_wide_block_shrink() {
+        let wide = "x".repeat(DEFAULT_BLOCK_WIDTH + 1);
+        let options = RoffOptions {
+            wide_blocks: WideBlocks::Shrink,
+            ..RoffOptions::default()
+        };
+        let roff = ManNode::code_block(wide.clone()).to_roff_with(&options);
+        assert_eq!(
+            roff,
+            format!(".if t .ps -1\n.EX\n{}\n.EE\n.if t .ps +1\n", wide)
+        );
+
+        let narrow = ManNode::NoFill("x".into()).to_roff_with(&options);
+        assert_eq!(narrow, ".nf\nx\n.fi\n");
+    }
+
+    #[test]
+    fn test_widest_line_counts_columns() {
+        assert_eq!(widest_line("ab\n日本語\nx"), 6);
+    }
+
+    #[test]
+    fn test_wide_blocks_found_in_lists() {
+        let nodes = vec![ManNode::BulletList {
+            children: vec![ManNode::list_item(vec![ManNode::code_block(
+                "short\n0123456789",
+            )])],
+        }];
+        assert_eq!(wide_blocks(&nodes, 8), [10]);
+        assert!(wide_blocks(&nodes, 10).is_empty());
+    }
+
+    #[test]
+    fn test_pic_roff() {
+        let node = ManNode::Pic("box; arrow; box".into());
+        assert_eq!(node.to_roff(), ".PS\nbox; arrow; box\n.PE\n");
+        let options = RoffOptions::default();
+        assert_eq!(
+            preprocessor_line(&[node], &options).as_deref(),
+            Some("'\\\" p\n")
+        );
+        assert_eq!(
+            preprocessor_line(&[ManNode::Text("x".into())], &options),
+            None
+        );
+    }
+
+    #[test]
+    fn test_man_refs_roff() {
+        let (nodes, _) =
+            crate::parse_page("See **ls**(1), (grep(1)) and ls-files(1).\n", &None).unwrap();
+        assert_eq!(
+            nodes[0].to_roff(),
+            ".PD\n.PP\nSee\n.BR ls (1),\n.RB ( grep (1))\nand\n.BR ls\\-files (1)\\&.\n"
+        );
+    }
+
+    #[test]
+    fn test_man_refs_in_emphasis_roff() {
+        let markdown = "**See (cat(1))**, *or ls(1)* and ~~dir(1)~~.\n";
+        let (nodes, _) = crate::parse_page(markdown, &None).unwrap();
+        assert_eq!(
+            nodes[0].to_roff(),
+            ".PD\n.PP\n\\fBSee (\\fBcat\\fB(1))\\fR, \\fIor \\f(BIls\\fI(1)\\fR \
+             and \\fI\\f(BIdir\\fI(1)\\fR (deprecated)\\&.\n"
+        );
+    }
+
+    #[test]
+    fn test_equations() {
+        let markdown = "# EQ\n\n```math\nx over 2\n```\n\n$$\nE = m c sup 2\n$$\n\n\
+                        | a |\n|---|\n| $5 |\n";
+        let (nodes, _) = crate::parse_page(markdown, &None).unwrap();
+        assert_eq!(nodes[1].to_roff(), ".EQ\nx over 2\n.EN\n");
+        assert_eq!(nodes[2].to_roff(), ".EQ\nE = m c sup 2\n.EN\n");
+        assert_eq!(
+            preprocessor_line(&nodes, &RoffOptions::default()).as_deref(),
+            Some("'\\\" te\n")
+        );
+    }
+
+    #[test]
+    fn test_tbl_preprocessor_line() {
+        let nodes = [
+            ManNode::Pic("box".into()),
+            ManNode::Table {
+                align: vec![TableAlign::None],
+                style: None,
+                layout: TableLayout::default(),
+                children: vec![],
+            },
+        ];
+        let options = RoffOptions::default();
+        assert_eq!(
+            preprocessor_line(&nodes, &options).as_deref(),
+            Some("'\\\" pt\n")
+        );
+        let without = RoffOptions {
+            tbl_line: false,
+            ..RoffOptions::default()
+        };
+        assert_eq!(preprocessor_line(&nodes[1..], &without), None);
+        let all = [
+            ManNode::Equation("x".into()),
+            ManNode::Pic("box".into()),
+            ManNode::Table {
+                align: vec![TableAlign::None],
+                style: None,
+                layout: TableLayout::default(),
+                children: vec![],
+            },
+        ];
+        assert_eq!(
+            preprocessor_line(&all, &options).as_deref(),
+            Some("'\\\" pte\n")
+        );
+    }
+
+    #[test]
+    fn test_no_fill_roff() {
+        let node = ManNode::NoFill("Jane Doe\n.  Main St. 1".into());
+        assert_eq!(node.to_roff(), ".nf\nJane Doe\n\\&.  Main St\\&. 1\n.fi\n");
+    }
+
+    #[test]
+    fn test_soft_break_policies() {
+        let para = ManNode::Paragraph {
+            children: vec![
+                ManNode::Text("one".into()),
+                ManNode::SoftBreak,
+                ManNode::Text("two".into()),
+            ],
+        };
+        let render = |soft_breaks| {
+            para.to_roff_with(&RoffOptions {
+                soft_breaks,
+                ..RoffOptions::default()
+            })
+        };
+        assert_eq!(render(SoftBreaks::Keep), ".PD\n.PP\none\ntwo\n");
+        assert_eq!(render(SoftBreaks::Join), ".PD\n.PP\none two\n");
+        assert_eq!(render(SoftBreaks::Br), ".PD\n.PP\none\n.br\ntwo\n");
+    }
+
+    #[test]
+    fn test_raw_inline_roff() {
+        let node = ManNode::Paragraph {
+            children: vec![
+                ManNode::RawInline("\\*(lq".into()),
+                ManNode::Text("quoted.".into()),
+            ],
+        };
+        assert!(node.to_roff().contains("\n\\*(lqquoted\\&.\n"));
+    }
+
+    #[test]
+    fn test_raw_block_roff() {
+        let item = ManNode::list_item(vec![
+            ManNode::Text("item".into()),
+            ManNode::RawBlock(".sp".into()),
+            ManNode::Text("after".into()),
+        ]);
+        assert_eq!(item.to_roff(), "item\n.sp\nafter");
+    }
+
+    #[test]
+    fn test_composite_glyphs() {
+        assert_eq!(
+            escape("q\u{301}x", EscapeProfile::Aggressive),
+            "\\[u0071_0301]x"
+        );
+        assert_eq!(escape("-\u{301}", EscapeProfile::Groff), "\\-\\[u0301]");
+        assert_eq!(escape("q\u{301}", EscapeProfile::Minimal), "q\u{301}");
+        assert_eq!(escape("café", EscapeProfile::Aggressive), "café");
+    }
+
+    #[test]
+    fn test_escapes_in_code_and_emphasis() {
+        let code = ManNode::code_block("printf 'a\\n' | tr -d ~\n.hidden");
+        assert_eq!(
+            code.to_roff(),
+            ".EX\nprintf 'a\\\\n' | tr \\-d \\(ti\n\\&.hidden\n.EE\n"
+        );
+        let inline = ManNode::InlineCode("\\fB".into());
+        assert_eq!(inline.to_roff(), "\\fC\\\\fB\\fP");
+        let bold = ManNode::bold("a\\b");
+        assert_eq!(bold.to_roff(), "\\fBa\\\\b\\fP");
+    }
+
     #[test]
     fn test_uri_roff() {
         let node = ManNode::Uri {
@@ -210,4 +1942,85 @@ mod tests {
         let roff = node.to_roff();
         assert_eq!(roff, "\n.UR https://example.com\nLink Text\n.UE\n")
     }
+
+    #[test]
+    fn test_text_after_link_on_a_line_of_its_own() {
+        let options = RoffOptions {
+            escape_profile: EscapeProfile::Minimal,
+            ..RoffOptions::default()
+        };
+        let paragraph = ManNode::Paragraph {
+            children: vec![
+                ManNode::Uri {
+                    url: "https://example.com".into(),
+                    title: None,
+                    children: vec![ManNode::Text("docs".into())],
+                },
+                ManNode::Text(", .hidden and".into()),
+                ManNode::SoftBreak,
+                ManNode::Text(" 'quoted' .x".into()),
+            ],
+        };
+        let mut b = RoffBuilder::new(&options);
+        paragraph.write_roff(&mut b);
+        assert_eq!(
+            b.finish(),
+            ".PD\n.PP\n.UR https://example.com\ndocs\n.UE ,\n\\&.hidden and\n\\&'quoted' .x\n"
+        );
+    }
+
+    #[test]
+    fn test_autolink_and_email_roff() {
+        let link = |url: &str, text: &str| ManNode::Uri {
+            url: url.into(),
+            title: None,
+            children: vec![ManNode::Text(text.into())],
+        };
+        let autolink = link("https://example.com", "https://example.com");
+        assert_eq!(autolink.to_roff(), "\n.UR https://example.com\n.UE\n");
+        let www = link("http://www.example.com", "www.example.com");
+        assert_eq!(www.to_roff(), "\n.UR http://www.example.com\n.UE\n");
+        let email = link("mailto:user@example.com", "user@example.com");
+        assert_eq!(email.to_roff(), "\n.MT user@example.com\n.ME\n");
+        let named = link("mailto:user@example.com", "the author");
+        assert_eq!(named.to_roff(), "\n.MT user@example.com\nthe author\n.ME\n");
+
+        let options = RoffOptions {
+            compat_macros: true,
+            ..RoffOptions::default()
+        };
+        let macros = compat_macros(&[email], &options);
+        assert!(macros.starts_with(".if !d MT") && !macros.contains(".de UR"));
+    }
+
+    #[test]
+    fn test_normalize_stable() {
+        let mut chunks = vec![
+            ".IP \\(bu 2\nitem  \n\n".to_string(),
+            String::new(),
+            ".nf\nline\n\n".to_string(),
+            "more\t\n.fi\nend\\ \n".to_string(),
+            "last".to_string(),
+        ];
+        normalize_stable(&mut chunks);
+        assert_eq!(
+            chunks,
+            [
+                ".IP \\(bu 2\nitem\n.sp\n",
+                "",
+                ".nf\nline\n\n",
+                "more\n.fi\nend\\ \n",
+                "last\n"
+            ]
+        );
+
+        let page = crate::parse_page(
+            "---\nname: tool\nsection: 1\n---\n\n# NAME\n\ntool\n",
+            &None,
+        )
+        .unwrap()
+        .0;
+        let (preamble, _) = crate::render_page(&page, &RoffOptions::default().stable());
+        assert_eq!(preamble, ".\\\" mdman stable output style 1\n");
+    }
 }
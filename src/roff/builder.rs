@@ -0,0 +1,330 @@
+use super::{RoffOptions, escape_code, escape_into};
+
+/// Assembles roff output. Requests and macro calls always begin a line of
+/// their own, and text is escaped for the context it is written in, so the
+/// rendering code only has to say what to emit.
+pub struct RoffBuilder<'a> {
+    out: String,
+    options: &'a RoffOptions,
+    /// Fonts of the enclosing [`RoffBuilder::font`] calls, innermost last.
+    fonts: Vec<String>,
+    /// Fonts of the enclosing [`RoffBuilder::block_font`] calls, innermost last.
+    block_fonts: Vec<String>,
+    /// Slugs of the headings the output is under, outermost first.
+    outline: Vec<String>,
+    /// Whether nothing but font changes is on the current output line, so
+    /// that a `.` or `'` written next would make it a control line. Font
+    /// escapes do not count: lines are split in front of them when inline
+    /// requests are joined.
+    line_start: bool,
+}
+
+/// Macros that reset the font to roman.
+const PARAGRAPH_MACROS: &[&str] = &["PP", "LP", "P", "IP", "TP", "HP"];
+
+impl<'a> RoffBuilder<'a> {
+    pub fn new(options: &'a RoffOptions) -> Self {
+        Self {
+            out: String::new(),
+            options,
+            fonts: Vec::new(),
+            block_fonts: Vec::new(),
+            outline: Vec::new(),
+            line_start: true,
+        }
+    }
+
+    /// An empty builder for output that goes where this one is now, inside
+    /// the same fonts and on the same line.
+    pub fn nested(&self) -> Self {
+        Self {
+            out: String::new(),
+            options: self.options,
+            fonts: self.fonts.clone(),
+            block_fonts: self.block_fonts.clone(),
+            outline: self.outline.clone(),
+            line_start: self.line_start,
+        }
+    }
+
+    /// The same as [`RoffBuilder::nested`], for output that goes after
+    /// `before`, which this builder has not seen.
+    pub fn nested_after(&self, before: &str) -> Self {
+        let mut nested = self.nested();
+        if !before.is_empty() {
+            nested.line_start = before.ends_with('\n');
+        }
+        nested
+    }
+
+    pub fn options(&self) -> &'a RoffOptions {
+        self.options
+    }
+
+    /// Enter a heading at `level` (1 for `.SH`) and return a name for it made
+    /// from its title and those of the headings it is under, e.g.
+    /// `options.exit-codes`, so that equal subsection titles in different
+    /// sections get different names.
+    pub fn heading(&mut self, level: u8, title: &str) -> String {
+        let slug = title
+            .to_lowercase()
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>()
+            .join("-");
+        self.outline.truncate(usize::from(level).saturating_sub(1));
+        self.outline.push(if slug.is_empty() {
+            "section".into()
+        } else {
+            slug
+        });
+        self.outline.join(".")
+    }
+
+    pub fn finish(self) -> String {
+        self.out
+    }
+
+    /// Make room for about `additional` more bytes of output up front.
+    pub fn reserve(&mut self, additional: usize) -> &mut Self {
+        self.out.reserve(additional);
+        self
+    }
+
+    /// Whether text written next starts an output line, font changes aside.
+    pub fn at_line_start(&self) -> bool {
+        self.line_start
+    }
+
+    /// Note where `written` left the line.
+    fn wrote(&mut self, written: &str) {
+        if !written.is_empty() {
+            self.line_start = written.ends_with('\n');
+        }
+    }
+
+    /// Finish the current line, if anything was written to it.
+    pub fn end_line(&mut self) -> &mut Self {
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+        self.line_start = true;
+        self
+    }
+
+    /// A line break, even if it leaves an empty line behind.
+    pub fn newline(&mut self) -> &mut Self {
+        self.out.push('\n');
+        self.line_start = true;
+        self
+    }
+
+    /// A request or macro call, e.g. `.request("SH", &["NAME"])`. Arguments
+    /// are written as given; use [`RoffBuilder::quote`] for ones with spaces.
+    pub fn request(&mut self, name: &str, args: &[&str]) -> &mut Self {
+        self.end_line();
+        self.out.push('.');
+        self.out.push_str(name);
+        for arg in args {
+            self.out.push(' ');
+            self.out.push_str(arg);
+        }
+        self.out.push('\n');
+        if let Some(font) = self.block_fonts.last()
+            && PARAGRAPH_MACROS.contains(&name)
+        {
+            self.out.push_str(&format!(".ft {}\n", font));
+        }
+        self.line_start = true;
+        self
+    }
+
+    /// A whole line that is not a request, e.g. tbl format lines.
+    pub fn line(&mut self, line: &str) -> &mut Self {
+        self.end_line();
+        self.out.push_str(line);
+        self.out.push('\n');
+        self.line_start = true;
+        self
+    }
+
+    /// Prose, escaped with the configured profile.
+    pub fn text(&mut self, text: &str) -> &mut Self {
+        let profile = self.options.escape_profile;
+        escape_into(&mut self.out, text, profile, self.line_start);
+        self.wrote(text);
+        self
+    }
+
+    /// Code, where every character has to come out as written.
+    pub fn code(&mut self, text: &str) -> &mut Self {
+        escape_code(&mut self.out, text, self.line_start);
+        self.wrote(text);
+        self
+    }
+
+    /// Output that is already roff.
+    pub fn raw(&mut self, roff: &str) -> &mut Self {
+        self.out.push_str(roff);
+        self.wrote(roff);
+        self
+    }
+
+    /// Whether output goes inside a [`RoffBuilder::font`] call.
+    pub fn in_font(&self) -> bool {
+        !self.fonts.is_empty()
+    }
+
+    /// Switch to `font` for whatever `f` writes, then back to the previous one.
+    /// Inside another font the two are combined, e.g. `I` in `B` is `BI`.
+    pub fn font(&mut self, font: &str, f: impl FnOnce(&mut Self)) -> &mut Self {
+        let font = match self.fonts.last().or(self.block_fonts.last()) {
+            Some(outer) => combined_font(outer, font),
+            None => font.to_string(),
+        };
+        self.out.push_str(&select_font(&font));
+        self.fonts.push(font);
+        let start = self.out.len();
+        f(self);
+        self.fonts.pop();
+        // `\fP` only remembers one font, so it is wrong after nested changes.
+        match self.fonts.last().or(self.block_fonts.last()) {
+            Some(outer) => self.out.push_str(&select_font(outer)),
+            None if has_font_escape(&self.out[start..]) => self.out.push_str("\\fR"),
+            None => self.out.push_str("\\fP"),
+        }
+        self
+    }
+
+    /// Set `font` with `.ft` for the blocks `f` writes, e.g. a whole block
+    /// quote. The paragraph macros reset the font, so it is set again after
+    /// each of them.
+    pub fn block_font(&mut self, font: &str, f: impl FnOnce(&mut Self)) -> &mut Self {
+        let outer = self.block_fonts.last().cloned();
+        let font = match &outer {
+            Some(outer) => combined_font(outer, font),
+            None => font.to_string(),
+        };
+        self.request("ft", &[&font]);
+        self.block_fonts.push(font);
+        f(self);
+        self.block_fonts.pop();
+        self.request("ft", &[outer.as_deref().unwrap_or("R")]);
+        self
+    }
+
+    /// A double-quoted request argument. Quotes inside it become `\(dq`.
+    pub fn quote(arg: &str) -> String {
+        format!("\"{}\"", arg.replace('"', "\\(dq"))
+    }
+}
+
+/// Whether `roff` switches fonts, as opposed to containing an escaped
+/// backslash followed by `f`.
+fn has_font_escape(roff: &str) -> bool {
+    let mut chars = roff.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' && chars.next() == Some('f') {
+            return true;
+        }
+    }
+    false
+}
+
+fn select_font(font: &str) -> String {
+    match font.len() {
+        1 => format!("\\f{}", font),
+        2 => format!("\\f({}", font),
+        _ => format!("\\f[{}]", font),
+    }
+}
+
+/// The font for `inner` text inside `outer` text: bold and italic make bold
+/// italic, and code keeps its constant width font in the bold or italic variant.
+pub fn combined_font(outer: &str, inner: &str) -> String {
+    let is_code = |font: &str| font.starts_with('C');
+    let font = match (outer, inner) {
+        ("B", "I") | ("I", "B") | ("BI", "B" | "I") => "BI",
+        (code, "B") | ("B" | "BI", code) if is_code(code) => "CB",
+        (code, "I") | ("I", code) if is_code(code) => "CI",
+        _ => inner,
+    };
+    font.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roff::EscapeProfile;
+
+    #[test]
+    fn test_requests_start_a_line() {
+        let options = RoffOptions::default();
+        let mut b = RoffBuilder::new(&options);
+        b.request("SH", &["NAME"])
+            .text("a-b")
+            .request("TH", &[&RoffBuilder::quote("say \"hi\"")])
+            .font("B", |b| {
+                b.code("x.y");
+            })
+            .font("CR", |b| {
+                b.raw("z");
+            });
+        assert_eq!(
+            b.finish(),
+            ".SH NAME\na\\-b\n.TH \"say \\(dqhi\\(dq\"\n\\fBx.y\\fP\\f(CRz\\fP"
+        );
+    }
+
+    #[test]
+    fn test_nested_fonts() {
+        let options = RoffOptions::default();
+        let mut b = RoffBuilder::new(&options);
+        b.font("B", |b| {
+            b.text("a ").font("I", |b| {
+                b.text("b ").font("CR", |b| {
+                    b.code("c");
+                });
+            });
+            b.text(" d");
+        });
+        assert_eq!(b.finish(), "\\fBa \\f(BIb \\f(CBc\\f(BI\\fB d\\fR");
+    }
+
+    #[test]
+    fn test_control_characters_at_line_start() {
+        let options = RoffOptions {
+            escape_profile: EscapeProfile::Minimal,
+            ..RoffOptions::default()
+        };
+        let mut b = RoffBuilder::new(&options);
+        b.text(".a .b\n'c").code(" .d\n");
+        let mut nested = b.nested();
+        nested.text(".e");
+        b.raw(&nested.finish()).font("B", |b| {
+            b.code(".f");
+        });
+        b.newline().font("B", |b| {
+            b.code(".g");
+        });
+        let mut after = b.nested_after("x");
+        after.text(".h");
+        assert_eq!(
+            b.finish(),
+            "\\&.a .b\n\\&'c .d\n\\&.e\\fB.f\\fP\n\\fB\\&.g\\fP"
+        );
+        assert_eq!(after.finish(), ".h");
+    }
+
+    #[test]
+    fn test_block_font() {
+        let options = RoffOptions::default();
+        let mut b = RoffBuilder::new(&options);
+        b.block_font("I", |b| {
+            b.request("PP", &[]).text("a ").font("B", |b| {
+                b.text("b");
+            });
+        });
+        assert_eq!(b.finish(), ".ft I\n.PP\n.ft I\na \\f(BIb\\fI\n.ft R\n");
+    }
+}
@@ -0,0 +1,119 @@
+//! Install rules for the man pages of a project, for pasting into the build
+//! files of the usual build systems (`mdman build --emit-install-snippet`).
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+
+/// A build system to write install rules for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BuildSystem {
+    /// `manN.install` lines for the `install` method of a Homebrew formula.
+    Homebrew,
+    /// `install(FILES ...)` commands, using GNUInstallDirs.
+    Cmake,
+    /// An `install_man()` call.
+    Meson,
+    /// An `install-man` target for a Makefile.
+    Make,
+}
+
+/// A generated man page: the file and its section.
+pub struct InstalledPage {
+    pub file: PathBuf,
+    pub section: u8,
+}
+
+/// Rules that put every page into the `manN` directory of its section.
+/// Makefiles install below `prefix` unless told otherwise.
+pub fn install_snippet(system: BuildSystem, pages: &[InstalledPage], prefix: &Path) -> String {
+    let mut sections: BTreeMap<u8, Vec<String>> = BTreeMap::new();
+    for page in pages {
+        let file = page.file.to_string_lossy().to_string();
+        sections.entry(page.section).or_default().push(file);
+    }
+    let mut out = String::new();
+    match system {
+        BuildSystem::Homebrew => {
+            for (section, files) in &sections {
+                let files = files.iter().map(|f| format!("\"{}\"", f));
+                let files = files.collect::<Vec<_>>().join(", ");
+                out.push_str(&format!("man{}.install {}\n", section, files));
+            }
+        }
+        BuildSystem::Cmake => {
+            out.push_str("include(GNUInstallDirs)\n");
+            for (section, files) in &sections {
+                out.push_str(&format!(
+                    "install(FILES {} DESTINATION ${{CMAKE_INSTALL_MANDIR}}/man{})\n",
+                    files.join(" "),
+                    section
+                ));
+            }
+        }
+        BuildSystem::Meson => {
+            // install_man() takes the section from the file name.
+            let files = sections
+                .values()
+                .flatten()
+                .map(|f| format!("'{}'", f))
+                .collect::<Vec<_>>();
+            out.push_str(&format!("install_man({})\n", files.join(", ")));
+        }
+        BuildSystem::Make => {
+            out.push_str(&format!("PREFIX ?= {}\n", prefix.display()));
+            out.push_str("MANDIR ?= $(PREFIX)/share/man\n\n");
+            out.push_str(".PHONY: install-man\ninstall-man:\n");
+            for (section, files) in &sections {
+                let dir = format!("$(DESTDIR)$(MANDIR)/man{}", section);
+                out.push_str(&format!("\tinstall -d {}\n", dir));
+                out.push_str(&format!("\tinstall -m 644 {} {}\n", files.join(" "), dir));
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_install_snippet() {
+        let pages = [
+            ("man/tool.1", 1),
+            ("man/tool.conf.5", 5),
+            ("man/tool-run.1", 1),
+        ]
+        .map(|(file, section)| InstalledPage {
+            file: file.into(),
+            section,
+        });
+        let prefix = Path::new("/usr/local");
+        let snippet = |system| install_snippet(system, &pages, prefix);
+        assert_eq!(
+            snippet(BuildSystem::Homebrew),
+            "man1.install \"man/tool.1\", \"man/tool-run.1\"\nman5.install \"man/tool.conf.5\"\n"
+        );
+        assert_eq!(
+            snippet(BuildSystem::Cmake),
+            "include(GNUInstallDirs)\n\
+             install(FILES man/tool.1 man/tool-run.1 DESTINATION ${CMAKE_INSTALL_MANDIR}/man1)\n\
+             install(FILES man/tool.conf.5 DESTINATION ${CMAKE_INSTALL_MANDIR}/man5)\n"
+        );
+        assert_eq!(
+            snippet(BuildSystem::Meson),
+            "install_man('man/tool.1', 'man/tool-run.1', 'man/tool.conf.5')\n"
+        );
+        assert_eq!(
+            snippet(BuildSystem::Make),
+            "PREFIX ?= /usr/local\nMANDIR ?= $(PREFIX)/share/man\n\n\
+             .PHONY: install-man\ninstall-man:\n\
+             \tinstall -d $(DESTDIR)$(MANDIR)/man1\n\
+             \tinstall -m 644 man/tool.1 man/tool-run.1 $(DESTDIR)$(MANDIR)/man1\n\
+             \tinstall -d $(DESTDIR)$(MANDIR)/man5\n\
+             \tinstall -m 644 man/tool.conf.5 $(DESTDIR)$(MANDIR)/man5\n"
+        );
+    }
+}
@@ -0,0 +1,313 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use mdman::man_node::{ManNode, TitleLine};
+use mdman::options::{OptionSpec, options_definition_list};
+
+/// Description of a command and its subcommands, used to generate a family of
+/// pages: `tool.N` plus `tool-subcmd.N` for every subcommand.
+#[derive(Debug, Deserialize)]
+pub struct SuiteSpec {
+    pub name: String,
+    #[serde(default = "default_section")]
+    pub section: u8,
+    pub date: Option<String>,
+    #[serde(alias = "left-footer")]
+    pub left_footer: Option<String>,
+    #[serde(alias = "center-footer")]
+    pub center_footer: Option<String>,
+    #[serde(default)]
+    pub description: String,
+    pub details: Option<String>,
+    #[serde(default)]
+    pub options: Vec<OptionSpec>,
+    #[serde(default)]
+    pub subcommands: Vec<SubcommandSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SubcommandSpec {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub details: Option<String>,
+    #[serde(default)]
+    pub options: Vec<OptionSpec>,
+}
+
+fn default_section() -> u8 {
+    1
+}
+
+/// A generated page: its name (without section) and content.
+pub struct SuitePage {
+    pub name: String,
+    pub nodes: Vec<ManNode>,
+}
+
+#[derive(Debug)]
+pub enum SuiteSpecError {
+    ReadError(String, io::Error),
+    ParseError(String, serde_yaml::Error),
+}
+
+impl fmt::Display for SuiteSpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SuiteSpecError::ReadError(file, e) => {
+                write!(f, "mdman: Could not read suite spec {}. Error: {}", file, e)
+            }
+            SuiteSpecError::ParseError(file, e) => {
+                write!(
+                    f,
+                    "mdman: Could not parse suite spec {}. Error: {}",
+                    file, e
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for SuiteSpecError {}
+
+pub fn load_suite_spec(path: &Path) -> Result<SuiteSpec, SuiteSpecError> {
+    let file = path.to_string_lossy().to_string();
+    let content =
+        fs::read_to_string(path).map_err(|e| SuiteSpecError::ReadError(file.clone(), e))?;
+    serde_yaml::from_str(&content).map_err(|e| SuiteSpecError::ParseError(file, e))
+}
+
+pub fn suite_pages(spec: &SuiteSpec) -> Vec<SuitePage> {
+    let sub_names = spec
+        .subcommands
+        .iter()
+        .map(|sub| format!("{}-{}", spec.name, sub.name))
+        .collect::<Vec<_>>();
+
+    let mut main_nodes = vec![title_line(spec, &spec.name)];
+    main_nodes.extend(name_section(&spec.name, &spec.description));
+    let mut synopsis = vec![
+        ManNode::bold(spec.name.clone()),
+        ManNode::Text(" [".into()),
+        ManNode::italic("OPTIONS"),
+        ManNode::Text("]".into()),
+    ];
+    if !spec.subcommands.is_empty() {
+        synopsis.push(ManNode::Text(" ".into()));
+        synopsis.push(ManNode::italic("COMMAND"));
+    }
+    main_nodes.extend(section("SYNOPSIS", synopsis));
+    main_nodes.extend(description_section(&spec.description, &spec.details));
+    main_nodes.extend(options_section(&spec.options));
+    if !spec.subcommands.is_empty() {
+        let items = spec
+            .subcommands
+            .iter()
+            .zip(&sub_names)
+            .map(|(sub, page)| {
+                ManNode::list_item(vec![
+                    ManNode::bold(sub.name.clone()),
+                    ManNode::Text(format!(
+                        "\n{}See {}({}).",
+                        sentence(&sub.description),
+                        page,
+                        spec.section
+                    )),
+                ])
+            })
+            .collect();
+        main_nodes.push(heading("COMMANDS"));
+        main_nodes.push(ManNode::DefinitionList { children: items });
+    }
+    main_nodes.extend(see_also(&sub_names, spec.section));
+
+    let mut pages = vec![SuitePage {
+        name: spec.name.clone(),
+        nodes: main_nodes,
+    }];
+
+    for (sub, page) in spec.subcommands.iter().zip(&sub_names) {
+        let mut nodes = vec![title_line(spec, page)];
+        nodes.extend(name_section(page, &sub.description));
+        nodes.extend(section(
+            "SYNOPSIS",
+            vec![
+                ManNode::bold(format!("{} {}", spec.name, sub.name)),
+                ManNode::Text(" [".into()),
+                ManNode::italic("OPTIONS"),
+                ManNode::Text("]".into()),
+            ],
+        ));
+        nodes.extend(description_section(&sub.description, &sub.details));
+        nodes.extend(options_section(&sub.options));
+
+        let related = std::iter::once(spec.name.clone())
+            .chain(sub_names.iter().filter(|&other| other != page).cloned())
+            .collect::<Vec<_>>();
+        nodes.extend(see_also(&related, spec.section));
+
+        pages.push(SuitePage {
+            name: page.clone(),
+            nodes,
+        });
+    }
+
+    // Written in a fixed order, whatever the order in the spec.
+    pages.sort_by(|a, b| a.name.cmp(&b.name));
+    pages
+}
+
+fn title_line(spec: &SuiteSpec, name: &str) -> ManNode {
+    ManNode::TitleLine(TitleLine {
+        name: name.to_string(),
+        section: spec.section,
+        date: spec.date.clone(),
+        left_footer: spec.left_footer.clone(),
+        center_footer: spec.center_footer.clone(),
+        options_file: None,
+        lang: None,
+        table_style: None,
+        version: None,
+    })
+}
+
+fn heading(title: &str) -> ManNode {
+    ManNode::SectionHeading {
+        title: title.to_string(),
+        children: vec![],
+    }
+}
+
+fn section(title: &str, inlines: Vec<ManNode>) -> Vec<ManNode> {
+    vec![heading(title), ManNode::Paragraph { children: inlines }]
+}
+
+/// A description as a sentence followed by a space, so that more can follow
+/// it, or nothing if there is no description.
+fn sentence(description: &str) -> String {
+    match description.trim() {
+        "" => String::new(),
+        text if text.ends_with(['.', '!', '?']) => format!("{} ", text),
+        text => format!("{}. ", text),
+    }
+}
+
+fn name_section(name: &str, description: &str) -> Vec<ManNode> {
+    section(
+        "NAME",
+        vec![
+            ManNode::bold(name),
+            ManNode::Text(format!(" - {}", description.trim())),
+        ],
+    )
+}
+
+fn description_section(description: &str, details: &Option<String>) -> Vec<ManNode> {
+    let text = details.as_deref().unwrap_or(description).trim();
+    if text.is_empty() {
+        return vec![];
+    }
+    let mut nodes = vec![heading("DESCRIPTION")];
+    for paragraph in text.split("\n\n") {
+        nodes.push(ManNode::Paragraph {
+            children: vec![ManNode::Text(paragraph.trim().to_string())],
+        });
+    }
+    nodes
+}
+
+fn options_section(options: &[OptionSpec]) -> Vec<ManNode> {
+    if options.is_empty() {
+        return vec![];
+    }
+    vec![heading("OPTIONS"), options_definition_list(options)]
+}
+
+/// SEE ALSO references, sorted by name as man-pages(7) recommends.
+fn see_also(pages: &[String], section_number: u8) -> Vec<ManNode> {
+    if pages.is_empty() {
+        return vec![];
+    }
+    let mut pages = pages.to_vec();
+    pages.sort();
+    pages.dedup();
+    let refs = pages
+        .iter()
+        .map(|p| format!("{}({})", p, section_number))
+        .collect::<Vec<_>>()
+        .join(", ");
+    section("SEE ALSO", vec![ManNode::Text(refs)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mdman::roff::ToRoff;
+
+    const SPEC: &str = r#"
+name: tool
+date: 2025-01-01
+description: do things
+options:
+  - name: verbose
+    short: v
+    description: Be verbose.
+subcommands:
+  - name: build
+    description: build things
+  - name: clean
+    description: remove things
+"#;
+
+    fn render(page: &SuitePage) -> String {
+        page.nodes.iter().map(|n| n.to_roff()).collect()
+    }
+
+    #[test]
+    fn test_page_names() {
+        let spec: SuiteSpec = serde_yaml::from_str(SPEC).unwrap();
+        let pages = suite_pages(&spec);
+        let names = pages.iter().map(|p| p.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["tool", "tool-build", "tool-clean"]);
+    }
+
+    #[test]
+    fn test_main_page_links_subcommands() {
+        let spec: SuiteSpec = serde_yaml::from_str(SPEC).unwrap();
+        let roff = render(&suite_pages(&spec)[0]);
+        assert!(roff.starts_with(".TH \"TOOL\" \"1\" \"2025-01-01\"\n"));
+        assert!(roff.contains(
+            ".SH COMMANDS\n.TP\n\\fBbuild\\fP\nbuild things\\&. See tool\\-build(1)\\&.\n"
+        ));
+        assert!(roff.contains(".SH SEE ALSO\n.PD\n.PP\ntool\\-build(1), tool\\-clean(1)\n"));
+    }
+
+    #[test]
+    fn test_pages_sorted_by_name() {
+        let spec: SuiteSpec = serde_yaml::from_str(
+            "name: tool\nsubcommands:\n  - name: zap\n  - name: add\n  - name: mid\n",
+        )
+        .unwrap();
+        let pages = suite_pages(&spec);
+        let names = pages.iter().map(|p| p.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["tool", "tool-add", "tool-mid", "tool-zap"]);
+        let roff = render(&pages[0]);
+        assert!(roff.contains("\ntool\\-add(1), tool\\-mid(1), tool\\-zap(1)\n"));
+        assert!(roff.contains(".TP\n\\fBadd\\fP\nSee tool\\-add(1)\\&.\n"));
+        let roff = render(&pages[3]);
+        assert!(roff.contains("\ntool(1), tool\\-add(1), tool\\-mid(1)\n"));
+    }
+
+    #[test]
+    fn test_subcommand_page_links_siblings() {
+        let spec: SuiteSpec = serde_yaml::from_str(SPEC).unwrap();
+        let roff = render(&suite_pages(&spec)[1]);
+        assert!(roff.contains(".SH NAME\n.PD\n.PP\n\\fBtool\\-build\\fP \\- build things\n"));
+        assert!(roff.contains(".SH SEE ALSO\n.PD\n.PP\ntool(1), tool\\-clean(1)\n"));
+        assert!(!roff.contains(".SH OPTIONS"));
+    }
+}
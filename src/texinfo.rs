@@ -0,0 +1,344 @@
+use crate::man_node::{ManNode, title_line};
+use crate::render::Renderer;
+
+/// Render a page as a Texinfo manual: every section becomes a chapter and
+/// every subsection a section, each with a node of its own, so that
+/// `makeinfo` can build an info manual from the same source as the man page.
+pub fn texinfo_page(nodes: &[ManNode]) -> String {
+    let title = title_line(nodes);
+    let name = title.map_or("manual", |t| t.name.as_str());
+
+    let mut out = String::from("\\input texinfo\n");
+    out.push_str(&format!("@setfilename {}.info\n", name));
+    match title {
+        Some(t) => out.push_str(&format!("@settitle {}({})\n", escape(name), t.section)),
+        None => out.push_str(&format!("@settitle {}\n", escape(name))),
+    }
+    if let Some(lang) = title.and_then(|t| t.lang.as_deref()) {
+        out.push_str(&format!("@documentlanguage {}\n", lang));
+    }
+    if let Some(date) = title.and_then(|t| t.date.as_deref()) {
+        out.push_str(&format!("@set UPDATED {}\n", escape(date)));
+    }
+
+    let outline = outline(nodes);
+    out.push_str(&format!("\n@node Top\n@top {}\n", escape(name)));
+    menu(&mut out, outline.iter().map(|chapter| &chapter.node));
+
+    let mut chapters = outline.iter();
+    let mut sections = [].iter();
+    for node in nodes {
+        match node {
+            ManNode::TitleLine(_) => {}
+            ManNode::SectionHeading { title, .. } => {
+                let chapter = chapters.next().unwrap();
+                out.push_str(&format!(
+                    "\n@node {}\n@chapter {}\n",
+                    chapter.node,
+                    escape(title)
+                ));
+                menu(&mut out, chapter.sections.iter());
+                sections = chapter.sections.iter();
+            }
+            ManNode::SubsectionHeading { title, .. } => {
+                // Subsections before the first section have no chapter to go in.
+                match sections.next() {
+                    Some(section) => out.push_str(&format!(
+                        "\n@node {}\n@section {}\n",
+                        section,
+                        escape(title)
+                    )),
+                    None => out.push_str(&format!("\n@heading {}\n", escape(title))),
+                }
+            }
+            // Without nodes of their own, so the menus stay as they are.
+            ManNode::MinorHeading { level, title, .. } => {
+                let command = if *level == 3 {
+                    "subheading"
+                } else {
+                    "subsubheading"
+                };
+                out.push_str(&format!("\n@{} {}\n", command, escape(title)));
+            }
+            block => write_block(&mut out, block),
+        }
+    }
+    out.push_str("\n@bye\n");
+    out
+}
+
+/// The node names of the chapters and their sections.
+struct Chapter {
+    node: String,
+    sections: Vec<String>,
+}
+
+fn outline(nodes: &[ManNode]) -> Vec<Chapter> {
+    let mut chapters: Vec<Chapter> = Vec::new();
+    let mut used = vec!["Top".to_string()];
+    for node in nodes {
+        match node {
+            ManNode::SectionHeading { title, .. } => {
+                let name = unique_node_name(title, None, &mut used);
+                chapters.push(Chapter {
+                    node: name,
+                    sections: vec![],
+                });
+            }
+            ManNode::SubsectionHeading { title, .. } => {
+                if let Some(chapter) = chapters.last_mut() {
+                    let name = unique_node_name(title, Some(&chapter.node), &mut used);
+                    chapter.sections.push(name);
+                }
+            }
+            _ => {}
+        }
+    }
+    chapters
+}
+
+/// Node names have to be unique and must not contain `,`, `:` or `.`, which
+/// separate the parts of menu entries and cross references.
+fn unique_node_name(title: &str, chapter: Option<&str>, used: &mut Vec<String>) -> String {
+    let clean = |s: &str| {
+        let s = s.replace([',', ':', '.', '(', ')'], " ");
+        escape(&s.split_whitespace().collect::<Vec<_>>().join(" "))
+    };
+    let mut name = clean(title);
+    if let Some(chapter) = chapter
+        && used.contains(&name)
+    {
+        name = format!("{} in {}", name, chapter);
+    }
+    let base = name.clone();
+    let mut n = 2;
+    while used.contains(&name) {
+        name = format!("{} {}", base, n);
+        n += 1;
+    }
+    used.push(name.clone());
+    name
+}
+
+fn menu<'a>(out: &mut String, entries: impl Iterator<Item = &'a String>) {
+    let entries = entries.map(|e| format!("* {}::\n", e)).collect::<String>();
+    if !entries.is_empty() {
+        out.push_str(&format!("\n@menu\n{}@end menu\n", entries));
+    }
+}
+
+fn write_block(out: &mut String, node: &ManNode) {
+    match node {
+        ManNode::Paragraph { children } => {
+            out.push('\n');
+            write_inlines(out, children);
+            end_line(out);
+        }
+        ManNode::CodeBlock { code, .. } | ManNode::Equation(code) => {
+            out.push_str("\n@example\n");
+            out.push_str(&escape(code));
+            end_line(out);
+            out.push_str("@end example\n");
+        }
+        ManNode::NoFill(text) => {
+            out.push_str("\n@format\n");
+            out.push_str(&escape(text));
+            end_line(out);
+            out.push_str("@end format\n");
+        }
+        ManNode::Pic(_) => {
+            // There is no pic in Texinfo; leave a trace for whoever reads the source.
+            out.push_str("\n@c pic diagram omitted\n");
+        }
+        ManNode::RawBlock(_) => out.push_str("\n@c raw roff omitted\n"),
+        ManNode::BulletList { children } => {
+            list(out, "itemize @bullet", "itemize", children);
+        }
+        ManNode::NumberedList { children } => {
+            list(out, "enumerate", "enumerate", children);
+        }
+        ManNode::DefinitionList { children } => {
+            out.push_str("\n@table @asis\n");
+            for item in children {
+                let mut text = String::new();
+                write_inlines(&mut text, item.children());
+                let (term, description) = text.split_once('\n').unwrap_or((&text, ""));
+                out.push_str(&format!("@item {}\n", term.trim()));
+                let description = description.trim();
+                if !description.is_empty() {
+                    out.push_str(description);
+                    out.push('\n');
+                }
+            }
+            out.push_str("@end table\n");
+        }
+        ManNode::Table {
+            align, children, ..
+        } => {
+            let fraction = format!(" {:.2}", 1.0 / align.len().max(1) as f64);
+            out.push_str(&format!(
+                "\n@multitable @columnfractions{}\n",
+                fraction.repeat(align.len())
+            ));
+            for (i, row) in children.iter().enumerate() {
+                out.push_str(if i == 0 { "@headitem " } else { "@item " });
+                let cells = row
+                    .children()
+                    .iter()
+                    .map(|cell| {
+                        let mut text = String::new();
+                        write_inlines(&mut text, cell.children());
+                        text
+                    })
+                    .collect::<Vec<_>>();
+                out.push_str(&cells.join(" @tab "));
+                out.push('\n');
+            }
+            out.push_str("@end multitable\n");
+        }
+        ManNode::Blockquote { children } => {
+            out.push_str("\n@quotation\n");
+            for child in children {
+                write_block(out, child);
+            }
+            out.push_str("@end quotation\n");
+        }
+        inline => {
+            write_inlines(out, std::slice::from_ref(inline));
+            end_line(out);
+        }
+    }
+}
+
+fn list(out: &mut String, start: &str, end: &str, items: &[ManNode]) {
+    out.push_str(&format!("\n@{}\n", start));
+    for item in items {
+        out.push_str("@item\n");
+        match item.checked() {
+            Some(true) => out.push_str("[x] "),
+            Some(false) => out.push_str("[ ] "),
+            None => {}
+        }
+        write_inlines(out, item.children());
+        end_line(out);
+    }
+    out.push_str(&format!("@end {}\n", end));
+}
+
+/// Inline nodes as written; blocks nested in list items get lines of their own.
+fn write_inlines(out: &mut String, children: &[ManNode]) {
+    for child in children {
+        match child {
+            ManNode::Text(text) => out.push_str(&escape(text)),
+            ManNode::SoftBreak => out.push('\n'),
+            ManNode::LineBreak => out.push_str("@*\n"),
+            ManNode::Bold(children) => command(out, "strong", children),
+            ManNode::Italic(children) => command(out, "emph", children),
+            // Texinfo has no strike-through either; see the roff output.
+            ManNode::Strikethrough(children) => {
+                command(out, "emph", children);
+                out.push_str(" (deprecated)");
+            }
+            ManNode::InlineCode(text) => out.push_str(&format!("@code{{{}}}", escape(text))),
+            // Roff means nothing to makeinfo.
+            ManNode::RawInline(_) => {}
+            ManNode::ManRef { name, section } => {
+                out.push_str(&format!("@strong{{{}}}({})", escape(name), section));
+            }
+            ManNode::Uri { url, children, .. } => {
+                let mut text = String::new();
+                write_inlines(&mut text, children);
+                if let Some(address) = url.strip_prefix("mailto:") {
+                    let address = escape(address).replace(',', "@comma{}");
+                    if text == address {
+                        out.push_str(&format!("@email{{{}}}", address));
+                    } else {
+                        let text = text.replace(',', "@comma{}");
+                        out.push_str(&format!("@email{{{}, {}}}", address, text));
+                    }
+                    continue;
+                }
+                let url = escape(url).replace(',', "@comma{}");
+                if text == url {
+                    out.push_str(&format!("@uref{{{}}}", url));
+                } else {
+                    let text = text.replace(',', "@comma{}");
+                    out.push_str(&format!("@uref{{{}, {}}}", url, text));
+                }
+            }
+            ManNode::ListItem { children, .. } => write_inlines(out, children),
+            block => {
+                end_line(out);
+                write_block(out, block);
+            }
+        }
+    }
+}
+
+/// `@name{...}` around the rendered children.
+fn command(out: &mut String, name: &str, children: &[ManNode]) {
+    out.push_str(&format!("@{}{{", name));
+    write_inlines(out, children);
+    out.push('}');
+}
+
+fn end_line(out: &mut String) {
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '@' | '{' | '}') {
+            escaped.push('@');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// [`texinfo_page`] as a [`Renderer`].
+pub struct TexinfoRenderer;
+
+impl Renderer for TexinfoRenderer {
+    fn render(&self, nodes: &[ManNode]) -> String {
+        texinfo_page(nodes)
+    }
+
+    fn extension(&self, _nodes: &[ManNode]) -> String {
+        "texi".into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_page;
+
+    #[test]
+    fn test_texinfo_page() {
+        let markdown = "---\nname: tool\nsection: 1\n---\n\n# NAME\n\n**tool** - do {things}\n\n\
+                        # OPTIONS\n\n## Flags\n\n---\n\n- **-v**\n  Be verbose.\n\n---\n\n\
+                        # EXAMPLES\n\n## Flags\n\n```\ntool -v\n```\n";
+        let (nodes, _) = parse_page(markdown, &None).unwrap();
+        assert_eq!(
+            texinfo_page(&nodes),
+            "\\input texinfo\n@setfilename tool.info\n@settitle tool(1)\n\n\
+             @node Top\n@top tool\n\n@menu\n* NAME::\n* OPTIONS::\n* EXAMPLES::\n@end menu\n\n\
+             @node NAME\n@chapter NAME\n\n@strong{tool} - do @{things@}\n\n\
+             @node OPTIONS\n@chapter OPTIONS\n\n@menu\n* Flags::\n@end menu\n\n\
+             @node Flags\n@section Flags\n\n@table @asis\n@item @strong{-v}\nBe verbose.\n@end table\n\n\
+             @node EXAMPLES\n@chapter EXAMPLES\n\n@menu\n* Flags in EXAMPLES::\n@end menu\n\n\
+             @node Flags in EXAMPLES\n@section Flags\n\n@example\ntool -v\n@end example\n\n@bye\n"
+        );
+    }
+
+    #[test]
+    fn test_node_names() {
+        let mut used = vec!["Top".to_string()];
+        assert_eq!(unique_node_name("a, b: c.", None, &mut used), "a b c");
+        assert_eq!(unique_node_name("Top", None, &mut used), "Top 2");
+    }
+}
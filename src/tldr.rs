@@ -0,0 +1,131 @@
+use crate::man_node::{ManNode, section_range, title_line};
+use crate::render::Renderer;
+
+/// Condense a page into the tldr-pages format: the command name, its
+/// one-line description (from NAME) and synopsis, then every example of the
+/// EXAMPLES section as a description followed by the command.
+pub fn tldr_page(nodes: &[ManNode]) -> String {
+    let name = title_line(nodes)
+        .map(|t| t.name.clone())
+        .unwrap_or_default();
+    let name_line = section_text(nodes, "NAME");
+    let (command, description) = match name_line.split_once(" - ") {
+        Some((command, description)) => (command.trim().to_string(), description.trim()),
+        None => (name, name_line.trim()),
+    };
+
+    let mut page = format!("# {}\n\n", command);
+    if !description.is_empty() {
+        page.push_str(&format!("> {}\n", sentence(description)));
+    }
+    let synopsis = section_text(nodes, "SYNOPSIS");
+    if !synopsis.is_empty() {
+        page.push_str(&format!("> Usage: `{}`.\n", synopsis.trim()));
+    }
+
+    for (description, command) in examples(nodes) {
+        page.push_str(&format!("\n- {}:\n\n`{}`\n", description, command));
+    }
+    page
+}
+
+/// The examples as (description, command) pairs. A description is the text
+/// of the list item or paragraph before a code block.
+fn examples(nodes: &[ManNode]) -> Vec<(String, String)> {
+    let Some(range) = section_range(nodes, "EXAMPLES") else {
+        return vec![];
+    };
+    let mut examples = Vec::new();
+    let mut description = String::new();
+    for node in &nodes[range] {
+        node.walk(&mut |n| match n {
+            ManNode::Paragraph { children } | ManNode::ListItem { children, .. } => {
+                description = inline_markdown(children);
+            }
+            ManNode::CodeBlock { code, .. } => {
+                let command = code
+                    .lines()
+                    .map(|l| l.trim().trim_start_matches("$ "))
+                    .find(|l| !l.is_empty());
+                if let Some(command) = command {
+                    let text = description.trim().trim_end_matches([':', '.']);
+                    examples.push((text.to_string(), command.to_string()));
+                }
+                description.clear();
+            }
+            _ => {}
+        });
+    }
+    examples
+}
+
+fn section_text(nodes: &[ManNode], name: &str) -> String {
+    let Some(range) = section_range(nodes, name) else {
+        return String::new();
+    };
+    nodes[range]
+        .iter()
+        .filter_map(|n| match n {
+            ManNode::Paragraph { .. } => Some(n.plain_text()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Inline nodes as tldr Markdown: code stays in backticks, emphasis is dropped.
+fn inline_markdown(children: &[ManNode]) -> String {
+    let text = children
+        .iter()
+        .map(|n| match n {
+            ManNode::InlineCode(code) => format!("`{}`", code),
+            other => other.plain_text(),
+        })
+        .collect::<String>();
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// tldr descriptions are sentences that start upper case and end with a dot.
+fn sentence(text: &str) -> String {
+    let mut chars = text.chars();
+    let mut sentence = match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+        None => return String::new(),
+    };
+    if !sentence.ends_with('.') {
+        sentence.push('.');
+    }
+    sentence
+}
+
+/// [`tldr_page`] as a [`Renderer`].
+pub struct TldrRenderer;
+
+impl Renderer for TldrRenderer {
+    fn render(&self, nodes: &[ManNode]) -> String {
+        tldr_page(nodes)
+    }
+
+    fn extension(&self, _nodes: &[ManNode]) -> String {
+        "tldr.md".into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_page;
+
+    #[test]
+    fn test_tldr_page() {
+        let markdown = "# NAME\n\n**tool** - do things\n\n# SYNOPSIS\n\n**tool** [_OPTIONS_]\n\n\
+                        # EXAMPLES\n\n- Do the `thing`:\n\n```sh\n$ tool --thing\n```\n\n\
+                        Do nothing.\n\n```\ntool\n```\n";
+        let (nodes, _) = parse_page(markdown, &None).unwrap();
+        assert_eq!(
+            tldr_page(&nodes),
+            "# tool\n\n> Do things.\n> Usage: `tool [OPTIONS]`.\n\n\
+             - Do the `thing`:\n\n`tool --thing`\n\n- Do nothing:\n\n`tool`\n"
+        );
+    }
+}
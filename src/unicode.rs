@@ -0,0 +1,78 @@
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::canonical_combining_class;
+use unicode_width::UnicodeWidthChar;
+
+/// Number of terminal columns `c` occupies: 2 for East Asian wide characters,
+/// 0 for combining marks and format characters, 1 otherwise. Right-to-left
+/// scripts take one column per character like any other narrow text.
+pub fn char_width(c: char) -> usize {
+    if c.is_ascii() {
+        (!c.is_ascii_control()) as usize
+    } else {
+        c.width().unwrap_or(0)
+    }
+}
+
+/// Number of terminal columns `text` occupies on a single line.
+pub fn display_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+pub fn is_combining(c: char) -> bool {
+    canonical_combining_class(c) != 0
+}
+
+/// Normalize `text` to Unicode NFC, so precomposed characters, decomposed
+/// ones (as produced by e.g. macOS tools) and mixes of both come out the same.
+pub fn nfc(text: &str) -> String {
+    if text.is_ascii() {
+        return text.to_string();
+    }
+    text.nfc().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nfc_composes_accents() {
+        assert_eq!(nfc("Cafe\u{301} Zu\u{308}rich"), "Café Zürich");
+        assert_eq!(nfc("A\u{30A}ngstro\u{308}m"), "Ångström");
+        assert_eq!(nfc("already café"), "already café");
+    }
+
+    #[test]
+    fn test_nfc_multiple_marks() {
+        // Dot below (class 220) and circumflex (class 230) both compose onto "a".
+        assert_eq!(nfc("a\u{323}\u{302}"), "\u{1EAD}");
+        // No precomposed "q" with acute exists, so the mark is kept.
+        assert_eq!(nfc("q\u{301}"), "q\u{301}");
+    }
+
+    #[test]
+    fn test_nfc_mixed_and_unordered() {
+        // A precomposed "é" with a dot below still takes the dot first.
+        assert_eq!(nfc("\u{E9}\u{323}"), "\u{1EB9}\u{301}");
+        assert_eq!(nfc("e\u{301}\u{323}"), "\u{1EB9}\u{301}");
+        // Singletons and composition exclusions stay decomposed.
+        assert_eq!(nfc("\u{212B}"), "\u{C5}");
+        assert_eq!(nfc("\u{958}"), "\u{915}\u{93C}");
+        assert_eq!(nfc("한"), "한");
+    }
+
+    #[test]
+    fn test_display_width() {
+        assert_eq!(display_width("mdman"), 5);
+        assert_eq!(display_width("日本語"), 6);
+        assert_eq!(display_width("ｆｕｌｌ"), 8);
+        assert_eq!(display_width("q\u{301}"), 1);
+        assert_eq!(display_width("שלום"), 4);
+        assert_eq!(display_width("a\u{200B}b"), 2);
+    }
+
+    #[test]
+    fn test_nfc_hangul() {
+        assert_eq!(nfc("\u{1112}\u{1161}\u{11AB}"), "한");
+    }
+}
@@ -0,0 +1,195 @@
+use std::fmt;
+use std::io;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use crate::external::{find_executable, run_filter};
+
+/// Maps lines of the generated roff back to the Markdown they came from.
+pub struct SourceMap {
+    /// First roff line (1-based) of every rendered node and its Markdown line.
+    starts: Vec<(usize, Option<usize>)>,
+}
+
+impl SourceMap {
+    /// `preamble` is the text emitted before the first node (e.g. the
+    /// preprocessor line), `chunks` the rendered nodes and `lines` their
+    /// Markdown lines, as returned by [`mdman::parse_page`].
+    pub fn new(preamble: &str, chunks: &[String], lines: &[Option<usize>]) -> Self {
+        let mut next = 1 + preamble.matches('\n').count();
+        let mut starts = Vec::new();
+        for (chunk, line) in chunks.iter().zip(lines) {
+            starts.push((next, *line));
+            next += chunk.matches('\n').count();
+        }
+        Self { starts }
+    }
+
+    /// The Markdown line for a roff line. Nodes without a source (e.g. the
+    /// generated OPTIONS section) are attributed to the closest node before
+    /// them.
+    pub fn markdown_line(&self, roff_line: usize) -> Option<usize> {
+        self.starts
+            .iter()
+            .take_while(|(start, _)| *start <= roff_line)
+            .filter_map(|(_, line)| *line)
+            .last()
+    }
+}
+
+/// A warning or error reported by the linter.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Line in the roff input, if the linter reported one.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+#[derive(Debug)]
+pub enum ValidateError {
+    NoLinter,
+    NoMan,
+    LinterError(String, io::Error),
+}
+
+impl fmt::Display for ValidateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidateError::NoLinter => {
+                write!(f, "mdman: --validate needs mandoc or groff in PATH")
+            }
+            ValidateError::NoMan => {
+                write!(f, "mdman: --lintian-check needs man (man-db) in PATH")
+            }
+            ValidateError::LinterError(linter, e) => {
+                write!(f, "mdman: Could not run {}. Error: {}", linter, e)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidateError {}
+
+/// Linters in order of preference, with the arguments that make them check a
+/// man page on stdin without producing output.
+const LINTERS: &[(&str, &[&str])] = &[
+    ("mandoc", &["-man", "-T", "lint", "-W", "warning"]),
+    ("groff", &["-man", "-ww", "-z"]),
+];
+
+/// Run the roff through the first available linter and collect its messages.
+/// The linter is killed if it runs longer than `timeout`.
+pub fn lint(roff: &str, timeout: Option<Duration>) -> Result<Vec<Diagnostic>, ValidateError> {
+    let (linter, args) = LINTERS
+        .iter()
+        .find(|(name, _)| find_executable(name).is_some())
+        .ok_or(ValidateError::NoLinter)?;
+
+    let output = run_filter(
+        Command::new(linter)
+            .args(*args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped()),
+        roff.as_bytes(),
+        timeout,
+    )
+    .map_err(|e| ValidateError::LinterError(linter.to_string(), e))?;
+
+    // mandoc reports on stdout, groff on stderr.
+    let text = String::from_utf8_lossy(&output.stdout) + String::from_utf8_lossy(&output.stderr);
+    Ok(text.lines().filter_map(parse_diagnostic).collect())
+}
+
+/// Format the roff with `man --warnings` the way lintian does for its
+/// `groff-message` tag, and collect the warnings. The formatted page itself
+/// is thrown away.
+pub fn man_warnings(
+    roff: &str,
+    timeout: Option<Duration>,
+) -> Result<Vec<Diagnostic>, ValidateError> {
+    find_executable("man").ok_or(ValidateError::NoMan)?;
+    let output = run_filter(
+        Command::new("man")
+            .args(["--warnings", "-E", "UTF-8", "-l", "-Tutf8", "-Z", "-"])
+            .env("LC_ALL", "C.UTF-8")
+            .env("MANROFFSEQ", "")
+            .env("MANWIDTH", "80")
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped()),
+        roff.as_bytes(),
+        timeout,
+    )
+    .map_err(|e| ValidateError::LinterError("man".into(), e))?;
+    let text = String::from_utf8_lossy(&output.stderr);
+    Ok(text.lines().filter_map(parse_diagnostic).collect())
+}
+
+/// Parse a line like `mandoc: <stdin>:12:5: WARNING: ...` or
+/// `troff:<standard input>:12: warning: ...`.
+fn parse_diagnostic(line: &str) -> Option<Diagnostic> {
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+    let rest = ["<stdin>:", "<standard input>:"]
+        .iter()
+        .find_map(|input| line.split_once(input).map(|(_, rest)| rest));
+    let Some(rest) = rest else {
+        return Some(Diagnostic {
+            line: None,
+            message: line.to_string(),
+        });
+    };
+
+    let mut fields = rest.splitn(3, ':');
+    let roff_line = fields.next().and_then(|n| n.trim().parse().ok());
+    let mut message = fields.collect::<Vec<_>>();
+    // Drop the column, if any.
+    if message.len() == 2 && message[0].trim().parse::<usize>().is_ok() {
+        message.remove(0);
+    }
+    Some(Diagnostic {
+        line: roff_line,
+        message: message.join(":").trim().to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mandoc() {
+        assert_eq!(
+            parse_diagnostic(
+                "mandoc: <stdin>:12:5: WARNING: skipping paragraph macro: PP after SH"
+            ),
+            Some(Diagnostic {
+                line: Some(12),
+                message: "WARNING: skipping paragraph macro: PP after SH".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_groff() {
+        assert_eq!(
+            parse_diagnostic("troff:<standard input>:7: warning: macro 'XY' not defined"),
+            Some(Diagnostic {
+                line: Some(7),
+                message: "warning: macro 'XY' not defined".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_source_map() {
+        let chunks = [".TH A 1\n", ".SH NAME\n", ".TP\nx\n\n", ".PD\n.PP\ny\n"].map(String::from);
+        let map = SourceMap::new("'\\\" p\n", &chunks, &[Some(1), Some(5), None, Some(9)]);
+        assert_eq!(map.markdown_line(1), None);
+        assert_eq!(map.markdown_line(2), Some(1));
+        assert_eq!(map.markdown_line(3), Some(5));
+        assert_eq!(map.markdown_line(6), Some(5));
+        assert_eq!(map.markdown_line(8), Some(9));
+    }
+}
@@ -1,5 +1,6 @@
 use std::io::Write;
-use std::process::{Command, Stdio};
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
 
 /// Normalize line endings and trim for consistent testing.
 fn normalize(s: &str) -> String {
@@ -190,11 +191,12 @@ becomes
 mdman(1), markdown(7), man(7)
     "#;
 
-    let expected_output = r#".TH "MDMAN" "5" "2025-05-24" "mdman Format" "File Formats"
+    let expected_output = r#"'\" t
+.TH "MDMAN" "5" "2025-05-24" "mdman Format" "File Formats"
 .SH NAME
 .PD
 .PP
-\fBmdman\fP \- Markdown format specification for generating man pages
+\f(CBmdman\fP \- Markdown format specification for generating man pages
 .SH DESCRIPTION
 .PD
 .PP
@@ -215,13 +217,13 @@ GitHub Flavored Markdown for content
 Metadata at the top of the Markdown file must be formatted as a YAML block:
 .EX
 
----
+\-\-\-
 name: mytool
 section: 1
-date: 2025-05-24
-left-footer: MyTool Manual
-center-footer: v1.0
----
+date: 2025\-05\-24
+left\-footer: MyTool Manual
+center\-footer: v1.0
+\-\-\-
 .EE
 .PD
 .PP
@@ -236,44 +238,44 @@ Elements:
 .IP \(bu 2
 \fIdate\fP (optional): Date of last update
 .IP \(bu 2
-\fIleft-footer\fP, \fIcenter-footer\fP (optional): Header/footer strings
+\fIleft\-footer\fP, \fIcenter\-footer\fP (optional): Header/footer strings
 
 .RE
 .SH SUPPORTED ELEMENTS
 .SS Headings
 .PD
 .PP
-\fI#\fP maps to \fI.SH\fP (section),
-\fI##\fP maps to \fI.SS\fP (subsection)
+\fI#\fP maps to \f(CI.SH\fP (section),
+\fI##\fP maps to \f(CI.SS\fP (subsection)
 .SS Paragraphs
 .PD
 .PP
-Plain text separated by a blank line becomes a \fI.PP\fP paragraph\&.
-Indented blocks or triple\-backtick code blocks render as \fI.EX\fP / \fI.EE\fP\&.
+Plain text separated by a blank line becomes a \f(CI.PP\fP paragraph\&.
+Indented blocks or triple\-backtick code blocks render as \f(CI.EX\fP / \f(CI.EE\fP\&.
 .SS Emphasis
 
 .RS 2
 .PD 0
 .IP \(bu 2
-\fC*italic*\fP → \fC\\fI...\\fP\fP → \fIitalic\fP
+\fC*italic*\fP → \fC\\\\fI...\\\\fP\fP → \fIitalic\fP
 .IP \(bu 2
-\fC**bold**\fP → \fC\\fB...\\fP\fP → \fBblod\fP
+\fC**bold**\fP → \fC\\\\fB...\\\\fP\fP → \fBblod\fP
 .IP \(bu 2
-\fCinline\fP   → \fC\\fC\fP\&.\&.\&.\fC\\fP\fP → \fCinline\fP
+\fCinline\fP   → \fC\\\\fC\fP\&.\&.\&.\fC\\\\fP\fP → \fCinline\fP
 
 .RE
 .SS Lists
 .PD
 .PP
-Unordered lists use \fI-\fP and becoome \fI.IP \\(bu\fP\&.
-Ordered lists use \fIN.\fP and become \fI.IP N.\fP, e\&.g\&.:
+Unordered lists use \f(CI\-\fP and becoome \f(CI.IP \\\\(bu\fP\&.
+Ordered lists use \f(CIN.\fP and become \f(CI.IP N.\fP, e\&.g\&.:
 .EX
 
-- one
-- two
-    - sub 1
-    - sub 2
-- three
+\- one
+\- two
+    \- sub 1
+    \- sub 2
+\- three
 .EE
 .PD
 .PP
@@ -335,30 +337,30 @@ sub third
 .SS Thematic Break
 .PD
 .PP
-Thematic breaks (\fC---\fP) mark the start and the end of a definition list, e\&.g\&.:
+Thematic breaks (\fC\-\-\-\fP) mark the start and the end of a definition list, e\&.g\&.:
 .EX
 
 # OPTIONS
 
----
+\-\-\-
 
-- **-h**, **--help**
+\- **\-h**, **\-\-help**
   Print help message
 
-- **-v**, **--verbose**
+\- **\-v**, **\-\-verbose**
   Enter verbose mode
 
----
+\-\-\-
 .EE
 .PD
 .PP
 becomes
 .TP
-\fB-h\fP, \fB--help\fP
+\fB\-h\fP, \fB\-\-help\fP
 Print help message
 
 .TP
-\fB-v\fP, \fB--verbose\fP
+\fB\-v\fP, \fB\-\-verbose\fP
 Enter verbose mode
 
 .SS Tables
@@ -368,7 +370,7 @@ Tables are written using GitHub\-Flavored Markdown syntax:
 .EX
 
 | Column A | Column B | Column C |
-|:-------- |:--------:| --------:|
+|:\-\-\-\-\-\-\-\- |:\-\-\-\-\-\-\-\-:| \-\-\-\-\-\-\-\-:|
 | left     | center   | right    |
 .EE
 .PD
@@ -376,6 +378,7 @@ Tables are written using GitHub\-Flavored Markdown syntax:
 The result looks like this:
 .TS
 allbox;
+lB cB rB
 l c r.
 T{
 Column A
@@ -399,17 +402,17 @@ Column alignments are respected:
 .RS 2
 .PD 0
 .IP \(bu 2
-\fI:---\fP  = left\-aligned
+\f(CI:\-\-\-\fP  = left\-aligned
 .IP \(bu 2
-\fI:---:\fP = center\-aligned
+\f(CI:\-\-\-:\fP = center\-aligned
 .IP \(bu 2
-\fI---:\fP  = right\-aligned
+\f(CI\-\-\-:\fP  = right\-aligned
 
 .RE
 .PD
 .PP
-These are rendered using the roff \fI.TS\fP/\fI.TE\fP macros with allbox for boxed
-tables\&. Each cell is wrapped in \fIT{ ... T}\fP for multi\-line content\&.
+These are rendered using the roff \f(CI.TS\fP/\f(CI.TE\fP macros with allbox for boxed
+tables\&. Each cell is wrapped in \f(CIT{ ... T}\fP for multi\-line content\&.
 .PD
 .PP
 Note:
@@ -427,7 +430,7 @@ Long cell content is supported but not automatically wrapped\&.
 .SS Links
 .PD
 .PP
-Markdown links in the form \fC[text](url)\fP are rendered using \fI.UR\fP / \fI.UE\fP blocks\&.
+Markdown links in the form \fC[text](url)\fP are rendered using \f(CI.UR\fP / \f(CI.UE\fP blocks\&.
 E\&.g\&.:
 .PD
 .PP
@@ -437,15 +440,15 @@ E\&.g\&.:
 becomes
 .PD
 .PP
-
 .UR https://github.com/matkrin/mdman
 mdman on Github
 .UE
-
 .SH SEE ALSO
 .PD
 .PP
-mdman(1), markdown(7), man(7)
+.BR mdman (1),
+.BR markdown (7),
+.BR man (7)
     "#;
 
     let mut child = Command::new(env!("CARGO_BIN_EXE_mdman"))
@@ -474,6 +477,1165 @@ mdman(1), markdown(7), man(7)
     );
 }
 
+#[test]
+fn test_verbose_version_lists_formats_and_formatters() {
+    let output = Command::new(env!("CARGO_BIN_EXE_mdman"))
+        .args(["--version", "--verbose"])
+        .output()
+        .expect("Failed to run mdman");
+    let stdout = String::from_utf8(output.stdout).expect("Invalid UTF-8");
+
+    assert!(output.status.success());
+    assert!(stdout.starts_with(&format!("mdman {}\n", env!("CARGO_PKG_VERSION"))));
+    assert!(stdout.contains("\nformats: man"));
+    assert!(stdout.contains("\n  mandoc: "));
+}
+
+/// Run mdman on `markdown` with `args` and return its stdout.
+fn run_mdman(args: &[&str], markdown: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_mdman"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn mdman");
+    child
+        .stdin
+        .as_mut()
+        .unwrap()
+        .write_all(markdown.as_bytes())
+        .expect("Failed to write input");
+    let output = child.wait_with_output().expect("Failed to read output");
+    String::from_utf8(output.stdout).expect("Invalid UTF-8")
+}
+
+/// A page with frontmatter for tests that need a file to convert.
+const TOOL_PAGE: &str =
+    "---\nname: tool\nsection: 1\ndate: 2025-01-01\n---\n\n# NAME\n\ntool - does one thing\n";
 
+/// A temporary directory with files in it to run mdman in. It is removed
+/// when dropped, so also when an assertion fails.
+struct Project {
+    dir: PathBuf,
+}
+
+impl Project {
+    /// A new directory for the test `name` with `files`, each a path relative
+    /// to the directory and its content.
+    fn new(name: &str, files: &[(&str, &str)]) -> Project {
+        let dir = std::env::temp_dir().join(format!("mdman-test-{}-{}", name, std::process::id()));
+        // Left over from an earlier run that was killed.
+        _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let project = Project { dir };
+        for (path, content) in files {
+            project.write(path, content);
+        }
+        project
+    }
+
+    fn path(&self, path: &str) -> PathBuf {
+        self.dir.join(path)
+    }
+
+    fn write(&self, path: &str, content: impl AsRef<[u8]>) {
+        let path = self.path(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    fn read(&self, path: &str) -> String {
+        std::fs::read_to_string(self.path(path)).unwrap_or_default()
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.path(path).is_file()
+    }
+
+    /// mdman, to be run in the directory.
+    fn command(&self) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_mdman"));
+        command.current_dir(&self.dir);
+        command
+    }
+
+    /// Run mdman with `args` in the directory.
+    fn mdman(&self, args: &[&str]) -> Output {
+        self.command()
+            .args(args)
+            .output()
+            .expect("Failed to run mdman")
+    }
+}
+
+impl Drop for Project {
+    fn drop(&mut self) {
+        _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[test]
+fn test_alternating_fonts_in_option_syntax() {
+    let markdown_input = r#"# OPTIONS
+
+---
+
+- **-o** *FILE*, **--output**=*FILE*
+  Write to *FILE*.
+
+---
+
+**--file**=*PATH*[,*MORE*]
+"#;
+
+    let expected_output = r#".SH OPTIONS
+.TP
+\fB\-o\fP \fIFILE\fP, \fB\-\-output\fP=\fIFILE\fP
+Write to \fIFILE\fP\&.
+
+.PD
+.PP
+\fB\-\-file\fP=\fIPATH\fP[,\fIMORE\fP]"#;
 
+    let stdout = run_mdman(&["--stdout"], markdown_input);
+    assert_eq!(normalize(&stdout), normalize(expected_output));
+}
+
+#[test]
+fn test_inline_adjacency_corpus() {
+    let cases = [
+        ("**bold**, then", "\\fBbold\\fP, then"),
+        ("`code`word", "\\fCcode\\fPword"),
+        ("word`code`", "word\\fCcode\\fP"),
+        ("*a*_b_ **c**", "\\fIa\\fP\\fIb\\fP \\fBc\\fP"),
+        (
+            "see [x](http://u), then",
+            "see\n.UR http://u\nx\n.UE ,\nthen",
+        ),
+        (
+            "see [x](http://u) for more",
+            "see\n.UR http://u\nx\n.UE\nfor more",
+        ),
+        ("[x](http://u) starts", ".UR http://u\nx\n.UE\nstarts"),
+        ("ends [x](http://u).", "ends\n.UR http://u\nx\n.UE \\&."),
+        (
+            "[x](http://u)\nnext line",
+            ".UR http://u\nx\n.UE\nnext line",
+        ),
+    ];
+
+    for (markdown_input, expected_body) in cases {
+        let stdout = run_mdman(&["--stdout"], markdown_input);
+        let expected = format!(".PD\n.PP\n{}", expected_body);
+        assert_eq!(
+            normalize(&stdout),
+            expected,
+            "Unexpected output for {:?}",
+            markdown_input
+        );
+    }
+}
+
+#[test]
+fn test_list_item_paragraphs_stay_apart() {
+    let stdout = run_mdman(&["--stdout"], "- first\n\n  second\n");
+    assert!(stdout.contains(".IP \\(bu 2\nfirst\nsecond\n"));
+}
+
+#[test]
+fn test_soft_breaks_join_keeps_definition_terms() {
+    let markdown_input = "one\ntwo\n\n---\n\n- **-v**\n  Enter verbose\n  mode\n\n---\n";
+    let stdout = run_mdman(&["--stdout", "--soft-breaks", "join"], markdown_input);
+    assert!(stdout.contains(".PP\none two\n"));
+    assert!(stdout.contains(".TP\n\\fB\\-v\\fP\nEnter verbose mode\n"));
+}
+
+#[test]
+fn test_markdown_backslash_escapes() {
+    let markdown_input = r#"\*star\* \_u\_ \[b\] \\ **a\\b** `\fB` *\#*
 
+# A \\ B
+
+```
+printf 'a\n'
+.hidden
+```
+"#;
+
+    let expected_output = r#".PD
+.PP
+*star* _u_ [b] \\ \fBa\\b\fP \fC\\fB\fP \fI#\fP
+.SH A \\ B
+.EX
+printf 'a\\n'
+\&.hidden
+.EE"#;
+
+    let stdout = run_mdman(&["--stdout"], markdown_input);
+    assert_eq!(normalize(&stdout), normalize(expected_output));
+}
+
+#[test]
+fn test_corpus_reports_mismatches() {
+    let markdown = "---\nname: tool\nsection: 1\ndate: 2025-01-01\n---\n\n# NAME\n\ntool\n";
+    let project = Project::new(
+        "corpus",
+        &[
+            ("tool.md", markdown),
+            ("tool.expected.1", &run_mdman(&["--stdout"], markdown)),
+        ],
+    );
+    let passing = project.mdman(&["test-corpus", "."]);
+    project.write("tool.md", markdown.replace("\ntool\n", "\nother\n"));
+    let failing = project.mdman(&["test-corpus", "."]);
+
+    assert!(passing.status.success());
+    assert!(!failing.status.success());
+    let stdout = String::from_utf8(failing.stdout).expect("Invalid UTF-8");
+    assert!(stdout.contains("    5 -tool\n    5 +other\n"));
+    assert!(stdout.ends_with("0 passed, 1 failed, 0 skipped\n"));
+}
+
+#[test]
+fn test_section_only() {
+    let markdown = "# NAME\n\ntool\n\n# OPTIONS\n\n## Flags\n\nnone\n\n# SEE ALSO\n\nls(1)\n";
+    let stdout = run_mdman(&["--section-only", "options"], markdown);
+    assert_eq!(stdout, ".SH OPTIONS\n.SS Flags\n.PD\n.PP\nnone\n");
+}
+
+#[test]
+fn test_export_options_json() {
+    let markdown =
+        "# OPTIONS\n\n---\n\n- **-o**, **--output** _FILE_\n  Write to \"FILE\".\n\n---\n";
+    let stdout = run_mdman(&["--export-options", "-"], markdown);
+    assert_eq!(
+        stdout,
+        r#"[
+  {
+    "short": "-o",
+    "long": "--output",
+    "arg": "FILE",
+    "description": "Write to \"FILE\"."
+  }
+]
+"#
+    );
+}
+
+#[test]
+fn test_format_tldr() {
+    let markdown =
+        "# NAME\n\ntool - do things\n\n# EXAMPLES\n\nList them:\n\n```\n$ tool -l\n```\n";
+    let stdout = run_mdman(&["--format", "tldr"], markdown);
+    assert_eq!(
+        stdout,
+        "# tool\n\n> Do things.\n\n- List them:\n\n`tool -l`\n"
+    );
+}
+
+#[test]
+fn test_format_html_accessibility_warnings() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_mdman"))
+        .args(["--format", "html", "--stdout"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to run mdman");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"# NAME\n\ntool\n\n### Deep\n\nSee [here](https://example.com).\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(output.status.success());
+    assert!(stdout.contains("<nav aria-label=\"Contents\">\n"));
+    assert!(stdout.contains("<section aria-labelledby=\"deep\">\n<h4 id=\"deep\">Deep</h4>\n"));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "mdman: warning: <stdin>:5: heading \"Deep\" is level 3 but the one before it is level 1\n\
+         mdman: warning: <stdin>:7: link text \"here\" does not say where it leads\n"
+    );
+}
+
+#[test]
+fn test_format_html_links_pages_of_the_batch() {
+    let project = Project::new(
+        "html-links",
+        &[
+            (
+                "tool.md",
+                &format!("{TOOL_PAGE}\n# SEE ALSO\n\ntool.conf(5), ls(1)\n"),
+            ),
+            (
+                "conf.md",
+                "---\nname: tool.conf\nsection: 5\n---\n\n# SEE ALSO\n\ntool(1)\n",
+            ),
+        ],
+    );
+    let output = project.mdman(&["--format", "html", "tool.md", "conf.md"]);
+
+    assert!(output.status.success());
+    assert!(project.read("tool.1.html").contains(
+        "<p><a href=\"conf.5.html\"><strong>tool.conf</strong>(5)</a>, <strong>ls</strong>(1)</p>\n"
+    ));
+    assert!(
+        project
+            .read("conf.5.html")
+            .contains("<p><a href=\"tool.1.html\"><strong>tool</strong>(1)</a></p>\n")
+    );
+}
+
+#[test]
+fn test_format_html_single_file() {
+    let project = Project::new(
+        "html-single-file",
+        &[
+            (
+                "tool.md",
+                &format!("{TOOL_PAGE}\n# SEE ALSO\n\ntool.conf(5)\n"),
+            ),
+            (
+                "conf.md",
+                "---\nname: tool.conf\nsection: 5\n---\n\n# SEE ALSO\n\ntool(1)\n",
+            ),
+        ],
+    );
+    let output = project.mdman(&["--format", "html", "--single-file", "tool.md", "conf.md"]);
+
+    assert!(output.status.success());
+    assert!(!project.exists("tool.1.html"));
+    let html = project.read("index.html");
+    assert!(html.contains(
+        "<nav class=\"pages\" aria-label=\"Pages\">\n<ul>\n\
+         <li><a href=\"#tool.1\">tool(1)</a></li>\n\
+         <li><a href=\"#tool.conf.5\">tool.conf(5)</a></li>\n"
+    ));
+    assert!(html.contains("<p><a href=\"#tool.conf.5\"><strong>tool.conf</strong>(5)</a></p>\n"));
+    assert!(html.contains("<p><a href=\"#tool.1\"><strong>tool</strong>(1)</a></p>\n"));
+
+    let output = project.mdman(&[
+        "--format",
+        "html",
+        "--single-file",
+        "--output",
+        "manual.html",
+        "tool.md",
+        "conf.md",
+    ]);
+    assert!(output.status.success());
+    assert_eq!(project.read("manual.html"), html);
+
+    // The pages share one output, which fails once.
+    let output = project.mdman(&[
+        "--format",
+        "html",
+        "--single-file",
+        "--output",
+        "conf.md/manual.html",
+        "tool.md",
+        "conf.md",
+    ]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(stderr.matches("mdman: Could not write").count(), 1);
+    assert!(stderr.ends_with("mdman: converted 2 pages\n"));
+
+    let output = project.mdman(&["--single-file", "tool.md"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "mdman: --single-file works with --format html only\n"
+    );
+}
+
+#[test]
+fn test_format_html_template_and_css() {
+    let project = Project::new(
+        "html-template",
+        &[
+            ("tool.md", TOOL_PAGE),
+            (
+                "site.html",
+                "<html lang=\"{{lang}}\">\n<head>\n{{head}}<title>{{title}} | Docs</title>\n\
+                 </head>\n<body class=\"docs\">\n{{content}}</body>\n</html>\n",
+            ),
+            ("bare.html", "<html></html>\n"),
+        ],
+    );
+    let output = project.mdman(&[
+        "--format",
+        "html",
+        "--html-template",
+        "site.html",
+        "--css",
+        "docs.css",
+        "tool.md",
+    ]);
+
+    assert!(output.status.success());
+    let html = project.read("tool.1.html");
+    assert!(html.starts_with("<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n"));
+    assert!(
+        html.contains(
+            "<link rel=\"stylesheet\" href=\"docs.css\">\n<title>tool(1) | Docs</title>\n"
+        )
+    );
+    assert!(html.contains("<body class=\"docs\">\n<a class=\"skip-link\""));
+
+    let output = project.mdman(&[
+        "--format",
+        "html",
+        "--html-template",
+        "bare.html",
+        "tool.md",
+    ]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "mdman: bare.html has no {{content}}\n"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_postprocess() {
+    let stdout = run_mdman(&["--postprocess", "tr a-z A-Z"], "# NAME\n\ntool\n");
+    assert_eq!(stdout, ".SH NAME\n.PD\n.PP\nTOOL\n");
+}
+
+#[test]
+fn test_strikethrough() {
+    let stdout = run_mdman(&[], "# NAME\n\n~~--old~~ see ~/.config\n");
+    assert_eq!(
+        stdout,
+        ".SH NAME\n.PD\n.PP\n\\fI\\-\\-old\\fP (deprecated) see \\(ti/\\&.config\n"
+    );
+}
+
+#[test]
+fn test_info_json() {
+    let output = Command::new(env!("CARGO_BIN_EXE_mdman"))
+        .args(["info", "--json", "man/mdman.5.md"])
+        .output()
+        .expect("Failed to run mdman");
+    let stdout = String::from_utf8(output.stdout).expect("Invalid UTF-8");
+
+    assert!(output.status.success());
+    assert!(
+        stdout.starts_with("{\n  \"title\": {\n    \"name\": \"mdman\",\n    \"section\": 5,\n")
+    );
+    assert!(stdout.contains("\n  \"output\": \"mdman.5\",\n  \"sections\": [\n    \"NAME\",\n"));
+    assert!(stdout.contains("{\n      \"name\": \"mdman\",\n      \"section\": \"1\"\n    }"));
+}
+
+#[test]
+fn test_date_from_mtime() {
+    let project = Project::new("mtime", &[]);
+    let convert = |markdown: &str| {
+        project.write("tool.md", markdown);
+        // Noon UTC, so the local day is the same nearly everywhere.
+        let noon = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_580_731_200);
+        std::fs::File::options()
+            .write(true)
+            .open(project.path("tool.md"))
+            .unwrap()
+            .set_modified(noon)
+            .unwrap();
+        let output = project.mdman(&["--stdout", "--date-from", "mtime", "tool.md"]);
+        String::from_utf8(output.stdout).expect("Invalid UTF-8")
+    };
+    let from_mtime = convert(&TOOL_PAGE.replace("date: 2025-01-01\n", ""));
+    let from_frontmatter = convert(TOOL_PAGE);
+
+    assert!(from_mtime.starts_with(".TH \"TOOL\" \"1\" \"2020-02-03\"\n"));
+    assert!(from_frontmatter.starts_with(".TH \"TOOL\" \"1\" \"2025-01-01\"\n"));
+}
+
+#[test]
+fn test_options_without_thematic_breaks() {
+    let stdout = run_mdman(&[], "# OPTIONS\n\n- **-v**, **--verbose**\n  Be verbose.\n");
+    assert_eq!(
+        stdout,
+        ".SH OPTIONS\n.TP\n\\fB\\-v\\fP, \\fB\\-\\-verbose\\fP\nBe verbose\\&.\n\n"
+    );
+}
+
+#[test]
+fn test_autolinks() {
+    let stdout = run_mdman(
+        &[],
+        "# AUTHORS\n\nJane <jane@example.com>, see www.example.com.\n",
+    );
+    assert_eq!(
+        stdout,
+        ".SH AUTHORS\n.PD\n.PP\nJane\n.MT jane@example.com\n.ME ,\nsee\n\
+         .UR http://www.example.com\n.UE \\&.\n"
+    );
+}
+
+#[test]
+fn test_malformed_input_is_an_error() {
+    let project = Project::new(
+        "malformed",
+        &[("tool.md", "---\nname: [tool\n---\n\n# NAME\n\ntool\n")],
+    );
+    let output = project.mdman(&["--stdout", "tool.md"]);
+
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).expect("Invalid UTF-8");
+    assert_eq!(
+        stderr,
+        "mdman: Could not parse the frontmatter in tool.md at line 2. \
+         Error: name: invalid type: sequence, expected a string\n"
+    );
+}
+
+#[test]
+fn test_tbl_preprocessor_line() {
+    let markdown = "# FILES\n\n| a | b |\n|---|---|\n| 1 | 2 |\n";
+    assert!(run_mdman(&[], markdown).starts_with("'\\\" t\n.SH FILES\n"));
+    assert!(run_mdman(&["--no-tbl-line"], markdown).starts_with(".SH FILES\n"));
+}
+
+#[test]
+fn test_toc() {
+    let stdout = run_mdman(
+        &["--toc"],
+        "# NAME\n\ntool - x\n\n# DESCRIPTION\n\n## Files\n\ntext\n",
+    );
+    assert_eq!(
+        stdout,
+        ".SH NAME\n.PD\n.PP\ntool \\- x\n.SH CONTENTS\n.nf\nNAME\nDESCRIPTION\n    Files\n.fi\n\
+         .SH DESCRIPTION\n.SS Files\n.PD\n.PP\ntext\n"
+    );
+}
+
+#[test]
+fn test_lint_project() {
+    let page = |name: &str, other: &str| {
+        format!(
+            "---\nname: {name}\nsection: 1\n---\n\n# NAME\n\n{name} - x\n\n# SEE ALSO\n\n{other}(1)\n"
+        )
+    };
+    let project = Project::new(
+        "lint",
+        &[
+            ("a.md", &page("a", "b")),
+            ("b.md", &page("b", "a")),
+            ("c.md", &page("c", "a")),
+            ("d.md", "# NAME\n\nd\n"),
+        ],
+    );
+    let passing = project.mdman(&["lint", "a.md", "b.md"]);
+    let failing = project.mdman(&["lint", "a.md", "b.md", "c.md"]);
+    let json = project.mdman(&["lint", "--json", "d.md"]);
+
+    assert!(passing.status.success());
+    assert!(passing.stdout.is_empty());
+    assert!(!failing.status.success());
+    let stdout = String::from_utf8(failing.stdout).expect("Invalid UTF-8");
+    assert!(
+        stdout.ends_with(
+            "c.md: orphan: c(1) is not listed in the SEE ALSO section of any other page\n"
+        )
+    );
+    assert!(!json.status.success());
+    let stdout = String::from_utf8(json.stdout).expect("Invalid UTF-8");
+    assert!(stdout.starts_with("[\n  {\n    \"file\": "));
+    assert!(stdout.contains("\"line\": 1,\n    \"check\": \"name-section\","));
+}
+
+#[test]
+fn test_build_from_manifest() {
+    let project = Project::new(
+        "build",
+        &[
+            ("doc/tool.md", TOOL_PAGE),
+            (
+                "mdman.toml",
+                "output-dir = \"out\"\nlayout = \"sections\"\nformats = [\"man\", \"org\"]\n\n\
+                 [[page]]\nsource = \"doc/tool.md\"\nsection = 8\n",
+            ),
+        ],
+    );
+    let output = project.mdman(&["build", "--manifest", "mdman.toml"]);
+
+    assert!(output.status.success());
+    assert!(
+        project
+            .read("out/man8/tool.8")
+            .starts_with(".TH \"TOOL\" \"8\" \"2025-01-01\"")
+    );
+    assert!(project.read("out/tool.org").contains(":MAN_SECTION: 8\n"));
+}
+
+#[test]
+fn test_build_transforms_pages() {
+    let page = |name: &str, term: &str| {
+        format!(
+            "---\nname: {name}\nsection: 1\n---\n\n# NAME\n\n{name} - x\n\n\
+             # DESCRIPTION\n\n## Files\n\n<!-- mdman:gloss {term}: A {term}. -->\n"
+        )
+    };
+    let project = Project::new(
+        "build-transforms",
+        &[
+            ("a.md", &page("a", "apple")),
+            ("b.md", &page("b", "banana")),
+            (
+                "mdman.toml",
+                "[[page]]\nsource = \"a.md\"\n\n[[page]]\nsource = \"b.md\"\n",
+            ),
+        ],
+    );
+    let output = project.mdman(&["--toc", "--numbered-headings", "build"]);
+
+    assert!(output.status.success());
+    let a = project.read("a.1");
+    assert!(a.contains(".SH CONTENTS\n"));
+    assert!(a.contains(".SS 2\\&.1 Files\n"));
+    // The pages share their glossaries.
+    assert!(a.contains("apple") && a.contains("banana"));
+    assert!(project.read("b.1").contains("apple"));
+}
+
+#[test]
+fn test_build_install_snippet() {
+    let project = Project::new(
+        "snippet",
+        &[
+            ("doc/tool.md", TOOL_PAGE),
+            ("doc/conf.md", "---\nname: tool.conf\nsection: 5\n---\n"),
+            (
+                "mdman.toml",
+                "output-dir = \"man\"\n\n[[page]]\nsource = \"doc/tool.md\"\n\n\
+                 [[page]]\nsource = \"doc/conf.md\"\nname = \"tool.conf\"\n",
+            ),
+        ],
+    );
+    let output = project.mdman(&[
+        "build",
+        "--emit-install-snippet",
+        "homebrew",
+        "--manifest",
+        "mdman.toml",
+    ]);
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "man1.install \"man/tool.1\"\nman5.install \"man/tool.conf.5\"\n"
+    );
+    assert!(!project.exists("man/tool.1"));
+}
+
+#[test]
+fn test_dropped_content_warnings() {
+    let stderr = |args: &[&str]| {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_mdman"))
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("Failed to run mdman");
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(b"# NAME\n\n![a](a.png) ![b](b.png)\n\ntool <br>\n\n![c](c.png)\n")
+            .unwrap();
+        String::from_utf8(child.wait_with_output().unwrap().stderr).unwrap()
+    };
+    assert_eq!(
+        stderr(&[]),
+        "mdman: warning: <stdin>: image left out 3 times (lines 3, 7)\n\
+         mdman: warning: <stdin>: HTML left out once (line 5)\n"
+    );
+    assert_eq!(stderr(&["--quiet"]), "");
+}
+
+#[test]
+fn test_strict() {
+    let markdown = "# NAME\n\ntool ![logo](logo.png)\n";
+    assert!(run_mdman(&[], markdown).ends_with("\ntool \n"));
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_mdman"))
+        .arg("--strict")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to run mdman");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(markdown.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "mdman: <stdin>:3:6: image is not supported\n"
+    );
+}
+
+#[test]
+fn test_profile() {
+    let markdown = "# Name\n\nRun `tool -x`.\n";
+    assert_eq!(
+        run_mdman(&["--profile", "debian"], markdown),
+        ".SH NAME\n.PD\n.PP\nRun \\f(CRtool \\-x\\fP.\n"
+    );
+    assert!(run_mdman(&["--profile", "bsd"], markdown).contains("\\f(CWtool \\-x\\fP"));
+    let overridden = run_mdman(
+        &["--profile", "bsd", "--target-formatter", "groff"],
+        markdown,
+    );
+    assert!(overridden.contains("\\f(CRtool"));
+}
+
+#[test]
+fn test_check_for_drift() {
+    let project = Project::new("check", &[("tool.md", TOOL_PAGE)]);
+    let missing = project.mdman(&["--check", "tool.md"]);
+    let created = project.exists("tool.1");
+    project.mdman(&["tool.md"]);
+    let in_sync = project.mdman(&["--check", "tool.md"]);
+    project.write("tool.md", TOOL_PAGE.replace("one thing", "two things"));
+    let drifted = project.mdman(&["--check", "tool.md"]);
+
+    assert!(!missing.status.success());
+    assert!(!created);
+    assert!(in_sync.status.success(), "{:?}", in_sync);
+    assert!(!drifted.status.success());
+    assert!(
+        String::from_utf8_lossy(&drifted.stderr)
+            .ends_with("mdman: tool.1 is out of date, first difference at line 5\n")
+    );
+    assert!(project.read("tool.1").contains("tool \\- does one thing"));
+}
+
+#[test]
+fn test_multiple_inputs() {
+    let page = |name: &str, term: &str| {
+        format!(
+            "---\nname: {name}\nsection: 1\n---\n\n# NAME\n\n{name} - x\n\n\
+             <!-- mdman:gloss {term}: A {term}. -->\n"
+        )
+    };
+    let project = Project::new(
+        "batch",
+        &[
+            ("a.md", &page("a", "apple")),
+            ("b.md", &page("b", "banana")),
+            ("c.md", "---\nsection: one\n---\n"),
+        ],
+    );
+    // Quoted, so mdman expands the pattern.
+    let output = project.mdman(&["*.md"]);
+    let single_only = project.mdman(&["a.md", "b.md", "--stdout"]);
+    let no_match = project.mdman(&["*.markdown"]);
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.ends_with("mdman: converted 2 of 3 pages, 1 failed: c.md\n"));
+    let a = project.read("a.1");
+    // The pages share their glossaries.
+    assert!(a.contains("apple") && a.contains("banana"));
+    assert!(project.read("b.1").contains("apple"));
+    assert_eq!(
+        String::from_utf8_lossy(&single_only.stderr),
+        "mdman: --stdout works with one input file only\n"
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&no_match.stderr),
+        "mdman: No files match *.markdown\n"
+    );
+}
+
+#[test]
+fn test_output_dir() {
+    let project = Project::new(
+        "outdir",
+        &[
+            ("tool.md", TOOL_PAGE),
+            ("conf.md", "---\nname: tool.conf\nsection: 5\n---\n"),
+        ],
+    );
+    let flat = project.mdman(&["--output-dir", "target/man", "tool.md"]);
+    let sections = project.mdman(&[
+        "--output-dir",
+        "share/man",
+        "--layout",
+        "sections",
+        "tool.md",
+        "conf.md",
+    ]);
+    let org = project.mdman(&[
+        "--output-dir",
+        "doc",
+        "--layout",
+        "sections",
+        "--format",
+        "org",
+        "tool.md",
+    ]);
+
+    assert!(flat.status.success() && sections.status.success() && org.status.success());
+    assert_eq!(
+        [
+            "target/man/tool.1",
+            "share/man/man1/tool.1",
+            "share/man/man5/conf.5",
+            "doc/tool.org",
+        ]
+        .map(|path| project.exists(path)),
+        [true; 4]
+    );
+}
+
+#[test]
+fn test_rendered_diff() {
+    let page = |text: &str| format!("# NAME\n\ntool - x\n\n# DESCRIPTION\n\n{}\n", text);
+    let project = Project::new(
+        "diff",
+        &[
+            ("old.md", &page("Does **one** thing.")),
+            ("new.md", &page("Does **two** things.")),
+        ],
+    );
+    let diff = |old: &str, new: &str| project.mdman(&["diff", old, new, "--rendered"]);
+    let changed = diff("old.md", "new.md");
+    let same = diff("old.md", "old.md");
+    let missing = diff("old.md", "gone.md");
+
+    assert_eq!(changed.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&changed.stdout),
+        "--- old.md\n+++ new.md\n@@ DESCRIPTION @@\n-       Does one thing.\n+       Does two things.\n"
+    );
+    assert_eq!(same.status.code(), Some(0));
+    assert!(same.stdout.is_empty());
+    assert_eq!(missing.status.code(), Some(2));
+}
+
+#[test]
+fn test_recursive() {
+    let project = Project::new(
+        "recursive",
+        &[
+            ("docs/tool.md", TOOL_PAGE),
+            (
+                "docs/conf/conf.md",
+                "---\nname: tool.conf\nsection: 5\n---\n",
+            ),
+            ("docs/conf/snippets/files.md", "*~/.toolrc*"),
+        ],
+    );
+    let output = project.mdman(&["--recursive", "docs", "--output-dir", "man"]);
+    let directory = project.mdman(&["docs"]);
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "mdman: converted 2 pages\n"
+    );
+    assert_eq!(
+        [
+            project.exists("man/man1/tool.1"),
+            project.exists("man/man5/conf.5"),
+            project.exists("man/files.1") || project.exists("man/man1/files.1"),
+        ],
+        [true, true, false]
+    );
+    assert!(!directory.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&directory.stderr),
+        "mdman: docs is a directory; use --recursive to convert the pages below it\n"
+    );
+}
+
+#[test]
+fn test_parallel_batch() {
+    let project = Project::new("jobs", &[]);
+    let mut files = Vec::new();
+    for i in 0..12 {
+        let file = format!("page{:02}.md", i);
+        project.write(
+            &file,
+            format!(
+                "---\nname: page{i}\nsection: 1\n---\n# NAME\n\npage{i} - x\n\n<video>{i}</video>\n"
+            ),
+        );
+        files.push(file);
+    }
+    let mdman = |jobs: &str| {
+        project
+            .command()
+            .args(["--jobs", jobs, "--verbose"])
+            .args(&files)
+            .output()
+            .expect("Failed to run mdman")
+    };
+    let serial = mdman("1");
+    let parallel = mdman("4");
+
+    let warnings = |output: &Output| {
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        let (warnings, summary) = stderr.trim_end().rsplit_once('\n').unwrap();
+        assert!(summary.starts_with("mdman: converted 12 pages in "));
+        assert!(summary.ends_with('s'));
+        warnings.to_string()
+    };
+    let expected = (0..12)
+        .map(|i| {
+            format!(
+                "mdman: warning: page{:02}.md: HTML left out 2 times (line 9)\n",
+                i
+            )
+        })
+        .collect::<String>();
+    assert!(serial.status.success() && parallel.status.success());
+    assert_eq!(warnings(&serial) + "\n", expected);
+    assert_eq!(warnings(&parallel), warnings(&serial));
+}
+
+#[test]
+fn test_extract_examples() {
+    let project = Project::new(
+        "examples",
+        &[
+            (
+                "tool.md",
+                "---\nname: tool\nsection: 1\n---\n\n# EXAMPLES\n\nGreet:\n\n\
+                 ```console\n$ echo hello\nhello\n```\n\nBreak:\n\n```sh\nfalse\necho after\n```\n",
+            ),
+            ("none.md", "# EXAMPLES\n\n```toml\nkey = 1\n```\n"),
+        ],
+    );
+    let commands = project.mdman(&["extract-examples", "tool.md"]);
+    let script = project.mdman(&["extract-examples", "--shell", "tool.md"]);
+    let none = project.mdman(&["extract-examples", "none.md"]);
+    project.write("examples.sh", &script.stdout);
+    let run = Command::new("sh")
+        .arg(project.path("examples.sh"))
+        .output()
+        .expect("Failed to run sh");
+
+    assert_eq!(
+        String::from_utf8_lossy(&commands.stdout),
+        "# Greet\necho hello\n\n# Break\nfalse\necho after\n"
+    );
+    assert!(
+        script
+            .stdout
+            .starts_with(b"#!/bin/sh\n# The examples of tool(1)")
+    );
+    assert_eq!(run.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&run.stdout),
+        "1..2\nok 1 - Greet\nnot ok 2 - Break\n"
+    );
+    assert_eq!(String::from_utf8_lossy(&run.stderr), "hello\n");
+    assert!(!none.status.success());
+}
+
+#[test]
+fn test_watch() {
+    let project = Project::new("watch", &[("tool.md", TOOL_PAGE)]);
+    let mut child = project
+        .command()
+        .args(["--watch", "tool.md"])
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to run mdman");
+    let wait_for = |text: &str| {
+        (0..100).any(|_| {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            project.read("tool.1").contains(text)
+        })
+    };
+    let first = wait_for("one thing");
+    project.write("tool.md", TOOL_PAGE.replace("one thing", "two things"));
+    let again = wait_for("two things");
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    assert!(first && again);
+}
+
+#[test]
+fn test_watch_recursive() {
+    let project = Project::new("watch-recursive", &[("docs/tool.md", TOOL_PAGE)]);
+    let mut child = project
+        .command()
+        .args(["--watch", "--recursive", "docs", "--output-dir", "man"])
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to run mdman");
+    let wait_for = |file: &str| {
+        (0..100).any(|_| {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            project.exists(file)
+        })
+    };
+    let first = wait_for("man/man1/tool.1");
+    project.write(
+        "docs/sub/other.md",
+        TOOL_PAGE.replace("name: tool", "name: other"),
+    );
+    let added = wait_for("man/man1/other.1");
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    assert!(first && added);
+}
+
+#[test]
+fn test_gzip() {
+    let project = Project::new("gzip", &[("tool.md", TOOL_PAGE)]);
+    let gunzip = |file: &str| {
+        let output = Command::new("gzip")
+            .arg("-dc")
+            .arg(project.path(file))
+            .output()
+            .expect("Failed to run gzip");
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+    let roff =
+        String::from_utf8_lossy(&project.mdman(&["-z", "--stdout", "tool.md"]).stdout).to_string();
+    let auto = project.mdman(&["-z", "tool.md"]).status.success();
+    let named = project
+        .mdman(&["--output", "page.1.gz", "tool.md"])
+        .status
+        .success();
+
+    assert!(roff.starts_with(".TH \"TOOL\" \"1\""));
+    assert!(auto && named && !project.exists("tool.1"));
+    assert_eq!(gunzip("tool.1.gz"), roff);
+    assert_eq!(gunzip("page.1.gz"), roff);
+}
+
+#[test]
+fn test_install() {
+    let project = Project::new(
+        "install",
+        &[
+            ("tool.md", &TOOL_PAGE.replace("section: 1", "section: 8")),
+            ("mdman.toml", "prefix = \"root/usr\"\n"),
+        ],
+    );
+    let install = |flags: &[&str]| {
+        project
+            .command()
+            .args(["install", "--no-index", "tool.md"])
+            .args(flags)
+            .status()
+            .expect("Failed to run mdman")
+            .success()
+    };
+    let man_dir = "root/usr/share/man/man8";
+    let plain = install(&[]);
+    let roff = project.read(&format!("{man_dir}/tool.8"));
+    let gzipped = install(&["--gzip"]);
+    let unzipped = Command::new("gzip")
+        .arg("-dc")
+        .arg(project.path(&format!("{man_dir}/tool.8.gz")))
+        .output()
+        .expect("Failed to run gzip");
+
+    assert!(plain && gzipped);
+    assert!(!project.exists(&format!("{man_dir}/tool.8")));
+    assert!(roff.starts_with(".TH \"TOOL\" \"8\""));
+    assert_eq!(String::from_utf8_lossy(&unzipped.stdout), roff);
+}
+
+#[test]
+fn test_watch_pager() {
+    let project = Project::new("watch-pager", &[("tool.md", TOOL_PAGE)]);
+    // Without a terminal, less prints the page and quits, and so does mdman.
+    let output = project.mdman(&["--watch", "--pager", "tool.md"]);
+
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("tool - does one thing"));
+    assert!(!project.exists("tool.1"));
+}
+
+#[test]
+fn test_lint_check_urls() {
+    use std::io::{BufRead, BufReader, Write};
+
+    // A server that knows /ok and nothing else.
+    let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = server.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        for stream in server.incoming().flatten() {
+            let mut request_line = String::new();
+            let mut reader = BufReader::new(&stream);
+            reader.read_line(&mut request_line).unwrap_or_default();
+            let mut header = String::new();
+            while reader.read_line(&mut header).is_ok_and(|n| n > 2) {
+                header.clear();
+            }
+            let status = match request_line.split_whitespace().nth(1) {
+                Some("/ok") => "200 OK",
+                _ => "404 Not Found",
+            };
+            let response =
+                format!("HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+            _ = (&stream).write_all(response.as_bytes());
+        }
+    });
+    let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let closed_port = closed.local_addr().unwrap().port();
+    drop(closed);
+
+    let project = Project::new("urls", &[]);
+    project.write(
+        "tool.md",
+        format!(
+            "---\nname: tool\nsection: 1\n---\n\n# NAME\n\ntool - x\n\n# DESCRIPTION\n\n\
+             See <http://127.0.0.1:{port}/ok> and [the old docs](http://127.0.0.1:{port}/gone).\n\n\
+             Also <http://127.0.0.1:{closed_port}/>, <http://127.0.0.1:{port}/internal> \
+             and <mailto:x@example.com>.\n"
+        ),
+    );
+    let lint = || {
+        project
+            .command()
+            .args([
+                "lint",
+                "tool.md",
+                "--check-urls",
+                "--url-cache",
+                "urls.cache",
+            ])
+            .args(["--allow-url", &format!("http://127.0.0.1:{port}/internal")])
+            .output()
+            .expect("Failed to run mdman")
+    };
+    let output = lint();
+    let cache = project.read("urls.cache");
+
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2, "{}", stdout);
+    assert_eq!(
+        lines[0],
+        format!("tool.md:12: dead-url: http://127.0.0.1:{port}/gone returns 404")
+    );
+    assert!(lines[1].starts_with(&format!(
+        "tool.md:14: dead-url: http://127.0.0.1:{closed_port}/ cannot be reached: "
+    )));
+    assert!(cache.starts_with(&format!("http://127.0.0.1:{port}/ok\t")));
+    assert_eq!(cache.lines().count(), 1);
+}